@@ -0,0 +1,22 @@
+#[path = "codegen/rust.rs"]
+mod rust;
+#[path = "codegen/schema.rs"]
+mod schema;
+#[path = "codegen/typescript.rs"]
+mod typescript;
+
+use std::fs;
+
+static SCHEMA_PATH: &str = "protocol.json";
+static RUST_PATH: &str = "src/types/protocol.rs";
+static TYPESCRIPT_PATH: &str = "../../frontend/src/lib/protocol.ts";
+
+fn main() {
+    println!("cargo:rerun-if-changed={SCHEMA_PATH}");
+    println!("cargo:rerun-if-changed=codegen");
+
+    let schema = schema::Schema::load(SCHEMA_PATH);
+
+    fs::write(RUST_PATH, rust::generate(&schema)).unwrap();
+    fs::write(TYPESCRIPT_PATH, typescript::generate(&schema)).unwrap();
+}
@@ -0,0 +1,452 @@
+use super::schema::*;
+use proc_macro2::{Literal, TokenStream};
+use quote::{format_ident, quote};
+
+/// Doc attributes with a leading space, so the generated comments read `/// Text`.
+fn doc_attributes(doc: Option<&String>) -> Vec<TokenStream> {
+    doc.iter()
+        .flat_map(|doc| doc.lines())
+        .map(|line| {
+            let line = format!(" {line}");
+            quote! { #[doc = #line] }
+        })
+        .collect()
+}
+
+fn parse_type(source: &str) -> syn::Type {
+    syn::parse_str(source).unwrap()
+}
+
+fn fields(fields: &[FieldSchema], public: bool) -> Vec<TokenStream> {
+    fields
+        .iter()
+        .map(|field| {
+            let docs = doc_attributes(field.doc.as_ref());
+            let ident = format_ident!("{}", field.name);
+            let r#type = parse_type(&field.r#type);
+            if public {
+                quote! { #(#docs)* pub #ident: #r#type, }
+            } else {
+                quote! { #(#docs)* #ident: #r#type, }
+            }
+        })
+        .collect()
+}
+
+fn pattern(variant: &VariantSchema) -> TokenStream {
+    let ident = format_ident!("{}", variant.name);
+    if variant.fields.is_empty() {
+        quote! { Self::#ident }
+    } else {
+        quote! { Self::#ident { .. } }
+    }
+}
+
+fn generate_type(schema: &TypeSchema) -> TokenStream {
+    let docs = doc_attributes(schema.doc.as_ref());
+    let ident = format_ident!("{}", schema.name);
+    let derive = schema.derive.iter().map(|derive| format_ident!("{derive}"));
+    let attributes = quote! {
+        #(#docs)*
+        #[derive(bincode::Encode, bincode::Decode, Clone, Debug, PartialEq, #(#derive),*)]
+        #[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+    };
+
+    if !schema.is_enum() {
+        let fields = fields(&schema.fields, true);
+        return quote! {
+            #attributes
+            pub struct #ident {
+                #(#fields)*
+            }
+        };
+    }
+
+    let variants = schema.variants.iter().map(|variant| {
+        let docs = doc_attributes(variant.doc.as_ref());
+        let ident = format_ident!("{}", variant.name);
+        if variant.fields.is_empty() {
+            quote! { #(#docs)* #ident, }
+        } else {
+            let fields = fields(&variant.fields, false);
+            quote! { #(#docs)* #ident { #(#fields)* }, }
+        }
+    });
+
+    // Variants that carry a content type are formats the server can encode.
+    let formats = if schema.variants.iter().any(|variant| variant.mime.is_some()) {
+        let names = schema.variants.iter().map(|variant| {
+            variant
+                .extension
+                .as_ref()
+                .expect("Format without extension")
+        });
+        let mime_arms = schema.variants.iter().map(|variant| {
+            let pattern = pattern(variant);
+            let mime = variant.mime.as_ref().expect("Format without content type");
+            quote! { #pattern => #mime, }
+        });
+        quote! {
+            impl #ident {
+                pub fn names() -> Vec<String> {
+                    vec![#(#names.into()),*]
+                }
+
+                pub fn content_type(&self) -> &'static str {
+                    match self {
+                        #(#mime_arms)*
+                    }
+                }
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    quote! {
+        #attributes
+        pub enum #ident {
+            #(#variants)*
+        }
+
+        #formats
+    }
+}
+
+/// Enum payloads are sent with the index of their variant as a subtag.
+fn generate_subtags(schema: &Schema, prefix: &str, messages: &[MessageSchema]) -> TokenStream {
+    let impls = messages.iter().filter_map(|message| {
+        let payload = schema.get(message.payload.as_ref()?);
+        if !payload.is_enum() {
+            return None;
+        }
+
+        let ident = format_ident!("{}", payload.name);
+        let arms = payload.variants.iter().map(|variant| {
+            let pattern = pattern(variant);
+            let subtag = format_ident!("{}", subtag_name(prefix, message, variant));
+            quote! { #pattern => #subtag, }
+        });
+
+        Some(quote! {
+            impl #ident {
+                pub fn subtag(&self) -> u8 {
+                    match self {
+                        #(#arms)*
+                    }
+                }
+            }
+        })
+    });
+
+    quote! { #(#impls)* }
+}
+
+fn is_enum_payload(schema: &Schema, message: &MessageSchema) -> bool {
+    message
+        .payload
+        .as_ref()
+        .is_some_and(|payload| schema.get(payload).is_enum())
+}
+
+/// Decodes `payload` into the message, checking the subtag of enum payloads.
+fn decode_arm(
+    schema: &Schema,
+    prefix: &str,
+    enum_name: &str,
+    message: &MessageSchema,
+) -> TokenStream {
+    let tag = format_ident!("{}", tag_name(prefix, message));
+    let msg_enum = format_ident!("{enum_name}");
+    let ident = format_ident!("{}", message.name);
+    match &message.payload {
+        Some(payload) if is_enum_payload(schema, message) => {
+            let payload = parse_type(payload);
+            quote! {
+                #tag => {
+                    let (subtag, payload) = payload
+                        .split_first()
+                        .ok_or(DecodeError::Other("Missing message subtag"))?;
+                    let msg = decode::<#payload>(payload)?;
+                    if msg.subtag() != *subtag {
+                        return Err(DecodeError::Other("Invalid message subtag."));
+                    }
+                    #msg_enum::#ident(msg)
+                }
+            }
+        }
+        Some(payload) => {
+            let payload = parse_type(payload);
+            quote! { #tag => #msg_enum::#ident(decode::<#payload>(payload)?), }
+        }
+        None => quote! { #tag => #msg_enum::#ident, },
+    }
+}
+
+fn encode_arm(
+    schema: &Schema,
+    prefix: &str,
+    enum_name: &str,
+    message: &MessageSchema,
+) -> TokenStream {
+    let tag = format_ident!("{}", tag_name(prefix, message));
+    let msg_enum = format_ident!("{enum_name}");
+    let ident = format_ident!("{}", message.name);
+    if is_enum_payload(schema, message) {
+        quote! { #msg_enum::#ident(msg) => encode((#tag, msg.subtag(), msg))?, }
+    } else if message.payload.is_some() {
+        quote! { #msg_enum::#ident(msg) => encode((#tag, msg))?, }
+    } else {
+        quote! { #msg_enum::#ident => encode(#tag)?, }
+    }
+}
+
+/// One property per message, checking that decoding and re-encoding gives back its bytes.
+fn generate_tests(schema: &Schema) -> TokenStream {
+    let tests = |prefix: &str, enum_name: &str, messages: &[MessageSchema]| {
+        let msg_enum = format_ident!("{enum_name}");
+        messages
+            .iter()
+            .map(|message| {
+                let name = format_ident!(
+                    "{}_{}_round_trips",
+                    if prefix == "C" { "client" } else { "server" },
+                    screaming_snake_case(&message.name).to_ascii_lowercase()
+                );
+                let ident = format_ident!("{}", message.name);
+                let body = if message.payload.is_some() {
+                    quote! { assert_payloads_round_trip(#msg_enum::#ident); }
+                } else {
+                    quote! { assert_round_trip(#msg_enum::#ident); }
+                };
+                quote! {
+                    #[test]
+                    fn #name() {
+                        #body
+                    }
+                }
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let client_tests = tests("C", "ClientMsg", &schema.client);
+    let server_tests = tests("S", "ServerMsg", &schema.server);
+
+    quote! {
+        #[cfg(test)]
+        mod tests {
+            use super::*;
+            use proptest::{prelude::*, test_runner::TestRunner};
+
+            trait RoundTrip: Sized {
+                fn to_bytes(self) -> Vec<u8>;
+                fn from_bytes(bytes: Vec<u8>) -> Self;
+            }
+
+            impl RoundTrip for ClientMsg {
+                fn to_bytes(self) -> Vec<u8> {
+                    self.encode().unwrap()
+                }
+
+                fn from_bytes(bytes: Vec<u8>) -> Self {
+                    Self::try_from(Bytes::from(bytes)).unwrap()
+                }
+            }
+
+            impl RoundTrip for ServerMsg {
+                fn to_bytes(self) -> Vec<u8> {
+                    let msg: Message = self.try_into().unwrap();
+                    msg.into_data().to_vec()
+                }
+
+                fn from_bytes(bytes: Vec<u8>) -> Self {
+                    Self::decode(&bytes).unwrap()
+                }
+            }
+
+            // Bytes are compared rather than messages, as payloads may hold NaN.
+            fn assert_round_trip<M: RoundTrip>(msg: M) {
+                let bytes = msg.to_bytes();
+                assert_eq!(M::from_bytes(bytes.clone()).to_bytes(), bytes);
+            }
+
+            /// Round-trips the message with payloads generated by proptest.
+            fn assert_payloads_round_trip<P: Arbitrary, M: RoundTrip>(message: fn(P) -> M) {
+                TestRunner::default()
+                    .run(&any::<P>(), |payload| {
+                        let bytes = message(payload).to_bytes();
+                        prop_assert_eq!(M::from_bytes(bytes.clone()).to_bytes(), bytes);
+                        Ok(())
+                    })
+                    .unwrap();
+            }
+
+            #(#client_tests)*
+
+            #(#server_tests)*
+        }
+    }
+}
+
+pub fn generate(schema: &Schema) -> String {
+    let version = Literal::u32_unsuffixed(schema.version);
+    let min_version = Literal::u32_unsuffixed(schema.min_version);
+
+    let constants = |prefix: &str, messages: &[MessageSchema]| {
+        let mut constants = Vec::new();
+        for message in messages {
+            let ident = format_ident!("{}", tag_name(prefix, message));
+            let tag = Literal::u8_unsuffixed(message.tag);
+            constants.push(quote! { pub const #ident: u8 = #tag; });
+
+            let Some(payload) = &message.payload else {
+                continue;
+            };
+            for (subtag, variant) in schema.get(payload).variants.iter().enumerate() {
+                let ident = format_ident!("{}", subtag_name(prefix, message, variant));
+                let subtag = Literal::usize_unsuffixed(subtag);
+                constants.push(quote! { pub const #ident: u8 = #subtag; });
+            }
+        }
+        constants
+    };
+
+    let variants = |messages: &[MessageSchema]| {
+        messages
+            .iter()
+            .map(|message| {
+                let docs = doc_attributes(message.doc.as_ref());
+                let ident = format_ident!("{}", message.name);
+                match &message.payload {
+                    Some(payload) => {
+                        let payload = parse_type(payload);
+                        quote! { #(#docs)* #ident(#payload), }
+                    }
+                    None => quote! { #(#docs)* #ident, },
+                }
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let client_constants = constants("C", &schema.client);
+    let server_constants = constants("S", &schema.server);
+    let client_variants = variants(&schema.client);
+    let server_variants = variants(&schema.server);
+    let types = schema.types.iter().map(generate_type);
+    let client_subtags = generate_subtags(schema, "C", &schema.client);
+    let server_subtags = generate_subtags(schema, "S", &schema.server);
+
+    let client_decode_arms = schema
+        .client
+        .iter()
+        .map(|message| decode_arm(schema, "C", "ClientMsg", message));
+    let client_encode_arms = schema
+        .client
+        .iter()
+        .map(|message| encode_arm(schema, "C", "ClientMsg", message));
+    let server_decode_arms = schema
+        .server
+        .iter()
+        .map(|message| decode_arm(schema, "S", "ServerMsg", message));
+    let server_encode_arms = schema
+        .server
+        .iter()
+        .map(|message| encode_arm(schema, "S", "ServerMsg", message));
+
+    let tests = generate_tests(schema);
+
+    let code = quote! {
+        /// Auto-generated file from protocol.json. Any changes will be overwritten.
+        use crate::types::messages::{decode, encode};
+        use axum::{body::Bytes, extract::ws::Message};
+        use bincode::error::{DecodeError, EncodeError};
+
+        /// Protocol version spoken by this server.
+        pub const PROTOCOL_VERSION: u32 = #version;
+        /// Oldest protocol version this server still accepts.
+        pub const MIN_PROTOCOL_VERSION: u32 = #min_version;
+
+        #(#client_constants)*
+
+        #(#server_constants)*
+
+        #[derive(Debug)]
+        #[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+        pub enum ClientMsg {
+            #(#client_variants)*
+        }
+
+        #[derive(Debug)]
+        #[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+        pub enum ServerMsg {
+            #(#server_variants)*
+        }
+
+        #(#types)*
+
+        #client_subtags
+
+        #server_subtags
+
+        impl TryFrom<Bytes> for ClientMsg {
+            type Error = DecodeError;
+
+            fn try_from(msg: Bytes) -> Result<Self, <Self as TryFrom<Bytes>>::Error> {
+                let msg = msg.as_ref();
+
+                // Extract and match on the tag.
+                let (tag, payload) = msg
+                    .split_first()
+                    .ok_or(DecodeError::Other("Missing message tag"))?;
+
+                let result = match *tag {
+                    #(#client_decode_arms)*
+                    _ => return Err(DecodeError::Other("Invalid message.")),
+                };
+
+                Ok(result)
+            }
+        }
+
+        impl TryInto<Message> for ServerMsg {
+            type Error = EncodeError;
+
+            fn try_into(self) -> Result<Message, <Self as TryInto<Message>>::Error> {
+                let payload = match &self {
+                    #(#server_encode_arms)*
+                };
+
+                Ok(Message::Binary(payload.into()))
+            }
+        }
+
+        // The server only receives client messages and sends server messages, so the other
+        // directions exist for the round-trip tests.
+        #[cfg(test)]
+        impl ClientMsg {
+            fn encode(&self) -> Result<Vec<u8>, EncodeError> {
+                Ok(match self {
+                    #(#client_encode_arms)*
+                })
+            }
+        }
+
+        #[cfg(test)]
+        impl ServerMsg {
+            fn decode(msg: &[u8]) -> Result<Self, DecodeError> {
+                let (tag, payload) = msg
+                    .split_first()
+                    .ok_or(DecodeError::Other("Missing message tag"))?;
+
+                Ok(match *tag {
+                    #(#server_decode_arms)*
+                    _ => return Err(DecodeError::Other("Invalid message.")),
+                })
+            }
+        }
+
+        #tests
+    };
+
+    prettyplease::unparse(&syn::parse2(code).unwrap())
+}
@@ -0,0 +1,272 @@
+use serde::Deserialize;
+use std::{collections::HashSet, fs};
+
+#[derive(Deserialize)]
+pub struct Schema {
+    pub version: u32,
+    pub min_version: u32,
+    pub client: Vec<MessageSchema>,
+    pub server: Vec<MessageSchema>,
+    pub types: Vec<TypeSchema>,
+}
+
+#[derive(Deserialize)]
+pub struct MessageSchema {
+    pub tag: u8,
+    pub name: String,
+    pub payload: Option<String>,
+    pub doc: Option<String>,
+}
+
+/// Struct when it has fields, enum when it has variants.
+#[derive(Deserialize)]
+pub struct TypeSchema {
+    pub name: String,
+    pub doc: Option<String>,
+    // Derived on top of those every type gets.
+    #[serde(default)]
+    pub derive: Vec<String>,
+    #[serde(default)]
+    pub fields: Vec<FieldSchema>,
+    #[serde(default)]
+    pub variants: Vec<VariantSchema>,
+}
+
+#[derive(Deserialize)]
+pub struct VariantSchema {
+    pub name: String,
+    pub doc: Option<String>,
+    #[serde(default)]
+    pub fields: Vec<FieldSchema>,
+    // Tile formats carry the extension and content type they are served with.
+    pub extension: Option<String>,
+    pub mime: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct FieldSchema {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub r#type: String,
+    pub doc: Option<String>,
+}
+
+/// Field type, written in Rust syntax in the schema.
+pub enum Type {
+    Bool,
+    U8,
+    U32,
+    U64,
+    F32,
+    F64,
+    String,
+    /// `Vec<u8>`, sent as a single buffer.
+    Bytes,
+    Vec(Box<Type>),
+    Option(Box<Type>),
+    Named(String),
+}
+
+impl Schema {
+    pub fn load(path: &str) -> Self {
+        let schema: Self = serde_json::from_str(
+            &fs::read_to_string(path).expect("Failed to read protocol schema"),
+        )
+        .expect("Failed to parse protocol schema");
+        schema.validate();
+        schema
+    }
+
+    pub fn get(&self, name: &str) -> &TypeSchema {
+        self.types
+            .iter()
+            .find(|schema| schema.name == name)
+            .unwrap_or_else(|| panic!("Type {name} is not defined in the protocol schema"))
+    }
+
+    /// Types used by messages in one direction, including those nested in them.
+    pub fn reachable<'a>(&'a self, messages: &'a [MessageSchema]) -> HashSet<&'a str> {
+        let mut reachable = HashSet::new();
+        let mut pending: Vec<&str> = messages
+            .iter()
+            .filter_map(|message| message.payload.as_deref())
+            .collect();
+
+        while let Some(name) = pending.pop() {
+            if !reachable.insert(name) {
+                continue;
+            }
+            let schema = self.get(name);
+            let fields = schema
+                .fields
+                .iter()
+                .chain(schema.variants.iter().flat_map(|variant| &variant.fields));
+            for field in fields {
+                let mut r#type = Type::parse(&field.r#type);
+                while let Type::Vec(inner) | Type::Option(inner) = r#type {
+                    r#type = *inner;
+                }
+                if let Type::Named(name) = r#type {
+                    pending.push(&self.get(&name).name);
+                }
+            }
+        }
+
+        reachable
+    }
+
+    fn validate(&self) {
+        let mut names = HashSet::new();
+        for schema in &self.types {
+            assert!(
+                names.insert(&schema.name),
+                "Type {} is defined more than once",
+                schema.name
+            );
+            assert!(
+                schema.fields.is_empty() != schema.variants.is_empty(),
+                "Type {} must have either fields or variants",
+                schema.name
+            );
+            let fields = schema
+                .fields
+                .iter()
+                .chain(schema.variants.iter().flat_map(|variant| &variant.fields));
+            for field in fields {
+                Type::parse(&field.r#type).check(self);
+            }
+        }
+
+        let mut payloads = HashSet::new();
+        for messages in [&self.client, &self.server] {
+            let mut tags = HashSet::new();
+            for message in messages {
+                assert!(
+                    tags.insert(message.tag),
+                    "Tag {} is used more than once",
+                    message.tag
+                );
+                if let Some(payload) = &message.payload {
+                    // Subtags are named after the message, so an enum can only be sent by one.
+                    assert!(
+                        self.get(payload).variants.is_empty() || payloads.insert(payload),
+                        "{payload} is the payload of more than one message"
+                    );
+                }
+            }
+        }
+
+        // Subtags are only sent by the server, so the frontend writes no enum payloads.
+        for message in &self.client {
+            if let Some(payload) = &message.payload {
+                assert!(
+                    !self.get(payload).is_enum(),
+                    "Client message {} cannot have an enum payload",
+                    message.name
+                );
+            }
+        }
+    }
+}
+
+impl TypeSchema {
+    pub fn is_enum(&self) -> bool {
+        !self.variants.is_empty()
+    }
+
+    /// Enums without fields are sent as their variant index alone.
+    pub fn is_unit_enum(&self) -> bool {
+        self.is_enum()
+            && self
+                .variants
+                .iter()
+                .all(|variant| variant.fields.is_empty())
+    }
+}
+
+impl Type {
+    pub fn parse(source: &str) -> Self {
+        let source = source.trim();
+        let generic = |outer: &str| {
+            source
+                .strip_prefix(outer)
+                .and_then(|rest| rest.strip_prefix('<'))
+                .and_then(|rest| rest.strip_suffix('>'))
+        };
+
+        match source {
+            "bool" => Type::Bool,
+            "u8" => Type::U8,
+            "u32" => Type::U32,
+            "u64" => Type::U64,
+            "f32" => Type::F32,
+            "f64" => Type::F64,
+            "String" => Type::String,
+            "Vec<u8>" => Type::Bytes,
+            _ => {
+                if let Some(inner) = generic("Vec") {
+                    Type::Vec(Box::new(Type::parse(inner)))
+                } else if let Some(inner) = generic("Option") {
+                    Type::Option(Box::new(Type::parse(inner)))
+                } else {
+                    assert!(
+                        source.chars().all(char::is_alphanumeric),
+                        "Unsupported type {source} in the protocol schema"
+                    );
+                    Type::Named(source.to_string())
+                }
+            }
+        }
+    }
+
+    fn check(&self, schema: &Schema) {
+        match self {
+            Type::Vec(inner) | Type::Option(inner) => inner.check(schema),
+            Type::Named(name) => {
+                schema.get(name);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// `CamelCase` to `CAMEL_CASE`.
+pub fn screaming_snake_case(name: &str) -> String {
+    let mut output = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            output.push('_');
+        }
+        output.push(c.to_ascii_uppercase());
+    }
+    output
+}
+
+/// `snake_case` to `snakeCase`.
+pub fn camel_case(name: &str) -> String {
+    let mut output = String::new();
+    let mut upper = false;
+    for c in name.chars() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            output.push(c.to_ascii_uppercase());
+            upper = false;
+        } else {
+            output.push(c);
+        }
+    }
+    output
+}
+
+pub fn tag_name(prefix: &str, message: &MessageSchema) -> String {
+    format!("{prefix}_{}_TAG", screaming_snake_case(&message.name))
+}
+
+pub fn subtag_name(prefix: &str, message: &MessageSchema, variant: &VariantSchema) -> String {
+    format!(
+        "{prefix}_{}_{}_TAG",
+        screaming_snake_case(&message.name),
+        screaming_snake_case(&variant.name)
+    )
+}
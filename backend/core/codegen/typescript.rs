@@ -0,0 +1,579 @@
+use super::schema::*;
+use std::{collections::HashSet, fmt::Write};
+
+/// Width prettier wraps lines at, counting tabs as two columns.
+const PRINT_WIDTH: usize = 100;
+
+static CODECS: &str = r"const encoder = new TextEncoder();
+const decoder = new TextDecoder();
+
+// Messages are encoded by bincode with big-endian fixed-size integers. Enum variants are
+// sent as a u32 index, options with a u8 tag, and strings and lists with a u64 length.
+class Writer {
+	private data = new Uint8Array(64);
+	private view = new DataView(this.data.buffer);
+	private offset = 0;
+
+	private reserve(length: number): number {
+		const offset = this.offset;
+		if (offset + length > this.data.length) {
+			const data = new Uint8Array(Math.max(this.data.length * 2, offset + length));
+			data.set(this.data);
+			this.data = data;
+			this.view = new DataView(data.buffer);
+		}
+		this.offset += length;
+		return offset;
+	}
+
+	u8(value: number) {
+		this.view.setUint8(this.reserve(1), value);
+	}
+
+	u32(value: number) {
+		this.view.setUint32(this.reserve(4), value);
+	}
+
+	u64(value: bigint) {
+		this.view.setBigUint64(this.reserve(8), value);
+	}
+
+	f32(value: number) {
+		this.view.setFloat32(this.reserve(4), value);
+	}
+
+	f64(value: number) {
+		this.view.setFloat64(this.reserve(8), value);
+	}
+
+	bool(value: boolean) {
+		this.u8(value ? 1 : 0);
+	}
+
+	bytes(value: Uint8Array) {
+		this.u64(BigInt(value.length));
+		const offset = this.reserve(value.length);
+		this.data.set(value, offset);
+	}
+
+	string(value: string) {
+		this.bytes(encoder.encode(value));
+	}
+
+	option<T>(value: T | undefined, write: (value: T) => void) {
+		this.u8(value === undefined ? 0 : 1);
+		if (value !== undefined) write(value);
+	}
+
+	vec<T>(values: T[], write: (value: T) => void) {
+		this.u64(BigInt(values.length));
+		values.forEach((value) => write(value));
+	}
+
+	finish(): Uint8Array {
+		return this.data.slice(0, this.offset);
+	}
+}
+
+class Reader {
+	private offset = 0;
+
+	constructor(private view: DataView) {}
+
+	private advance(length: number): number {
+		const offset = this.offset;
+		if (offset + length > this.view.byteLength) throw new RangeError('Message is truncated.');
+		this.offset += length;
+		return offset;
+	}
+
+	// Every value takes at least a byte, so longer lengths can only come from a corrupt message.
+	private length(): number {
+		const length = this.u64();
+		if (length > BigInt(this.view.byteLength - this.offset)) {
+			throw new RangeError('Message is truncated.');
+		}
+		return Number(length);
+	}
+
+	u8(): number {
+		return this.view.getUint8(this.advance(1));
+	}
+
+	u32(): number {
+		return this.view.getUint32(this.advance(4));
+	}
+
+	u64(): bigint {
+		return this.view.getBigUint64(this.advance(8));
+	}
+
+	f32(): number {
+		return this.view.getFloat32(this.advance(4));
+	}
+
+	f64(): number {
+		return this.view.getFloat64(this.advance(8));
+	}
+
+	bool(): boolean {
+		return this.u8() !== 0;
+	}
+
+	bytes(): Uint8Array {
+		const length = this.length();
+		const offset = this.advance(length);
+		return new Uint8Array(this.view.buffer, this.view.byteOffset + offset, length);
+	}
+
+	string(): string {
+		return decoder.decode(this.bytes());
+	}
+
+	option<T>(read: () => T): T | undefined {
+		return this.u8() === 0 ? undefined : read();
+	}
+
+	vec<T>(read: () => T): T[] {
+		return Array.from({ length: this.length() }, () => read());
+	}
+}
+";
+
+fn width(line: &str) -> usize {
+    line.chars().map(|c| if c == '\t' { 2 } else { 1 }).sum()
+}
+
+fn indent(depth: usize) -> String {
+    "\t".repeat(depth)
+}
+
+fn push_doc(output: &mut String, doc: Option<&String>, depth: usize) {
+    let Some(doc) = doc else {
+        return;
+    };
+    let indent = indent(depth);
+    let lines: Vec<&str> = doc.lines().collect();
+    if let [line] = lines[..] {
+        writeln!(output, "{indent}/** {line} */").unwrap();
+    } else {
+        writeln!(output, "{indent}/**").unwrap();
+        for line in lines {
+            writeln!(output, "{indent} * {line}").unwrap();
+        }
+        writeln!(output, "{indent} */").unwrap();
+    }
+}
+
+fn ts_type(r#type: &Type) -> String {
+    match r#type {
+        Type::Bool => "boolean".into(),
+        Type::U8 | Type::U32 | Type::F32 | Type::F64 => "number".into(),
+        Type::U64 => "bigint".into(),
+        Type::String => "string".into(),
+        Type::Bytes => "Uint8Array".into(),
+        Type::Vec(inner) => match **inner {
+            Type::Option(_) => format!("({})[]", ts_type(inner)),
+            _ => format!("{}[]", ts_type(inner)),
+        },
+        Type::Option(inner) => format!("{} | undefined", ts_type(inner)),
+        Type::Named(name) => name.clone(),
+    }
+}
+
+/// Property of an interface or object type, optional when it holds an `Option`.
+fn ts_property(field: &FieldSchema) -> String {
+    let name = camel_case(&field.name);
+    match Type::parse(&field.r#type) {
+        Type::Option(inner) => format!("{name}?: {}", ts_type(&inner)),
+        r#type => format!("{name}: {}", ts_type(&r#type)),
+    }
+}
+
+fn write_expression(r#type: &Type, value: &str) -> String {
+    match r#type {
+        Type::Bool => format!("writer.bool({value})"),
+        Type::U8 => format!("writer.u8({value})"),
+        Type::U32 => format!("writer.u32({value})"),
+        Type::U64 => format!("writer.u64({value})"),
+        Type::F32 => format!("writer.f32({value})"),
+        Type::F64 => format!("writer.f64({value})"),
+        Type::String => format!("writer.string({value})"),
+        Type::Bytes => format!("writer.bytes({value})"),
+        Type::Vec(inner) => format!(
+            "writer.vec({value}, (item) => {})",
+            write_expression(inner, "item")
+        ),
+        Type::Option(inner) => format!(
+            "writer.option({value}, (item) => {})",
+            write_expression(inner, "item")
+        ),
+        Type::Named(name) => format!("write{name}(writer, {value})"),
+    }
+}
+
+fn read_expression(r#type: &Type) -> String {
+    match r#type {
+        Type::Bool => "reader.bool()".into(),
+        Type::U8 => "reader.u8()".into(),
+        Type::U32 => "reader.u32()".into(),
+        Type::U64 => "reader.u64()".into(),
+        Type::F32 => "reader.f32()".into(),
+        Type::F64 => "reader.f64()".into(),
+        Type::String => "reader.string()".into(),
+        Type::Bytes => "reader.bytes()".into(),
+        Type::Vec(inner) => format!("reader.vec(() => {})", read_expression(inner)),
+        Type::Option(inner) => format!("reader.option(() => {})", read_expression(inner)),
+        Type::Named(name) => format!("read{name}(reader)"),
+    }
+}
+
+/// Object literal on one line when it fits, otherwise one property per line.
+fn push_object(
+    output: &mut String,
+    prefix: &str,
+    properties: &[String],
+    suffix: &str,
+    depth: usize,
+) {
+    let indent = indent(depth);
+    let line = format!("{indent}{prefix}{{ {} }}{suffix}", properties.join(", "));
+    if width(&line) <= PRINT_WIDTH {
+        writeln!(output, "{line}").unwrap();
+        return;
+    }
+    writeln!(output, "{indent}{prefix}{{").unwrap();
+    writeln!(
+        output,
+        "{indent}\t{}",
+        properties.join(&format!(",\n{indent}\t"))
+    )
+    .unwrap();
+    writeln!(output, "{indent}}}{suffix}").unwrap();
+}
+
+fn push_type(output: &mut String, schema: &TypeSchema) {
+    push_doc(output, schema.doc.as_ref(), 0);
+
+    if !schema.is_enum() {
+        writeln!(output, "export interface {} {{", schema.name).unwrap();
+        for field in &schema.fields {
+            push_doc(output, field.doc.as_ref(), 1);
+            writeln!(output, "\t{};", ts_property(field)).unwrap();
+        }
+        output.push_str("}\n");
+        return;
+    }
+
+    // Enums without fields are plain strings, and the others objects tagged by `type`.
+    writeln!(output, "export type {} =", schema.name).unwrap();
+    for variant in &schema.variants {
+        push_doc(output, variant.doc.as_ref(), 1);
+        if schema.is_unit_enum() {
+            writeln!(output, "\t| '{}'", variant.name).unwrap();
+            continue;
+        }
+
+        let mut properties = vec![format!("type: '{}'", variant.name)];
+        properties.extend(variant.fields.iter().map(ts_property));
+        let line = format!("\t| {{ {} }}", properties.join("; "));
+        if width(&line) <= PRINT_WIDTH {
+            writeln!(output, "{line}").unwrap();
+        } else {
+            output.push_str("\t| {\n");
+            for property in properties {
+                writeln!(output, "\t\t\t{property};").unwrap();
+            }
+            output.push_str("\t  }\n");
+        }
+    }
+    output.pop();
+    output.push_str(";\n");
+}
+
+fn push_writer(output: &mut String, schema: &TypeSchema) {
+    writeln!(
+        output,
+        "function write{0}(writer: Writer, value: {0}) {{",
+        schema.name
+    )
+    .unwrap();
+
+    if !schema.is_enum() {
+        for field in &schema.fields {
+            let value = format!("value.{}", camel_case(&field.name));
+            let expression = write_expression(&Type::parse(&field.r#type), &value);
+            writeln!(output, "\t{expression};").unwrap();
+        }
+        output.push_str("}\n");
+        return;
+    }
+
+    let discriminant = if schema.is_unit_enum() {
+        "value"
+    } else {
+        "value.type"
+    };
+    writeln!(output, "\tswitch ({discriminant}) {{").unwrap();
+    for (index, variant) in schema.variants.iter().enumerate() {
+        writeln!(output, "\t\tcase '{}':", variant.name).unwrap();
+        writeln!(output, "\t\t\twriter.u32({index});").unwrap();
+        for field in &variant.fields {
+            let value = format!("value.{}", camel_case(&field.name));
+            let expression = write_expression(&Type::parse(&field.r#type), &value);
+            writeln!(output, "\t\t\t{expression};").unwrap();
+        }
+        output.push_str("\t\t\tbreak;\n");
+    }
+    output.push_str("\t}\n}\n");
+}
+
+fn push_reader(output: &mut String, schema: &TypeSchema) {
+    writeln!(
+        output,
+        "function read{0}(reader: Reader): {0} {{",
+        schema.name
+    )
+    .unwrap();
+
+    let read_properties = |fields: &[FieldSchema]| {
+        fields
+            .iter()
+            .map(|field| {
+                let r#type = Type::parse(&field.r#type);
+                format!("{}: {}", camel_case(&field.name), read_expression(&r#type))
+            })
+            .collect::<Vec<_>>()
+    };
+
+    if !schema.is_enum() {
+        // Properties are read in order, as object literals are evaluated left to right.
+        push_object(output, "return ", &read_properties(&schema.fields), ";", 1);
+        output.push_str("}\n");
+        return;
+    }
+
+    output.push_str("\tconst index = reader.u32();\n");
+    output.push_str("\tswitch (index) {\n");
+    for (index, variant) in schema.variants.iter().enumerate() {
+        writeln!(output, "\t\tcase {index}:").unwrap();
+        if schema.is_unit_enum() {
+            writeln!(output, "\t\t\treturn '{}';", variant.name).unwrap();
+            continue;
+        }
+        let mut properties = vec![format!("type: '{}'", variant.name)];
+        properties.extend(read_properties(&variant.fields));
+        if variant.fields.is_empty() {
+            push_object(output, "return ", &properties, ";", 3);
+        } else {
+            // Kept on separate lines, as prettier does for objects written that way.
+            writeln!(output, "\t\t\treturn {{").unwrap();
+            writeln!(output, "\t\t\t\t{}", properties.join(",\n\t\t\t\t")).unwrap();
+            output.push_str("\t\t\t};\n");
+        }
+    }
+    output.push_str("\t\tdefault:\n");
+    writeln!(
+        output,
+        "\t\t\tthrow new RangeError(`Invalid {} variant ${{index}}.`);",
+        schema.name
+    )
+    .unwrap();
+    output.push_str("\t}\n}\n");
+}
+
+fn push_messages(output: &mut String, name: &str, messages: &[MessageSchema]) {
+    writeln!(output, "export type {name} =").unwrap();
+    for message in messages {
+        push_doc(output, message.doc.as_ref(), 1);
+        match &message.payload {
+            Some(payload) => {
+                writeln!(
+                    output,
+                    "\t| {{ type: '{}'; payload: {payload} }}",
+                    message.name
+                )
+                .unwrap();
+            }
+            None => writeln!(output, "\t| {{ type: '{}' }}", message.name).unwrap(),
+        }
+    }
+    output.pop();
+    output.push_str(";\n");
+}
+
+pub fn generate(schema: &Schema) -> String {
+    let mut output = String::from(
+        "// Auto-generated file from backend/core/protocol.json. Any changes will be overwritten.\n\n",
+    );
+
+    writeln!(
+        output,
+        "export const PROTOCOL_VERSION = {};\nexport const MIN_PROTOCOL_VERSION = {};",
+        schema.version, schema.min_version
+    )
+    .unwrap();
+
+    for (prefix, messages) in [("C", &schema.client), ("S", &schema.server)] {
+        output.push('\n');
+        for message in messages {
+            writeln!(
+                output,
+                "export const {} = {};",
+                tag_name(prefix, message),
+                message.tag
+            )
+            .unwrap();
+        }
+        for message in messages {
+            let Some(payload) = &message.payload else {
+                continue;
+            };
+            for (subtag, variant) in schema.get(payload).variants.iter().enumerate() {
+                writeln!(
+                    output,
+                    "export const {} = {subtag};",
+                    subtag_name(prefix, message, variant)
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    for r#type in &schema.types {
+        output.push('\n');
+        push_type(&mut output, r#type);
+    }
+
+    output.push('\n');
+    push_messages(&mut output, "ClientMsg", &schema.client);
+    output.push('\n');
+    push_messages(&mut output, "ServerMsg", &schema.server);
+
+    // Content types of the formats tiles can be sent in.
+    for r#type in &schema.types {
+        if !r#type.variants.iter().any(|variant| variant.mime.is_some()) {
+            continue;
+        }
+        writeln!(
+            output,
+            "\nexport const {}_MIME_TYPES: Record<{}['type'], string> = {{",
+            screaming_snake_case(&r#type.name),
+            r#type.name
+        )
+        .unwrap();
+        let mimes = r#type
+            .variants
+            .iter()
+            .map(|variant| {
+                let mime = variant.mime.as_ref().expect("Format without content type");
+                format!("\t{}: '{mime}'", variant.name)
+            })
+            .collect::<Vec<_>>();
+        output.push_str(&mimes.join(",\n"));
+        output.push_str("\n};\n");
+    }
+
+    output.push('\n');
+    output.push_str(CODECS);
+
+    // Only the client side of the protocol is needed: writers for what it sends, and
+    // readers for what it receives.
+    let written = schema.reachable(&schema.client);
+    let read = schema.reachable(&schema.server);
+    let reachable =
+        |names: &HashSet<&str>, r#type: &&TypeSchema| names.contains(r#type.name.as_str());
+
+    for r#type in schema
+        .types
+        .iter()
+        .filter(|r#type| reachable(&written, r#type))
+    {
+        output.push('\n');
+        push_writer(&mut output, r#type);
+    }
+    for r#type in schema
+        .types
+        .iter()
+        .filter(|r#type| reachable(&read, r#type))
+    {
+        output.push('\n');
+        push_reader(&mut output, r#type);
+    }
+
+    output.push_str("\nexport function encodeClientMsg(msg: ClientMsg): Uint8Array {\n");
+    output.push_str("\tconst writer = new Writer();\n");
+    output.push_str("\tswitch (msg.type) {\n");
+    for message in &schema.client {
+        writeln!(output, "\t\tcase '{}':", message.name).unwrap();
+        writeln!(output, "\t\t\twriter.u8({});", tag_name("C", message)).unwrap();
+        if let Some(payload) = &message.payload {
+            writeln!(output, "\t\t\twrite{payload}(writer, msg.payload);").unwrap();
+        }
+        output.push_str("\t\t\tbreak;\n");
+    }
+    output.push_str("\t}\n\treturn writer.finish();\n}\n");
+
+    output.push_str("\nexport function decodeServerMsg(data: ArrayBuffer): ServerMsg {\n");
+    output.push_str("\tconst reader = new Reader(new DataView(data));\n");
+    output.push_str("\tconst tag = reader.u8();\n");
+    output.push_str("\tswitch (tag) {\n");
+    for message in &schema.server {
+        writeln!(output, "\t\tcase {}:", tag_name("S", message)).unwrap();
+        let Some(payload) = &message.payload else {
+            writeln!(output, "\t\t\treturn {{ type: '{}' }};", message.name).unwrap();
+            continue;
+        };
+        if schema.get(payload).is_enum() {
+            output.push_str("\t\t\t// The subtag repeats the variant index of the payload.\n");
+            output.push_str("\t\t\treader.u8();\n");
+        }
+        let properties = [
+            format!("type: '{}'", message.name),
+            format!("payload: read{payload}(reader)"),
+        ];
+        push_object(&mut output, "return ", &properties, ";", 3);
+    }
+    output.push_str("\t\tdefault:\n");
+    output.push_str("\t\t\tthrow new RangeError(`Invalid message tag ${tag}.`);\n");
+    output.push_str("\t}\n}\n");
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn frontend_protocol_matches_schema() {
+        let manifest = env!("CARGO_MANIFEST_DIR");
+        let schema = Schema::load(&format!("{manifest}/protocol.json"));
+        let generated = generate(&schema);
+
+        // Every message can be sent or received by the frontend.
+        for message in &schema.client {
+            assert!(generated.contains(&format!("\t\tcase '{}':\n", message.name)));
+        }
+        for message in &schema.server {
+            assert!(generated.contains(&format!("\t\tcase {}:\n", tag_name("S", message))));
+        }
+        for r#type in &schema.types {
+            let declaration = if r#type.is_enum() {
+                "type"
+            } else {
+                "interface"
+            };
+            assert!(generated.contains(&format!("export {declaration} {} ", r#type.name)));
+        }
+
+        // The committed file is the one generated from the current schema.
+        let committed =
+            fs::read_to_string(format!("{manifest}/../../frontend/src/lib/protocol.ts")).unwrap();
+        assert_eq!(
+            committed, generated,
+            "protocol.ts is out of date with protocol.json"
+        );
+    }
+}
@@ -0,0 +1,65 @@
+use crate::api::prelude::*;
+use crate::constants::{DEEPZOOM_OVERLAP, DEEPZOOM_TILE_SIZE};
+use axum::http::header::{ACCESS_CONTROL_ALLOW_ORIGIN, CACHE_CONTROL, CONTENT_TYPE};
+
+#[derive(Deserialize)]
+pub struct PathParams {
+    store_id: u32,
+    // Image id and extension, e.g. `12.dzi`.
+    file: String,
+}
+
+/// Synthesises a `DeepZoom` descriptor for a stored image.
+pub async fn descriptor(
+    Extension(dbm): Extension<Arc<DatabaseManager>>,
+    Extension(mut logger): Extension<Logger<'_>>,
+    Path(PathParams { store_id, file }): Path<PathParams>,
+) -> Response {
+    let Some(image_id) = file
+        .strip_suffix(".dzi")
+        .and_then(|id| id.parse::<u32>().ok())
+    else {
+        return logger.error(
+            StatusCode::NOT_FOUND,
+            Error::RequestIntegrity,
+            "DZD-E00",
+            "Descriptor must be requested as {image_id}.dzi.",
+            None,
+        );
+    };
+
+    let base = match crate::db::image::metadata_layer(&dbm, store_id, image_id, 0) {
+        Ok(base) => base,
+        Err(e) => {
+            return logger.error(
+                StatusCode::NOT_FOUND,
+                Error::ResourceExistence,
+                "DZD-E01",
+                "Image does not exist.",
+                Some(e),
+            );
+        }
+    };
+
+    let descriptor = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<Image xmlns="http://schemas.microsoft.com/deepzoom/2008" Format="jpeg" Overlap="{DEEPZOOM_OVERLAP}" TileSize="{DEEPZOOM_TILE_SIZE}">
+    <Size Width="{}" Height="{}"/>
+</Image>
+"#,
+        base.width, base.height
+    );
+
+    logger.success(StatusCode::OK, "Retrieved DeepZoom descriptor.");
+
+    (
+        StatusCode::OK,
+        [
+            (CONTENT_TYPE, "application/xml"),
+            (CACHE_CONTROL, "public, max-age=86400"),
+            (ACCESS_CONTROL_ALLOW_ORIGIN, "*"),
+        ],
+        descriptor,
+    )
+        .into_response()
+}
@@ -0,0 +1,273 @@
+use crate::api::prelude::*;
+use crate::constants::{DEEPZOOM_OVERLAP, DEEPZOOM_TILE_SIZE};
+use crate::types::messages::TileFormat;
+use axum::{
+    body::Bytes,
+    http::header::{ACCESS_CONTROL_ALLOW_ORIGIN, CACHE_CONTROL, CONTENT_TYPE},
+};
+use shared::types::{Address, Region, Size};
+
+#[derive(Deserialize)]
+pub struct PathParams {
+    store_id: u32,
+    // Image id with the DeepZoom suffix, e.g. `12_files`.
+    files: String,
+    level: u32,
+    // Tile column, row and extension, e.g. `3_4.jpeg`.
+    file: String,
+}
+
+/// Synthesises a `DeepZoom` tile from the nearest stored pyramid level.
+pub async fn tiles(
+    Extension(dbm): Extension<Arc<DatabaseManager>>,
+    Extension(mut logger): Extension<Logger<'_>>,
+    Path(PathParams {
+        store_id,
+        files,
+        level,
+        file,
+    }): Path<PathParams>,
+) -> Response {
+    let Some(image_id) = files
+        .strip_suffix("_files")
+        .and_then(|id| id.parse::<u32>().ok())
+    else {
+        return logger.error(
+            StatusCode::NOT_FOUND,
+            Error::RequestIntegrity,
+            "DZT-E00",
+            "Tiles must be requested under {image_id}_files.",
+            None,
+        );
+    };
+
+    let Some((col, row, format)) = file.split_once('.').and_then(|(position, extension)| {
+        let (col, row) = position.split_once('_')?;
+        let format = match extension {
+            "jpeg" | "jpg" => TileFormat::default(),
+            "png" => TileFormat::Png,
+            _ => return None,
+        };
+        Some((col.parse::<u32>().ok()?, row.parse::<u32>().ok()?, format))
+    }) else {
+        return logger.error(
+            StatusCode::NOT_FOUND,
+            Error::RequestIntegrity,
+            "DZT-E01",
+            "Tile must be requested as {col}_{row}.jpeg or {col}_{row}.png.",
+            None,
+        );
+    };
+
+    let layers = match crate::db::image::metadata_layers(&dbm, store_id, image_id) {
+        Ok(layers) if !layers.is_empty() => layers,
+        Ok(_) => {
+            return logger.error(
+                StatusCode::NOT_FOUND,
+                Error::ResourceExistence,
+                "DZT-E02",
+                "Image does not exist.",
+                None,
+            );
+        }
+        Err(e) => {
+            return logger.error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Error::DatabaseQuery,
+                "DZT-E03",
+                "Failed to retrieve image metadata.",
+                Some(e),
+            );
+        }
+    };
+
+    let Some(region) = deepzoom_region(layers[0].width, layers[0].height, level, col, row) else {
+        return logger.error(
+            StatusCode::NOT_FOUND,
+            Error::ResourceExistence,
+            "DZT-E04",
+            "Tile does not exist.",
+            None,
+        );
+    };
+
+    if !crate::io::within_source_limit(&layers, &region.0, &region.1) {
+        return logger.error(
+            StatusCode::BAD_REQUEST,
+            Error::RequestIntegrity,
+            "DZT-E08",
+            "Requested tile reads too many source pixels.",
+            None,
+        );
+    }
+
+    let path = match crate::db::image::image_path(&dbm, store_id, image_id) {
+        Ok(path) => path,
+        Err(e) => {
+            return logger.error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Error::DatabaseQuery,
+                "DZT-E05",
+                "Failed to retrieve image path.",
+                Some(e),
+            );
+        }
+    };
+
+    // Reading and resampling the region is blocking.
+    let result = tokio::task::spawn_blocking(move || {
+        let (region, output) = region;
+        let image = crate::io::region(&path, &layers, &region, &output)?;
+        crate::io::encode(image, format)
+    })
+    .await;
+
+    let buffer = match result {
+        Ok(Ok(buffer)) => buffer,
+        Ok(Err(e)) => {
+            return logger.error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Error::ResourceRead,
+                "DZT-E06",
+                "Failed to read DeepZoom tile.",
+                Some(e),
+            );
+        }
+        Err(e) => {
+            return logger.error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Error::ResourceRead,
+                "DZT-E07",
+                "DeepZoom tile task failed.",
+                Some(e.into()),
+            );
+        }
+    };
+
+    logger.success(StatusCode::OK, "Retrieved DeepZoom tile.");
+
+    (
+        StatusCode::OK,
+        [
+            (CONTENT_TYPE, format.content_type()),
+            (CACHE_CONTROL, "public, max-age=86400"),
+            (ACCESS_CONTROL_ALLOW_ORIGIN, "*"),
+        ],
+        Bytes::from(buffer),
+    )
+        .into_response()
+}
+
+/// Maps a `DeepZoom` tile onto a level 0 region and the size it should be returned at.
+///
+/// `DeepZoom` levels halve in size from the full image at the highest level
+/// down to a single pixel at level 0.
+fn deepzoom_region(
+    width: u32,
+    height: u32,
+    level: u32,
+    col: u32,
+    row: u32,
+) -> Option<(Region, Size)> {
+    let max_level = width
+        .max(height)
+        .max(1)
+        .next_power_of_two()
+        .trailing_zeros();
+    if level > max_level {
+        return None;
+    }
+
+    let factor = 1_u64 << (max_level - level);
+    let level_width = u64::from(width).div_ceil(factor);
+    let level_height = u64::from(height).div_ceil(factor);
+
+    // Tile bounds at this level, extended by the overlap on inner edges.
+    let bounds = |index: u32, length: u64| -> Option<(u64, u64)> {
+        let start = u64::from(index) * u64::from(DEEPZOOM_TILE_SIZE);
+        if start >= length {
+            return None;
+        }
+        let start_with_overlap = start.saturating_sub(u64::from(DEEPZOOM_OVERLAP));
+        let end = (start + u64::from(DEEPZOOM_TILE_SIZE + DEEPZOOM_OVERLAP)).min(length);
+        Some((start_with_overlap, end))
+    };
+
+    let (x_start, x_end) = bounds(col, level_width)?;
+    let (y_start, y_end) = bounds(row, level_height)?;
+
+    // Scale back up to level 0, clamped to the image.
+    let to_base = |value: u64, limit: u32| {
+        u32::try_from(value.saturating_mul(factor))
+            .unwrap_or(limit)
+            .min(limit)
+    };
+
+    let x = to_base(x_start, width);
+    let y = to_base(y_start, height);
+    let region = Region {
+        size: Size {
+            width: (to_base(x_end, width) - x).max(1),
+            height: (to_base(y_end, height) - y).max(1),
+        },
+        level: 0,
+        address: Address { x, y },
+    };
+
+    let output = Size {
+        width: u32::try_from(x_end - x_start).ok()?,
+        height: u32::try_from(y_end - y_start).ok()?,
+    };
+
+    Some((region, output))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 1000x600 has 11 levels, with 4 by 3 tiles of 254 pixels at the top one.
+    const WIDTH: u32 = 1000;
+    const HEIGHT: u32 = 600;
+
+    // Level 0 region as x, y, width and height, and the size it is returned at.
+    type Tile = ((u32, u32, u32, u32), (u32, u32));
+
+    fn tile(level: u32, col: u32, row: u32) -> Option<Tile> {
+        deepzoom_region(WIDTH, HEIGHT, level, col, row).map(|(region, output)| {
+            (
+                (
+                    region.address.x,
+                    region.address.y,
+                    region.size.width,
+                    region.size.height,
+                ),
+                (output.width, output.height),
+            )
+        })
+    }
+
+    #[test]
+    fn maps_tiles_of_the_top_level() {
+        // The first tile only overlaps its neighbours to the right and below.
+        assert_eq!(tile(10, 0, 0), Some(((0, 0, 255, 255), (255, 255))));
+        // The last tile only overlaps its neighbours to the left and above, and is cut
+        // short by the edges of the image.
+        assert_eq!(tile(10, 3, 2), Some(((761, 507, 239, 93), (239, 93))));
+        assert_eq!(tile(10, 4, 0), None);
+        assert_eq!(tile(10, 0, 3), None);
+    }
+
+    #[test]
+    fn maps_tiles_of_the_bottom_level() {
+        // The single pixel of the lowest level covers the whole image.
+        assert_eq!(tile(0, 0, 0), Some(((0, 0, WIDTH, HEIGHT), (1, 1))));
+        assert_eq!(tile(0, 1, 0), None);
+        assert_eq!(tile(0, 0, 1), None);
+    }
+
+    #[test]
+    fn rejects_levels_above_the_image() {
+        assert_eq!(tile(11, 0, 0), None);
+    }
+}
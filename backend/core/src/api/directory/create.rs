@@ -63,12 +63,15 @@ pub async fn create(
 
     // [COMMS]: Broadcast directory create message to connected clients.
     match csm
-        .broadcast(ServerMsg::Directory(DirectoryServerMsg::Create {
+        .broadcast(
             store_id,
-            parent_id,
-            id,
-            name,
-        }))
+            ServerMsg::Directory(DirectoryServerMsg::Create {
+                store_id,
+                parent_id,
+                id,
+                name,
+            }),
+        )
         .await
     {
         Ok(()) => logger.success(StatusCode::CREATED, "Directory created successfully."),
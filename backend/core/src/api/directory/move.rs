@@ -94,11 +94,14 @@ pub async fn r#move(
 
     // Broadcast directory move.
     match csm
-        .broadcast(ServerMsg::Directory(DirectoryServerMsg::Move {
+        .broadcast(
             store_id,
-            id: directory_id,
-            destination_id,
-        }))
+            ServerMsg::Directory(DirectoryServerMsg::Move {
+                store_id,
+                id: directory_id,
+                destination_id,
+            }),
+        )
         .await
     {
         Ok(()) => logger.success(StatusCode::OK, "Directory moved successfully."),
@@ -1,7 +1,11 @@
 use crate::api::prelude::*;
+use crate::types::generator::GeneratorRegistry;
 
-pub async fn generators(Extension(mut logger): Extension<Logger<'_>>) -> Response {
-    let generators = generators::export::names();
+pub async fn generators(
+    Extension(registry): Extension<Arc<GeneratorRegistry>>,
+    Extension(mut logger): Extension<Logger<'_>>,
+) -> Response {
+    let generators = registry.names();
 
     logger.success(StatusCode::OK, "Retrieved annotation generators.");
 
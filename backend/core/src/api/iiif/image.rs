@@ -0,0 +1,636 @@
+use crate::api::prelude::*;
+use crate::constants::{IIIF_PROFILE, MAX_REGION_SIZE};
+use crate::types::messages::TileFormat;
+use axum::{
+    body::Bytes,
+    http::header::{ACCESS_CONTROL_ALLOW_ORIGIN, CACHE_CONTROL, CONTENT_TYPE, LINK},
+};
+use image::{Rgb, RgbImage, imageops};
+use shared::types::{Address, Region, Size};
+
+#[derive(Deserialize)]
+pub struct PathParams {
+    store_id: u32,
+    image_id: u32,
+    region: String,
+    size: String,
+    rotation: String,
+    // Quality and format, e.g. `default.jpg`.
+    file: String,
+}
+
+/// Status code and reason for rejecting part of an image request.
+type Rejection = (StatusCode, &'static str);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Quality {
+    Color,
+    Gray,
+    Bitonal,
+}
+
+/// Parameters of an image request, in the order they are applied.
+struct Request {
+    region: Region,
+    output: Size,
+    mirror: bool,
+    rotation: u32,
+    quality: Quality,
+    format: TileFormat,
+}
+
+/// Serves an IIIF Image API 3.0 image request.
+pub async fn image(
+    Extension(dbm): Extension<Arc<DatabaseManager>>,
+    Extension(mut logger): Extension<Logger<'_>>,
+    Path(PathParams {
+        store_id,
+        image_id,
+        region,
+        size,
+        rotation,
+        file,
+    }): Path<PathParams>,
+) -> Response {
+    let layers = match crate::db::image::metadata_layers(&dbm, store_id, image_id) {
+        Ok(layers) if !layers.is_empty() => layers,
+        Ok(_) => {
+            return logger.error(
+                StatusCode::NOT_FOUND,
+                Error::ResourceExistence,
+                "IIIF-E02",
+                "Image does not exist.",
+                None,
+            );
+        }
+        Err(e) => {
+            return logger.error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Error::DatabaseQuery,
+                "IIIF-E03",
+                "Failed to retrieve image metadata.",
+                Some(e),
+            );
+        }
+    };
+
+    let parsed = parse_request(
+        &region,
+        &size,
+        &rotation,
+        &file,
+        layers[0].width,
+        layers[0].height,
+    );
+
+    let Request {
+        region,
+        output,
+        mirror,
+        rotation,
+        quality,
+        format,
+    } = match parsed {
+        Ok(parsed) => {
+            logger.report(Check::RequestIntegrity, "Parsed image request parameters.");
+            parsed
+        }
+        Err((status_code, message)) => {
+            return logger.error(
+                status_code,
+                Error::RequestIntegrity,
+                "IIIF-E04",
+                message,
+                None,
+            );
+        }
+    };
+
+    if !crate::io::within_source_limit(&layers, &region, &output) {
+        return logger.error(
+            StatusCode::BAD_REQUEST,
+            Error::RequestIntegrity,
+            "IIIF-E08",
+            "Requested region reads too many source pixels.",
+            None,
+        );
+    }
+
+    let path = match crate::db::image::image_path(&dbm, store_id, image_id) {
+        Ok(path) => path,
+        Err(e) => {
+            return logger.error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Error::DatabaseQuery,
+                "IIIF-E05",
+                "Failed to retrieve image path.",
+                Some(e),
+            );
+        }
+    };
+
+    // Reading and resampling the region is blocking.
+    let result = tokio::task::spawn_blocking(move || {
+        let mut image = crate::io::region(&path, &layers, &region, &output)?;
+
+        // Mirroring is applied before rotation.
+        if mirror {
+            imageops::flip_horizontal_in_place(&mut image);
+        }
+
+        image = match rotation {
+            90 => imageops::rotate90(&image),
+            180 => imageops::rotate180(&image),
+            270 => imageops::rotate270(&image),
+            _ => image,
+        };
+
+        apply_quality(&mut image, quality);
+
+        crate::io::encode(image, format)
+    })
+    .await;
+
+    let buffer = match result {
+        Ok(Ok(buffer)) => buffer,
+        Ok(Err(e)) => {
+            return logger.error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Error::ResourceRead,
+                "IIIF-E06",
+                "Failed to read image region.",
+                Some(e),
+            );
+        }
+        Err(e) => {
+            return logger.error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Error::ResourceRead,
+                "IIIF-E07",
+                "Image region task failed.",
+                Some(e.into()),
+            );
+        }
+    };
+
+    logger.success(StatusCode::OK, "Retrieved image region successfully.");
+
+    (
+        StatusCode::OK,
+        [
+            (CONTENT_TYPE, format.content_type().to_string()),
+            (CACHE_CONTROL, "public, max-age=86400".to_string()),
+            (LINK, format!("<{IIIF_PROFILE}>;rel=\"profile\"")),
+            (ACCESS_CONTROL_ALLOW_ORIGIN, "*".to_string()),
+        ],
+        Bytes::from(buffer),
+    )
+        .into_response()
+}
+
+/// Parses the path segments of an image request against an image of the given size.
+fn parse_request(
+    region: &str,
+    size: &str,
+    rotation: &str,
+    file: &str,
+    width: u32,
+    height: u32,
+) -> Result<Request, Rejection> {
+    let region = parse_region(region, width, height)?;
+    let output = parse_size(size, &region.size)?;
+    let (mirror, rotation) = parse_rotation(rotation)?;
+    let (quality, format) = parse_file(file)?;
+
+    Ok(Request {
+        region,
+        output,
+        mirror,
+        rotation,
+        quality,
+        format,
+    })
+}
+
+/// Parses `full`, `square`, `x,y,w,h` or `pct:x,y,w,h` into a level 0 region,
+/// cropped to the bounds of the image.
+fn parse_region(region: &str, width: u32, height: u32) -> Result<Region, Rejection> {
+    let (x, y, w, h) = match region {
+        "full" => (0, 0, width, height),
+        "square" => {
+            let side = width.min(height);
+            ((width - side) / 2, (height - side) / 2, side, side)
+        }
+        _ => {
+            let (values, percent) = match region.strip_prefix("pct:") {
+                Some(values) => (values, true),
+                None => (region, false),
+            };
+
+            let values = values
+                .split(',')
+                .map(str::parse::<f64>)
+                .collect::<Result<Vec<_>, _>>()
+                .ok()
+                .filter(|values| values.len() == 4 && values.iter().all(|v| *v >= 0.0))
+                .ok_or((StatusCode::BAD_REQUEST, "Invalid region."))?;
+
+            let (x, y, w, h) = if percent {
+                (
+                    values[0] / 100.0 * f64::from(width),
+                    values[1] / 100.0 * f64::from(height),
+                    values[2] / 100.0 * f64::from(width),
+                    values[3] / 100.0 * f64::from(height),
+                )
+            } else {
+                if values.iter().any(|v| v.fract() != 0.0) {
+                    return Err((StatusCode::BAD_REQUEST, "Region must be in whole pixels."));
+                }
+                (values[0], values[1], values[2], values[3])
+            };
+
+            (
+                to_pixels(x, u32::MAX),
+                to_pixels(y, u32::MAX),
+                to_pixels(w, u32::MAX),
+                to_pixels(h, u32::MAX),
+            )
+        }
+    };
+
+    if w == 0 || h == 0 || x >= width || y >= height {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Region is empty or outside the image.",
+        ));
+    }
+
+    Ok(Region {
+        size: Size {
+            width: w.min(width - x),
+            height: h.min(height - y),
+        },
+        level: 0,
+        address: Address { x, y },
+    })
+}
+
+/// Parses `max`, `w,`, `,h`, `pct:n`, `w,h` or `!w,h`, optionally prefixed with `^`
+/// to allow upscaling, into the dimensions of the returned image.
+fn parse_size(size: &str, region: &Size) -> Result<Size, Rejection> {
+    let (size, upscale) = match size.strip_prefix('^') {
+        Some(size) => (size, true),
+        None => (size, false),
+    };
+
+    let region_width = f64::from(region.width);
+    let region_height = f64::from(region.height);
+    let limit = f64::from(MAX_REGION_SIZE);
+    let invalid = (StatusCode::BAD_REQUEST, "Invalid size.");
+
+    let parse = |value: &str| value.parse::<f64>().ok().filter(|v| *v > 0.0);
+
+    let (width, height) = if size == "max" {
+        let mut scale = (limit / region_width).min(limit / region_height);
+        if !upscale {
+            scale = scale.min(1.0);
+        }
+        (region_width * scale, region_height * scale)
+    } else if let Some(percent) = size.strip_prefix("pct:") {
+        let percent = parse(percent).ok_or(invalid)?;
+        (
+            region_width * percent / 100.0,
+            region_height * percent / 100.0,
+        )
+    } else if let Some(confined) = size.strip_prefix('!') {
+        let (w, h) = confined.split_once(',').ok_or(invalid)?;
+        let (w, h) = (parse(w).ok_or(invalid)?, parse(h).ok_or(invalid)?);
+        let mut scale = (w / region_width).min(h / region_height);
+        if !upscale {
+            scale = scale.min(1.0);
+        }
+        (region_width * scale, region_height * scale)
+    } else {
+        match size.split_once(',').ok_or(invalid)? {
+            (w, "") => {
+                let w = parse(w).ok_or(invalid)?;
+                (w, region_height * w / region_width)
+            }
+            ("", h) => {
+                let h = parse(h).ok_or(invalid)?;
+                (region_width * h / region_height, h)
+            }
+            (w, h) => (parse(w).ok_or(invalid)?, parse(h).ok_or(invalid)?),
+        }
+    };
+
+    let width = to_pixels(width.round(), u32::MAX).max(1);
+    let height = to_pixels(height.round(), u32::MAX).max(1);
+
+    if !upscale && (width > region.width || height > region.height) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Size is larger than the region; use ^ to upscale.",
+        ));
+    }
+
+    if width > MAX_REGION_SIZE || height > MAX_REGION_SIZE {
+        return Err((StatusCode::BAD_REQUEST, "Size exceeds the maximum."));
+    }
+
+    Ok(Size { width, height })
+}
+
+/// Parses a rotation, optionally prefixed with `!` to mirror the image first.
+/// Only multiples of 90 degrees are supported.
+fn parse_rotation(rotation: &str) -> Result<(bool, u32), Rejection> {
+    let (rotation, mirror) = match rotation.strip_prefix('!') {
+        Some(rotation) => (rotation, true),
+        None => (rotation, false),
+    };
+
+    let degrees = rotation
+        .parse::<f64>()
+        .ok()
+        .filter(|degrees| (0.0..=360.0).contains(degrees))
+        .ok_or((StatusCode::BAD_REQUEST, "Invalid rotation."))?;
+
+    if degrees % 90.0 != 0.0 {
+        return Err((
+            StatusCode::NOT_IMPLEMENTED,
+            "Only rotations by multiples of 90 degrees are supported.",
+        ));
+    }
+
+    Ok((mirror, to_pixels(degrees, 360) % 360))
+}
+
+/// Parses `{quality}.{format}`.
+fn parse_file(file: &str) -> Result<(Quality, TileFormat), Rejection> {
+    let (quality, format) = file
+        .split_once('.')
+        .ok_or((StatusCode::BAD_REQUEST, "Invalid quality and format."))?;
+
+    let quality = match quality {
+        "default" | "color" => Quality::Color,
+        "gray" => Quality::Gray,
+        "bitonal" => Quality::Bitonal,
+        _ => return Err((StatusCode::BAD_REQUEST, "Invalid quality.")),
+    };
+
+    let format = match format {
+        "jpg" => TileFormat::default(),
+        "png" => TileFormat::Png,
+        "webp" => TileFormat::WebP,
+        "tif" => TileFormat::Tiff,
+        "gif" | "jp2" | "pdf" => {
+            return Err((StatusCode::NOT_IMPLEMENTED, "Format is not supported."));
+        }
+        _ => return Err((StatusCode::BAD_REQUEST, "Invalid format.")),
+    };
+
+    Ok((quality, format))
+}
+
+fn apply_quality(image: &mut RgbImage, quality: Quality) {
+    if let Quality::Color = quality {
+        return;
+    }
+
+    for Rgb(pixel) in image.pixels_mut() {
+        // ITU-R BT.601 luma.
+        let luma =
+            (299 * u32::from(pixel[0]) + 587 * u32::from(pixel[1]) + 114 * u32::from(pixel[2]))
+                / 1000;
+        let value = match quality {
+            Quality::Bitonal if luma < 128 => 0,
+            Quality::Bitonal => 255,
+            _ => u8::try_from(luma).unwrap_or(u8::MAX),
+        };
+        *pixel = [value; 3];
+    }
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn to_pixels(value: f64, max: u32) -> u32 {
+    value.clamp(0.0, f64::from(max)) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Method;
+    use shared::{constants::TILE_SIZE, types::MetadataLayer};
+
+    const WIDTH: u32 = 4000;
+    const HEIGHT: u32 = 3000;
+
+    fn parse(region: &str, size: &str, rotation: &str, file: &str) -> Result<Request, StatusCode> {
+        parse_request(region, size, rotation, file, WIDTH, HEIGHT).map_err(|(status, _)| status)
+    }
+
+    fn region(region: &str) -> Option<(u32, u32, u32, u32)> {
+        parse_region(region, WIDTH, HEIGHT).ok().map(|region| {
+            (
+                region.address.x,
+                region.address.y,
+                region.size.width,
+                region.size.height,
+            )
+        })
+    }
+
+    fn size(size: &str, width: u32, height: u32) -> Option<(u32, u32)> {
+        parse_size(size, &Size { width, height })
+            .ok()
+            .map(|size| (size.width, size.height))
+    }
+
+    #[test]
+    fn parses_regions() {
+        assert_eq!(region("full"), Some((0, 0, WIDTH, HEIGHT)));
+        assert_eq!(region("square"), Some((500, 0, 3000, 3000)));
+        assert_eq!(region("10,20,300,400"), Some((10, 20, 300, 400)));
+        assert_eq!(region("pct:50,50,50,50"), Some((2000, 1500, 2000, 1500)));
+        // Cropped to the image.
+        assert_eq!(region("3900,2900,500,500"), Some((3900, 2900, 100, 100)));
+
+        for invalid in [
+            "",
+            "1,2,3",
+            "1,2,3,4,5",
+            "a,b,c,d",
+            "-1,0,10,10",
+            "0.5,0,10,10",
+            "0,0,0,10",
+            "4000,0,10,10",
+            "pct:100,0,10,10",
+        ] {
+            assert_eq!(region(invalid), None, "{invalid}");
+        }
+    }
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(size("max", 1000, 500), Some((1000, 500)));
+        assert_eq!(size("max", 10000, 5000), Some((MAX_REGION_SIZE, 4096)));
+        assert_eq!(size("500,", 1000, 500), Some((500, 250)));
+        assert_eq!(size(",250", 1000, 500), Some((500, 250)));
+        assert_eq!(size("pct:50", 1000, 500), Some((500, 250)));
+        assert_eq!(size("300,300", 1000, 500), Some((300, 300)));
+        assert_eq!(size("!500,500", 1000, 500), Some((500, 250)));
+        assert_eq!(size("^2000,", 1000, 500), Some((2000, 1000)));
+        assert_eq!(size("^max", 100, 50), Some((MAX_REGION_SIZE, 4096)));
+
+        for invalid in [
+            "", "full", "0,", ",0", "-5,", "pct:0", "pct:", "!500", "a,b", "2000,", "pct:200",
+        ] {
+            assert_eq!(size(invalid, 1000, 500), None, "{invalid}");
+        }
+        assert_eq!(size("^10000,", 1000, 500), None);
+    }
+
+    #[test]
+    fn parses_rotations() {
+        assert_eq!(parse_rotation("0").ok(), Some((false, 0)));
+        assert_eq!(parse_rotation("90").ok(), Some((false, 90)));
+        assert_eq!(parse_rotation("!270").ok(), Some((true, 270)));
+        assert_eq!(parse_rotation("360").ok(), Some((false, 0)));
+
+        for invalid in ["", "!", "-90", "361", "a"] {
+            assert_eq!(
+                parse_rotation(invalid).err().map(|(status, _)| status),
+                Some(StatusCode::BAD_REQUEST),
+                "{invalid}"
+            );
+        }
+        assert_eq!(
+            parse_rotation("45").err().map(|(status, _)| status),
+            Some(StatusCode::NOT_IMPLEMENTED)
+        );
+    }
+
+    #[test]
+    fn parses_qualities_and_formats() {
+        assert_eq!(
+            parse_file("default.jpg").ok(),
+            Some((Quality::Color, TileFormat::default()))
+        );
+        assert_eq!(
+            parse_file("color.png").ok(),
+            Some((Quality::Color, TileFormat::Png))
+        );
+        assert_eq!(
+            parse_file("gray.webp").ok(),
+            Some((Quality::Gray, TileFormat::WebP))
+        );
+        assert_eq!(
+            parse_file("bitonal.tif").ok(),
+            Some((Quality::Bitonal, TileFormat::Tiff))
+        );
+
+        for invalid in ["default", "grey.jpg", "default.bmp", "default."] {
+            assert_eq!(
+                parse_file(invalid).err().map(|(status, _)| status),
+                Some(StatusCode::BAD_REQUEST),
+                "{invalid}"
+            );
+        }
+        assert_eq!(
+            parse_file("default.jp2").err().map(|(status, _)| status),
+            Some(StatusCode::NOT_IMPLEMENTED)
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_requests() {
+        let request = parse("full", "max", "!90", "gray.png").ok().unwrap();
+        assert_eq!(request.output.width, WIDTH);
+        assert!(request.mirror);
+        assert_eq!(request.rotation, 90);
+        assert_eq!(request.quality, Quality::Gray);
+        assert_eq!(request.format, TileFormat::Png);
+
+        for (region, size, rotation, file) in [
+            ("full", "max", "0", "default.jpg"),
+            ("0,0,10,10", "max", "0", "default.jpg"),
+        ] {
+            assert!(parse(region, size, rotation, file).is_ok());
+        }
+
+        for (region, size, rotation, file) in [
+            ("nowhere", "max", "0", "default.jpg"),
+            ("full", "huge", "0", "default.jpg"),
+            ("full", "max", "sideways", "default.jpg"),
+            ("full", "max", "0", "best.jpg"),
+            ("full", "max", "0", "default.doc"),
+            ("0,0,10,10", "20,20", "0", "default.jpg"),
+        ] {
+            assert_eq!(
+                parse(region, size, rotation, file).err(),
+                Some(StatusCode::BAD_REQUEST),
+                "{region}/{size}/{rotation}/{file}"
+            );
+        }
+    }
+
+    async fn request(
+        (store_id, image_id): (u32, u32),
+        (width, height): (u32, u32),
+        region: &str,
+        size: &str,
+    ) -> StatusCode {
+        let layer = MetadataLayer {
+            level: 0,
+            cols: width.div_ceil(TILE_SIZE),
+            rows: height.div_ceil(TILE_SIZE),
+            width,
+            height,
+        };
+        let dbm = DatabaseManager::with_metadata_layers(1, 2, &[layer]).unwrap();
+
+        image(
+            Extension(Arc::new(dbm)),
+            Extension(Logger::start(Method::GET, String::new(), String::new())),
+            Path(PathParams {
+                store_id,
+                image_id,
+                region: region.into(),
+                size: size.into(),
+                rotation: "0".into(),
+                file: "default.jpg".into(),
+            }),
+        )
+        .await
+        .status()
+    }
+
+    #[tokio::test]
+    async fn database_failure_is_server_error() {
+        let status = request((9, 2), (WIDTH, HEIGHT), "full", "max").await;
+
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[tokio::test]
+    async fn missing_image_is_not_found() {
+        let status = request((1, 3), (WIDTH, HEIGHT), "full", "max").await;
+
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn invalid_request_is_bad_request() {
+        let status = request((1, 2), (WIDTH, HEIGHT), "nowhere", "max").await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn oversized_source_is_bad_request() {
+        // Without smaller levels, the whole base level would be read to serve the request.
+        let status = request((1, 2), (120_000, 120_000), "full", "max").await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
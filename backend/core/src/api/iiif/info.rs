@@ -0,0 +1,320 @@
+use crate::api::prelude::*;
+use crate::constants::{IIIF_CONTEXT, IIIF_PROFILE, MAX_REGION_SIZE};
+use axum::http::{
+    HeaderMap,
+    header::{ACCESS_CONTROL_ALLOW_ORIGIN, CONTENT_TYPE, HOST, LINK, LOCATION},
+};
+use serde::Serialize;
+use shared::{constants::TILE_SIZE, types::MetadataLayer};
+
+#[derive(Deserialize)]
+pub struct PathParams {
+    store_id: u32,
+    image_id: u32,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Info {
+    #[serde(rename = "@context")]
+    context: &'static str,
+    id: String,
+    #[serde(rename = "type")]
+    r#type: &'static str,
+    protocol: &'static str,
+    profile: &'static str,
+    width: u32,
+    height: u32,
+    max_width: u32,
+    max_height: u32,
+    sizes: Vec<InfoSize>,
+    tiles: Vec<InfoTiles>,
+    extra_qualities: Vec<&'static str>,
+    extra_formats: Vec<&'static str>,
+    extra_features: Vec<&'static str>,
+}
+
+#[derive(Serialize)]
+struct InfoSize {
+    width: u32,
+    height: u32,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct InfoTiles {
+    width: u32,
+    height: u32,
+    scale_factors: Vec<u32>,
+}
+
+/// Redirects the base URI of an image to its image information document.
+pub async fn redirect(
+    Extension(mut logger): Extension<Logger<'_>>,
+    Path(PathParams { store_id, image_id }): Path<PathParams>,
+) -> Response {
+    logger.success(StatusCode::SEE_OTHER, "Redirected to image information.");
+
+    (
+        StatusCode::SEE_OTHER,
+        [(
+            LOCATION,
+            format!("/api/iiif/{store_id}/{image_id}/info.json"),
+        )],
+    )
+        .into_response()
+}
+
+/// Describes an image as an IIIF Image API 3.0 service.
+pub async fn info(
+    Extension(dbm): Extension<Arc<DatabaseManager>>,
+    Extension(mut logger): Extension<Logger<'_>>,
+    Path(PathParams { store_id, image_id }): Path<PathParams>,
+    headers: HeaderMap,
+) -> Response {
+    let layers = match crate::db::image::metadata_layers(&dbm, store_id, image_id) {
+        Ok(layers) if !layers.is_empty() => layers,
+        Ok(_) => {
+            return logger.error(
+                StatusCode::NOT_FOUND,
+                Error::ResourceExistence,
+                "IIIF-E00",
+                "Image does not exist.",
+                None,
+            );
+        }
+        Err(e) => {
+            return logger.error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Error::DatabaseQuery,
+                "IIIF-E01",
+                "Failed to retrieve image metadata.",
+                Some(e),
+            );
+        }
+    };
+
+    // Identifiers must be absolute, so rebuild the URL the client used.
+    let host = headers
+        .get(HOST)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("localhost");
+    let scheme = headers
+        .get("x-forwarded-proto")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("http");
+
+    let info = describe(
+        format!("{scheme}://{host}/api/iiif/{store_id}/{image_id}"),
+        &layers,
+    );
+
+    logger.success(StatusCode::OK, "Retrieved image information.");
+
+    (
+        [
+            (
+                CONTENT_TYPE,
+                format!("application/ld+json;profile=\"{IIIF_CONTEXT}\""),
+            ),
+            (LINK, format!("<{IIIF_PROFILE}>;rel=\"profile\"")),
+            (ACCESS_CONTROL_ALLOW_ORIGIN, "*".into()),
+        ],
+        Json(info),
+    )
+        .into_response()
+}
+
+/// Builds the image information document from the levels of an image, largest first.
+fn describe(id: String, layers: &[MetadataLayer]) -> Info {
+    let base = &layers[0];
+
+    // Levels are stored largest first, but sizes are listed smallest first. Levels
+    // beyond the maximum size would be rejected, so they are not advertised.
+    let sizes = layers
+        .iter()
+        .rev()
+        .filter(|layer| layer.width <= MAX_REGION_SIZE && layer.height <= MAX_REGION_SIZE)
+        .map(|layer| InfoSize {
+            width: layer.width,
+            height: layer.height,
+        })
+        .collect();
+
+    let mut scale_factors: Vec<u32> = layers
+        .iter()
+        .map(|layer| (base.width / layer.width.max(1)).max(1))
+        .collect();
+    scale_factors.dedup();
+
+    Info {
+        context: IIIF_CONTEXT,
+        id,
+        r#type: "ImageService3",
+        protocol: "http://iiif.io/api/image",
+        profile: "level1",
+        width: base.width,
+        height: base.height,
+        max_width: MAX_REGION_SIZE,
+        max_height: MAX_REGION_SIZE,
+        sizes,
+        tiles: vec![InfoTiles {
+            width: TILE_SIZE,
+            height: TILE_SIZE,
+            scale_factors,
+        }],
+        extra_qualities: vec!["color", "gray", "bitonal"],
+        extra_formats: vec!["png", "tif", "webp"],
+        extra_features: vec![
+            "mirroring",
+            "regionByPct",
+            "regionSquare",
+            "rotationBy90s",
+            "sizeByConfinedWh",
+            "sizeByPct",
+            "sizeByWh",
+            "sizeUpscaling",
+        ],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Method;
+    use serde_json::{Value, json};
+
+    fn layers(sizes: &[(u32, u32)]) -> Vec<MetadataLayer> {
+        sizes
+            .iter()
+            .zip(0..)
+            .map(|(&(width, height), level)| MetadataLayer {
+                level,
+                cols: width.div_ceil(TILE_SIZE),
+                rows: height.div_ceil(TILE_SIZE),
+                width,
+                height,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn describes_level1_service() {
+        let info = serde_json::to_value(describe(
+            "http://localhost/api/iiif/1/2".into(),
+            &layers(&[(4000, 3000), (2000, 1500), (1000, 750)]),
+        ))
+        .unwrap();
+
+        assert_eq!(info["@context"], IIIF_CONTEXT);
+        assert_eq!(info["id"], "http://localhost/api/iiif/1/2");
+        assert_eq!(info["type"], "ImageService3");
+        assert_eq!(info["protocol"], "http://iiif.io/api/image");
+        assert_eq!(info["profile"], "level1");
+        assert_eq!(info["width"], 4000);
+        assert_eq!(info["height"], 3000);
+        assert_eq!(info["maxWidth"], MAX_REGION_SIZE);
+        assert_eq!(info["maxHeight"], MAX_REGION_SIZE);
+        assert_eq!(
+            info["sizes"],
+            json!([
+                { "width": 1000, "height": 750 },
+                { "width": 2000, "height": 1500 },
+                { "width": 4000, "height": 3000 },
+            ])
+        );
+        assert_eq!(
+            info["tiles"],
+            json!([{ "width": TILE_SIZE, "height": TILE_SIZE, "scaleFactors": [1, 2, 4] }])
+        );
+        assert!(
+            info["extraFeatures"]
+                .as_array()
+                .unwrap()
+                .contains(&json!("regionByPct"))
+        );
+    }
+
+    #[test]
+    fn sizes_stay_within_maximum() {
+        let info = serde_json::to_value(describe(
+            String::new(),
+            &layers(&[(40000, 30000), (10000, 7500), (5000, 3750)]),
+        ))
+        .unwrap();
+
+        assert_eq!(info["sizes"], json!([{ "width": 5000, "height": 3750 }]));
+        assert_eq!(info["tiles"][0]["scaleFactors"], json!([1, 4, 8]));
+    }
+
+    #[test]
+    fn lists_each_scale_factor_once() {
+        let info = serde_json::to_value(describe(
+            String::new(),
+            &layers(&[(4000, 3000), (3999, 2999), (1000, 750)]),
+        ))
+        .unwrap();
+
+        assert_eq!(info["tiles"][0]["scaleFactors"], json!([1, 4]));
+    }
+
+    async fn request(store_id: u32, image_id: u32) -> (StatusCode, HeaderMap, Option<Value>) {
+        let dbm = DatabaseManager::with_metadata_layers(
+            1,
+            2,
+            &layers(&[(4000, 3000), (2000, 1500), (1000, 750)]),
+        )
+        .unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(HOST, "viewer.example".parse().unwrap());
+        headers.insert("x-forwarded-proto", "https".parse().unwrap());
+
+        let response = info(
+            Extension(Arc::new(dbm)),
+            Extension(Logger::start(Method::GET, String::new(), String::new())),
+            Path(PathParams { store_id, image_id }),
+            headers,
+        )
+        .await;
+
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, headers, serde_json::from_slice(&body).ok())
+    }
+
+    #[tokio::test]
+    async fn serves_image_information() {
+        let (status, headers, info) = request(1, 2).await;
+        let info = info.unwrap();
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            headers[CONTENT_TYPE],
+            format!("application/ld+json;profile=\"{IIIF_CONTEXT}\"")
+        );
+        assert_eq!(headers[LINK], format!("<{IIIF_PROFILE}>;rel=\"profile\""));
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        assert_eq!(info["@context"], IIIF_CONTEXT);
+        assert_eq!(info["id"], "https://viewer.example/api/iiif/1/2");
+        assert_eq!(info["profile"], "level1");
+        assert_eq!(info["sizes"][0], json!({ "width": 1000, "height": 750 }));
+        assert_eq!(info["tiles"][0]["scaleFactors"], json!([1, 2, 4]));
+    }
+
+    #[tokio::test]
+    async fn missing_image_is_not_found() {
+        let (status, _, _) = request(1, 3).await;
+
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn database_failure_is_server_error() {
+        let (status, _, _) = request(9, 2).await;
+
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
@@ -0,0 +1,252 @@
+use crate::api::prelude::*;
+use crate::db::annotation::Attach;
+use crate::types::generator::GeneratorRegistry;
+use axum_typed_multipart::{FieldData, TryFromMultipart, TypedMultipart};
+use shared::{traits::Generator, types::AnnotationLayer};
+use tempfile::NamedTempFile;
+
+#[derive(Deserialize)]
+pub struct PathParams {
+    store_id: u32,
+    image_id: u32,
+}
+
+#[derive(TryFromMultipart)]
+pub struct Multipart {
+    generator: String,
+    #[form_data(limit = "unlimited")]
+    annotations_file: FieldData<NamedTempFile>,
+}
+
+/// Translates an annotations file and adds its layers to an existing image.
+pub async fn attach(
+    Extension(csm): Extension<Arc<ClientSocketManager>>,
+    Extension(dbm): Extension<Arc<DatabaseManager>>,
+    Extension(registry): Extension<Arc<GeneratorRegistry>>,
+    Extension(logger): Extension<Logger<'_>>,
+    Path(params): Path<PathParams>,
+    TypedMultipart(Multipart {
+        generator,
+        annotations_file,
+    }): TypedMultipart<Multipart>,
+) -> Response {
+    let Some(generator) = registry.get(&generator) else {
+        return generator_not_found(logger);
+    };
+
+    attach_layers(
+        &csm,
+        dbm,
+        logger,
+        params,
+        generator,
+        annotations_file,
+        Attach::Merge,
+    )
+    .await
+}
+
+/// Translates an annotations file and replaces every layer of an existing image with its layers.
+pub async fn replace(
+    Extension(csm): Extension<Arc<ClientSocketManager>>,
+    Extension(dbm): Extension<Arc<DatabaseManager>>,
+    Extension(registry): Extension<Arc<GeneratorRegistry>>,
+    Extension(logger): Extension<Logger<'_>>,
+    Path(params): Path<PathParams>,
+    TypedMultipart(Multipart {
+        generator,
+        annotations_file,
+    }): TypedMultipart<Multipart>,
+) -> Response {
+    let Some(generator) = registry.get(&generator) else {
+        return generator_not_found(logger);
+    };
+
+    attach_layers(
+        &csm,
+        dbm,
+        logger,
+        params,
+        generator,
+        annotations_file,
+        Attach::Replace,
+    )
+    .await
+}
+
+fn generator_not_found(mut logger: Logger<'_>) -> Response {
+    logger.error(
+        StatusCode::NOT_FOUND,
+        Error::ResourceExistence,
+        "IAA-E00",
+        "Generator could not be found.",
+        None,
+    )
+}
+
+async fn attach_layers(
+    csm: &ClientSocketManager,
+    dbm: Arc<DatabaseManager>,
+    mut logger: Logger<'_>,
+    PathParams { store_id, image_id }: PathParams,
+    generator: Box<dyn Generator>,
+    annotations_file: FieldData<NamedTempFile>,
+    mode: Attach,
+) -> Response {
+    logger.report(Check::ResourceExistence, "Generator found.");
+
+    // Translating the file and triangulating its annotations is blocking.
+    let result = tokio::task::spawn_blocking(move || {
+        let layers = generator
+            .translate(annotations_file.contents.path())
+            .map_err(|e| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    Error::RequestIntegrity,
+                    "IAA-E01",
+                    "Failed to translate annotations file.",
+                    Some(e),
+                )
+            })?;
+
+        // [DATABASE]: Insert the layers and their annotations into the database.
+        let (removed, attached) =
+            match crate::db::annotation::attach(&dbm, store_id, image_id, layers, mode) {
+                Ok(Some(result)) => result,
+                Ok(None) => {
+                    return Err((
+                        StatusCode::NOT_FOUND,
+                        Error::ResourceExistence,
+                        "IAA-E02",
+                        "Image does not exist.",
+                        None,
+                    ));
+                }
+                Err(e) => {
+                    let status_code = if crate::db::is_constraint_violation(&e) {
+                        StatusCode::CONFLICT
+                    } else {
+                        StatusCode::INTERNAL_SERVER_ERROR
+                    };
+                    return Err((
+                        status_code,
+                        Error::DatabaseInsertion,
+                        "IAA-E03",
+                        "Failed to insert annotation layers into the database.",
+                        Some(e),
+                    ));
+                }
+            };
+
+        // [IO]: Remove the GLBs of replaced layers. Layers without annotations may have none.
+        for &layer_id in &removed {
+            crate::db::image::annotation_path(&dbm, store_id, image_id, layer_id)
+                .and_then(|path| match std::fs::remove_file(path) {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+                    _ => Ok(()),
+                })
+                .map_err(|e| {
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Error::ResourceDeletion,
+                        "IAA-E04",
+                        "Failed to remove GLB annotation layer file.",
+                        Some(e),
+                    )
+                })?;
+        }
+
+        // [IO]: Rebuild the GLBs of the layers, including annotations they already held.
+        for layer in &attached {
+            #[allow(clippy::cast_possible_truncation)]
+            let layer_id = layer.id as u32;
+            crate::geometry::rebuild_glb(&dbm, store_id, image_id, layer_id).map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Error::ResourceCreation,
+                    "IAA-E05",
+                    "Failed to compute annotation geometry.",
+                    Some(e),
+                )
+            })?;
+        }
+
+        Ok((removed, attached))
+    })
+    .await;
+
+    let (removed, attached) = match result {
+        Ok(Ok(result)) => result,
+        Ok(Err((status_code, error, id, message, details))) => {
+            return logger.error(status_code, error, id, message, details);
+        }
+        Err(e) => {
+            return logger.error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Error::ResourceCreation,
+                "IAA-E07",
+                "Annotation attach task failed.",
+                Some(e.into()),
+            );
+        }
+    };
+
+    logger.log("Annotation layers inserted into the database.");
+    logger.log("Successfully computed annotation geometries and saved to disk.");
+
+    // [COMMS]: Broadcast annotation layer messages to connected clients.
+    if let Err(e) = broadcast_layers(csm, store_id, image_id, removed, &attached).await {
+        return logger.error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Error::ResponseIntegrity,
+            "IAA-E06",
+            "Failed to encode annotation layer message.",
+            Some(e),
+        );
+    }
+
+    logger.success(
+        StatusCode::CREATED,
+        "Annotation layers attached successfully.",
+    );
+
+    let layers: Vec<_> = attached
+        .into_iter()
+        .map(|mut layer| {
+            // The annotations are served through the GLBs and the query endpoint.
+            layer.annotations.clear();
+            layer
+        })
+        .collect();
+
+    (StatusCode::CREATED, Json(layers)).into_response()
+}
+
+/// Tells clients which layers of an image were removed and which received annotations.
+pub async fn broadcast_layers(
+    csm: &ClientSocketManager,
+    store_id: u32,
+    image_id: u32,
+    removed: Vec<u32>,
+    attached: &[AnnotationLayer],
+) -> anyhow::Result<()> {
+    let messages = removed
+        .into_iter()
+        .map(|id| AnnotationLayerServerMsg::Delete {
+            store_id,
+            image_id,
+            id,
+        })
+        .chain(
+            attached
+                .iter()
+                .map(|layer| AnnotationLayerServerMsg::create(store_id, image_id, layer)),
+        );
+
+    for message in messages {
+        csm.broadcast(store_id, ServerMsg::AnnotationLayer(message))
+            .await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,124 @@
+use crate::api::prelude::*;
+use shared::types::Annotation;
+
+#[derive(Deserialize)]
+pub struct PathParams {
+    store_id: u32,
+    image_id: u32,
+    annotation_layer_id: u32,
+}
+
+pub async fn create(
+    Extension(csm): Extension<Arc<ClientSocketManager>>,
+    Extension(dbm): Extension<Arc<DatabaseManager>>,
+    Extension(mut logger): Extension<Logger<'_>>,
+    Path(PathParams {
+        store_id,
+        image_id,
+        annotation_layer_id,
+    }): Path<PathParams>,
+    Json(annotation): Json<Annotation>,
+) -> Response {
+    if annotation.geometry.bounds().is_none() {
+        return logger.error(
+            StatusCode::BAD_REQUEST,
+            Error::RequestIntegrity,
+            "IAC-E00",
+            "Annotation geometry has no coordinates.",
+            None,
+        );
+    }
+
+    // [DATABASE]: Insert annotation into the database.
+    let annotation = match crate::db::annotation::create(
+        &dbm,
+        store_id,
+        image_id,
+        annotation_layer_id,
+        annotation,
+    ) {
+        Ok(Some(annotation)) => {
+            logger.log("Annotation inserted into the database.");
+            annotation
+        }
+        Ok(None) => {
+            return logger.error(
+                StatusCode::NOT_FOUND,
+                Error::ResourceExistence,
+                "IAC-E01",
+                "Annotation layer does not exist.",
+                None,
+            );
+        }
+        Err(e) => {
+            return logger.error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Error::DatabaseInsertion,
+                "IAC-E02",
+                "Failed to insert annotation into the database.",
+                Some(e),
+            );
+        }
+    };
+
+    // [IO]: Rebuild the GLB of the layer. Triangulating is blocking.
+    let result = tokio::task::spawn_blocking(move || {
+        crate::geometry::rebuild_glb(&dbm, store_id, image_id, annotation_layer_id)
+    })
+    .await;
+
+    match result {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => {
+            return logger.error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Error::ResourceCreation,
+                "IAC-E03",
+                "Failed to compute annotation geometry.",
+                Some(e),
+            );
+        }
+        Err(e) => {
+            return logger.error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Error::ResourceCreation,
+                "IAC-E05",
+                "Annotation geometry task failed.",
+                Some(e.into()),
+            );
+        }
+    }
+
+    // [COMMS]: Broadcast annotation create message to connected clients.
+    let broadcast = match annotation.to_json() {
+        Ok((geometry, properties)) => {
+            csm.broadcast(
+                store_id,
+                ServerMsg::Annotation(AnnotationServerMsg::Create {
+                    store_id,
+                    image_id,
+                    layer_id: annotation_layer_id,
+                    id: annotation.id,
+                    geometry,
+                    properties,
+                }),
+            )
+            .await
+        }
+        Err(e) => Err(e.into()),
+    };
+
+    match broadcast {
+        Ok(()) => {
+            logger.success(StatusCode::CREATED, "Annotation created successfully.");
+            (StatusCode::CREATED, Json(annotation)).into_response()
+        }
+        Err(e) => logger.error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Error::ResponseIntegrity,
+            "IAC-E04",
+            "Failed to encode annotation create message.",
+            Some(e),
+        ),
+    }
+}
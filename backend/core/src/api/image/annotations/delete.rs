@@ -0,0 +1,101 @@
+use crate::api::prelude::*;
+
+#[derive(Deserialize)]
+pub struct PathParams {
+    store_id: u32,
+    image_id: u32,
+    annotation_layer_id: u32,
+    annotation_id: u32,
+}
+
+pub async fn delete(
+    Extension(csm): Extension<Arc<ClientSocketManager>>,
+    Extension(dbm): Extension<Arc<DatabaseManager>>,
+    Extension(mut logger): Extension<Logger<'_>>,
+    Path(PathParams {
+        store_id,
+        image_id,
+        annotation_layer_id,
+        annotation_id,
+    }): Path<PathParams>,
+) -> Response {
+    // [DATABASE]: Delete annotation from the database.
+    match crate::db::annotation::delete(
+        &dbm,
+        store_id,
+        image_id,
+        annotation_layer_id,
+        annotation_id,
+    ) {
+        Ok(true) => logger.log("Annotation deleted from the database."),
+        Ok(false) => {
+            return logger.error(
+                StatusCode::NOT_FOUND,
+                Error::ResourceExistence,
+                "IAD-E00",
+                "Annotation does not exist.",
+                None,
+            );
+        }
+        Err(e) => {
+            return logger.error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Error::DatabaseDeletion,
+                "IAD-E01",
+                "Failed to delete annotation from the database.",
+                Some(e),
+            );
+        }
+    }
+
+    // [IO]: Rebuild the GLB of the layer. Triangulating is blocking.
+    let result = tokio::task::spawn_blocking(move || {
+        crate::geometry::rebuild_glb(&dbm, store_id, image_id, annotation_layer_id)
+    })
+    .await;
+
+    match result {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => {
+            return logger.error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Error::ResourceCreation,
+                "IAD-E02",
+                "Failed to compute annotation geometry.",
+                Some(e),
+            );
+        }
+        Err(e) => {
+            return logger.error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Error::ResourceCreation,
+                "IAD-E04",
+                "Annotation geometry task failed.",
+                Some(e.into()),
+            );
+        }
+    }
+
+    // [COMMS]: Broadcast annotation delete message to connected clients.
+    match csm
+        .broadcast(
+            store_id,
+            ServerMsg::Annotation(AnnotationServerMsg::Delete {
+                store_id,
+                image_id,
+                layer_id: annotation_layer_id,
+                id: annotation_id,
+            }),
+        )
+        .await
+    {
+        Ok(()) => logger.success(StatusCode::OK, "Annotation deleted successfully."),
+        Err(e) => logger.error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Error::ResponseIntegrity,
+            "IAD-E03",
+            "Failed to encode annotation delete message.",
+            Some(e),
+        ),
+    }
+}
@@ -0,0 +1,110 @@
+use crate::api::prelude::*;
+use crate::types::annotation::ExportFormat;
+use axum::{body::Bytes, http::header};
+
+#[derive(Deserialize)]
+pub struct PathParams {
+    store_id: u32,
+    image_id: u32,
+}
+
+#[derive(Deserialize)]
+pub struct QueryParams {
+    format: Option<String>,
+    // Exports every layer when not given.
+    layer: Option<u32>,
+}
+
+/// Exports the annotations of an image as `GeoJSON`, `QuPath` `GeoJSON` or CSV.
+pub async fn export(
+    Extension(dbm): Extension<Arc<DatabaseManager>>,
+    Extension(mut logger): Extension<Logger<'_>>,
+    Path(PathParams { store_id, image_id }): Path<PathParams>,
+    Query(QueryParams { format, layer }): Query<QueryParams>,
+) -> Response {
+    let format = match format.as_deref().unwrap_or("geojson") {
+        "geojson" => ExportFormat::GeoJson,
+        "qupath" => ExportFormat::QuPath,
+        "csv" => ExportFormat::Csv,
+        _ => {
+            return logger.error(
+                StatusCode::BAD_REQUEST,
+                Error::RequestIntegrity,
+                "IAE-E00",
+                "Format must be one of geojson, qupath or csv.",
+                None,
+            );
+        }
+    };
+
+    // Serialising many annotations is blocking.
+    let result = tokio::task::spawn_blocking(move || {
+        let Some(layers) = crate::db::annotation::layers(&dbm, store_id, image_id, layer)? else {
+            return anyhow::Ok(None);
+        };
+        if layer.is_some() && layers.is_empty() {
+            return Ok(None);
+        }
+
+        let body = match format {
+            ExportFormat::GeoJson | ExportFormat::QuPath => {
+                let qupath = matches!(format, ExportFormat::QuPath);
+                serde_json::to_vec(&crate::geometry::export::geojson(&layers, qupath))?
+            }
+            ExportFormat::Csv => crate::geometry::export::csv(&layers).into_bytes(),
+        };
+
+        Ok(Some(body))
+    })
+    .await;
+
+    let body = match result {
+        Ok(Ok(Some(body))) => body,
+        Ok(Ok(None)) => {
+            return logger.error(
+                StatusCode::NOT_FOUND,
+                Error::ResourceExistence,
+                "IAE-E01",
+                "Image or annotation layer does not exist.",
+                None,
+            );
+        }
+        Ok(Err(e)) => {
+            return logger.error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Error::DatabaseQuery,
+                "IAE-E02",
+                "Failed to retrieve annotations for export.",
+                Some(e),
+            );
+        }
+        Err(e) => {
+            return logger.error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Error::ResourceRead,
+                "IAE-E03",
+                "Annotation export task failed.",
+                Some(e.into()),
+            );
+        }
+    };
+
+    logger.success(StatusCode::OK, "Exported annotations successfully.");
+
+    let name = match layer {
+        Some(layer) => format!("i{image_id}-a{layer}"),
+        None => format!("i{image_id}"),
+    };
+
+    (
+        axum::response::AppendHeaders([
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{name}.{}\"", format.extension()),
+            ),
+        ]),
+        Bytes::from(body),
+    )
+        .into_response()
+}
@@ -0,0 +1,261 @@
+use crate::api::prelude::*;
+use crate::db::annotation::Attach;
+use crate::types::{
+    generator::GeneratorRegistry,
+    messages::{ErrorServerMsg, GenerationServerMsg},
+    user::User,
+};
+use anyhow::anyhow;
+use axum::http::Method;
+use shared::traits::Generator;
+use std::{cell::Cell, path::PathBuf, sync::mpsc};
+
+#[derive(Deserialize)]
+pub struct PathParams {
+    store_id: u32,
+    image_id: u32,
+}
+
+#[derive(Deserialize)]
+pub struct Body {
+    generator: String,
+}
+
+/// Starts generating annotations for an existing image. The layers are broadcast to
+/// clients once the job completes.
+pub async fn generate(
+    Extension(user): Extension<User>,
+    Extension(csm): Extension<Arc<ClientSocketManager>>,
+    Extension(dbm): Extension<Arc<DatabaseManager>>,
+    Extension(registry): Extension<Arc<GeneratorRegistry>>,
+    Extension(mut logger): Extension<Logger<'_>>,
+    Path(PathParams { store_id, image_id }): Path<PathParams>,
+    Json(Body { generator }): Json<Body>,
+) -> Response {
+    let Some(generator) = registry.get(&generator) else {
+        return logger.error(
+            StatusCode::NOT_FOUND,
+            Error::ResourceExistence,
+            "IAG-E00",
+            "Generator could not be found.",
+            None,
+        );
+    };
+
+    logger.report(Check::ResourceExistence, "Generator found.");
+
+    let uploaded_image = match crate::db::image::uploaded_image(&dbm, store_id, image_id) {
+        Ok(Some(image)) => image,
+        Ok(None) => {
+            return logger.error(
+                StatusCode::NOT_FOUND,
+                Error::ResourceExistence,
+                "IAG-E01",
+                "Image does not exist.",
+                None,
+            );
+        }
+        Err(e) => {
+            return logger.error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Error::DatabaseQuery,
+                "IAG-E02",
+                "Failed to retrieve uploaded image.",
+                Some(e),
+            );
+        }
+    };
+
+    spawn(
+        csm,
+        dbm,
+        user.id,
+        store_id,
+        image_id,
+        generator,
+        uploaded_image,
+    );
+
+    logger.success(StatusCode::ACCEPTED, "Annotation generation started.")
+}
+
+/// Generates annotations in the background and adds them to the image as new layers,
+/// broadcasting the progress of the generator to clients. Failures are sent to every
+/// session of the user who started the job, as its request has already been answered.
+pub fn spawn(
+    csm: Arc<ClientSocketManager>,
+    dbm: Arc<DatabaseManager>,
+    user_id: u32,
+    store_id: u32,
+    image_id: u32,
+    generator: Box<dyn Generator>,
+    (image_path, extension): (PathBuf, String),
+) {
+    tokio::spawn(async move {
+        // The request that started the job has already completed, so it gets its own log.
+        let mut logger = Logger::start(
+            Method::POST,
+            format!("/api/image/{store_id}/{image_id}/annotations/generate"),
+            format!("generator={}", generator.name()),
+        );
+
+        let name = generator.name();
+        let runtime = tokio::runtime::Handle::current();
+        // Output of the generator is logged once the job completes.
+        let (output, lines) = mpsc::channel::<String>();
+
+        // Decoding the image and computing its annotations is blocking.
+        let result = tokio::task::spawn_blocking({
+            let csm = Arc::clone(&csm);
+            let dbm = Arc::clone(&dbm);
+            move || {
+                // The uploaded image is stored without an extension, which external tools
+                // rely on to pick a reader.
+                let directory = tempfile::tempdir()?;
+                let linked_path = directory.path().join(format!("image.{extension}"));
+                std::os::unix::fs::symlink(std::fs::canonicalize(&image_path)?, &linked_path)?;
+
+                let decoder = decoders::export::get(&extension, &linked_path)
+                    .ok_or_else(|| anyhow!("No decoders found for image."))?;
+
+                // Progress is broadcast in steps of a percent or more, as generators may report often.
+                let reported = Cell::new(0.0);
+                let progress = |fraction: f64| {
+                    if fraction - reported.get() < 0.01 {
+                        return;
+                    }
+                    reported.set(fraction);
+                    // Progress is informative, so failing to send it does not stop the job.
+                    let _ = runtime
+                        .block_on(broadcast_progress(&csm, store_id, image_id, name, fraction));
+                };
+
+                let log = |line: &str| {
+                    let _ = output.send(line.into());
+                };
+
+                let layers = generator.generate(&linked_path, &*decoder, &progress, &log)?;
+
+                // Each job adds its own layers, so running a generator again does not
+                // duplicate the annotations of an earlier run.
+                let attached = crate::db::annotation::attach(
+                    &dbm,
+                    store_id,
+                    image_id,
+                    layers,
+                    Attach::Separate,
+                )?
+                .map(|(_, attached)| attached);
+
+                for layer in attached.iter().flatten() {
+                    #[allow(clippy::cast_possible_truncation)]
+                    let layer_id = layer.id as u32;
+                    crate::geometry::rebuild_glb(&dbm, store_id, image_id, layer_id)?;
+                }
+
+                anyhow::Ok(attached)
+            }
+        })
+        .await;
+
+        for line in lines.try_iter() {
+            logger.output(name, line);
+        }
+
+        // Clients stop showing progress whether or not the generator succeeded.
+        let _ = broadcast_progress(&csm, store_id, image_id, name, 1.0).await;
+
+        let attached = match result {
+            Ok(Ok(Some(attached))) => Ok(attached),
+            Ok(Ok(None)) => Err((
+                StatusCode::NOT_FOUND,
+                Error::ResourceExistence,
+                "IAGJ-E00",
+                "Image was deleted before its annotations were generated.",
+                None,
+            )),
+            Ok(Err(e)) => Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Error::ResourceCreation,
+                "IAGJ-E01",
+                "Failed to generate annotations.",
+                Some(e),
+            )),
+            Err(e) => Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Error::ResourceCreation,
+                "IAGJ-E02",
+                "Annotation generation task failed.",
+                Some(e.into()),
+            )),
+        };
+
+        let attached = match attached {
+            Ok(attached) => attached,
+            Err((status_code, error, id, message, details)) => {
+                let reply = ServerMsg::Error(ErrorServerMsg {
+                    request_id: None,
+                    error: format!("{error:?}"),
+                    id: id.to_string(),
+                    message: message.to_string(),
+                });
+                if let Err(e) = csm.send_user(user_id, reply).await {
+                    logger.log_error(
+                        Error::WebSocketSend,
+                        "IAGJ-E04",
+                        "Failed to notify user of the failure.",
+                        Some(e),
+                    );
+                }
+
+                logger.error(status_code, error, id, message, details);
+                return;
+            }
+        };
+
+        logger.log("Generated annotations and saved them to disk.");
+
+        // [COMMS]: Broadcast the new layers to connected clients.
+        match crate::api::image::annotations::attach::broadcast_layers(
+            &csm,
+            store_id,
+            image_id,
+            vec![],
+            &attached,
+        )
+        .await
+        {
+            Ok(()) => {
+                logger.success(StatusCode::CREATED, "Annotations generated successfully.");
+            }
+            Err(e) => {
+                logger.error(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Error::ResponseIntegrity,
+                    "IAGJ-E03",
+                    "Failed to encode annotation layer message.",
+                    Some(e),
+                );
+            }
+        }
+    });
+}
+
+async fn broadcast_progress(
+    csm: &ClientSocketManager,
+    store_id: u32,
+    image_id: u32,
+    generator: &str,
+    progress: f64,
+) -> anyhow::Result<()> {
+    csm.broadcast(
+        store_id,
+        ServerMsg::Generation(GenerationServerMsg {
+            store_id,
+            image_id,
+            progress,
+            generator: generator.into(),
+        }),
+    )
+    .await
+}
@@ -8,7 +8,7 @@ pub struct PathParams {
     annotation_layer_id: u32,
 }
 
-pub async fn annotations(
+pub async fn layer(
     Extension(dbm): Extension<Arc<DatabaseManager>>,
     Extension(mut logger): Extension<Logger<'_>>,
     Path(PathParams {
@@ -0,0 +1,9 @@
+pub mod attach;
+pub mod create;
+pub mod delete;
+pub mod export;
+pub mod generate;
+pub mod layer;
+pub mod query;
+pub mod region;
+pub mod update;
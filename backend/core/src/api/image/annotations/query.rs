@@ -0,0 +1,70 @@
+use crate::api::prelude::*;
+use crate::constants::{ANNOTATION_QUERY_LIMIT, MAX_ANNOTATION_QUERY_LIMIT};
+use crate::types::annotation::PropertyFilter;
+
+#[derive(Deserialize)]
+pub struct PathParams {
+    store_id: u32,
+    image_id: u32,
+}
+
+#[derive(Deserialize)]
+pub struct QueryParams {
+    layer: Option<u32>,
+    // Comma separated property conditions, e.g. `prob>0.9,type=tumour`.
+    filter: Option<String>,
+    limit: Option<u32>,
+    offset: Option<u32>,
+}
+
+/// Lists the annotations of an image whose properties match the filter.
+pub async fn query(
+    Extension(dbm): Extension<Arc<DatabaseManager>>,
+    Extension(mut logger): Extension<Logger<'_>>,
+    Path(PathParams { store_id, image_id }): Path<PathParams>,
+    Query(QueryParams {
+        layer,
+        filter,
+        limit,
+        offset,
+    }): Query<QueryParams>,
+) -> Response {
+    let filters = match PropertyFilter::parse_all(filter.as_deref().unwrap_or_default()) {
+        Ok(filters) => filters,
+        Err(e) => {
+            return logger.error(
+                StatusCode::BAD_REQUEST,
+                Error::RequestIntegrity,
+                "IAQ-E00",
+                "Invalid property filter.",
+                Some(e),
+            );
+        }
+    };
+
+    let limit = limit
+        .unwrap_or(ANNOTATION_QUERY_LIMIT)
+        .min(MAX_ANNOTATION_QUERY_LIMIT);
+
+    match crate::db::annotation::query(
+        &dbm,
+        store_id,
+        image_id,
+        layer,
+        &filters,
+        limit,
+        offset.unwrap_or_default(),
+    ) {
+        Ok(annotations) => {
+            logger.success(StatusCode::OK, "Retrieved annotations successfully.");
+            Json(annotations).into_response()
+        }
+        Err(e) => logger.error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Error::DatabaseQuery,
+            "IAQ-E01",
+            "Failed to retrieve annotations.",
+            Some(e),
+        ),
+    }
+}
@@ -0,0 +1,234 @@
+use crate::api::prelude::*;
+use crate::constants::{ANNOTATION_MIN_EXTENT, ANNOTATION_SIMPLIFY_TOLERANCE, MAX_REGION_SIZE};
+use crate::geometry::Mesh;
+use axum::{body::Bytes, http::header::CONTENT_TYPE};
+use shared::types::{Bounds, MetadataLayer};
+
+#[derive(Deserialize)]
+pub struct PathParams {
+    store_id: u32,
+    image_id: u32,
+    annotation_layer_id: u32,
+}
+
+#[derive(Deserialize)]
+pub struct QueryParams {
+    // Rectangle in level 0 pixels.
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    // Level the annotations are drawn at, which sets how much detail they keep.
+    level: Option<u32>,
+}
+
+/// Annotations of a layer drawn within a rectangle, as a GLB.
+/// Below full resolution, outlines are simplified and annotations smaller than a pixel dropped.
+pub async fn region(
+    Extension(dbm): Extension<Arc<DatabaseManager>>,
+    Extension(mut logger): Extension<Logger<'_>>,
+    Path(PathParams {
+        store_id,
+        image_id,
+        annotation_layer_id,
+    }): Path<PathParams>,
+    Query(QueryParams {
+        x,
+        y,
+        width,
+        height,
+        level,
+    }): Query<QueryParams>,
+) -> Response {
+    let level = level.unwrap_or_default();
+
+    let layers = match crate::db::image::metadata_layers(&dbm, store_id, image_id) {
+        Ok(layers) if !layers.is_empty() => layers,
+        Ok(_) => {
+            return logger.error(
+                StatusCode::NOT_FOUND,
+                Error::ResourceExistence,
+                "IAR-E00",
+                "Image does not exist.",
+                None,
+            );
+        }
+        Err(e) => {
+            return logger.error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Error::DatabaseQuery,
+                "IAR-E01",
+                "Failed to retrieve image metadata.",
+                Some(e),
+            );
+        }
+    };
+
+    let Some(layer) = layers.iter().find(|layer| layer.level == level) else {
+        return logger.error(
+            StatusCode::BAD_REQUEST,
+            Error::RequestIntegrity,
+            "IAR-E02",
+            "Level does not exist.",
+            None,
+        );
+    };
+
+    logger.report(Check::RequestIntegrity, "Level exists.");
+
+    let Detail {
+        limit,
+        tolerance,
+        min_extent,
+    } = detail(&layers[0], layer);
+
+    if width == 0 || height == 0 || f64::from(width) > limit || f64::from(height) > limit {
+        return logger.error(
+            StatusCode::BAD_REQUEST,
+            Error::RequestIntegrity,
+            "IAR-E05",
+            "Region is empty or exceeds the maximum region size.",
+            None,
+        );
+    }
+
+    logger.report(Check::RequestIntegrity, "Region is within bounds.");
+
+    let region = Bounds {
+        min_x: f64::from(x),
+        min_y: f64::from(y),
+        max_x: f64::from(x) + f64::from(width),
+        max_y: f64::from(y) + f64::from(height),
+    };
+
+    // Querying and triangulating many annotations is blocking.
+    let result = tokio::task::spawn_blocking(move || {
+        let geometries = crate::db::annotation::region(
+            &dbm,
+            store_id,
+            image_id,
+            annotation_layer_id,
+            region,
+            min_extent,
+        )?;
+
+        let mut mesh = Mesh::default();
+        for geometry in &geometries {
+            match tolerance {
+                None => mesh.push(geometry),
+                Some(tolerance) => {
+                    if let Some(geometry) = crate::geometry::simplify(geometry, tolerance) {
+                        mesh.push(&geometry);
+                    }
+                }
+            }
+        }
+
+        anyhow::Ok(mesh.to_glb())
+    })
+    .await;
+
+    let glb = match result {
+        Ok(Ok(glb)) => glb,
+        Ok(Err(e)) => {
+            return logger.error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Error::DatabaseQuery,
+                "IAR-E03",
+                "Failed to retrieve annotations in region.",
+                Some(e),
+            );
+        }
+        Err(e) => {
+            return logger.error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Error::ResourceRead,
+                "IAR-E04",
+                "Annotation region task failed.",
+                Some(e.into()),
+            );
+        }
+    };
+
+    logger.success(StatusCode::OK, "Retrieved annotation region successfully.");
+
+    (
+        StatusCode::OK,
+        [(CONTENT_TYPE, "model/gltf-binary")],
+        Bytes::from(glb),
+    )
+        .into_response()
+}
+
+/// Detail annotations are drawn with at a level, in level 0 pixels.
+#[derive(Debug, PartialEq)]
+struct Detail {
+    // Longest side of the rectangle. As for images, it is capped at the resolution it is drawn at.
+    limit: f64,
+    // Outlines are simplified to this tolerance, or kept whole without one.
+    tolerance: Option<f64>,
+    // Annotations whose bounds are smaller than this are dropped.
+    min_extent: f64,
+}
+
+/// Full resolution keeps every annotation in full detail. Lower levels scale the limits
+/// by how many level 0 pixels each of their pixels covers.
+fn detail(base: &MetadataLayer, layer: &MetadataLayer) -> Detail {
+    let downsample = f64::from(base.width) / f64::from(layer.width);
+    let simplified = layer.level > 0;
+
+    Detail {
+        limit: f64::from(MAX_REGION_SIZE) * downsample,
+        tolerance: simplified.then_some(downsample * ANNOTATION_SIMPLIFY_TOLERANCE),
+        min_extent: if simplified {
+            downsample * ANNOTATION_MIN_EXTENT
+        } else {
+            0.0
+        },
+    }
+}
+
+#[cfg(test)]
+// Downsamples are powers of two, whose products are exact.
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::*;
+
+    fn layer(level: u32, width: u32) -> MetadataLayer {
+        MetadataLayer {
+            level,
+            cols: 1,
+            rows: 1,
+            width,
+            height: width,
+        }
+    }
+
+    #[test]
+    fn full_resolution_keeps_full_detail() {
+        let base = layer(0, 40000);
+
+        assert_eq!(
+            detail(&base, &base),
+            Detail {
+                limit: f64::from(MAX_REGION_SIZE),
+                tolerance: None,
+                min_extent: 0.0,
+            }
+        );
+    }
+
+    #[test]
+    fn lower_levels_scale_with_downsample() {
+        let base = layer(0, 40000);
+
+        assert_eq!(
+            detail(&base, &layer(2, 10000)),
+            Detail {
+                limit: f64::from(MAX_REGION_SIZE) * 4.0,
+                tolerance: Some(ANNOTATION_SIMPLIFY_TOLERANCE * 4.0),
+                min_extent: ANNOTATION_MIN_EXTENT * 4.0,
+            }
+        );
+    }
+}
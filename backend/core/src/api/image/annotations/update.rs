@@ -0,0 +1,146 @@
+use crate::api::prelude::*;
+use shared::types::{Geometry, Properties};
+
+#[derive(Deserialize)]
+pub struct PathParams {
+    store_id: u32,
+    image_id: u32,
+    annotation_layer_id: u32,
+    annotation_id: u32,
+}
+
+#[derive(Deserialize)]
+pub struct Body {
+    geometry: Option<Geometry>,
+    // Replaces all properties of the annotation.
+    properties: Option<Properties>,
+}
+
+pub async fn update(
+    Extension(csm): Extension<Arc<ClientSocketManager>>,
+    Extension(dbm): Extension<Arc<DatabaseManager>>,
+    Extension(mut logger): Extension<Logger<'_>>,
+    Path(PathParams {
+        store_id,
+        image_id,
+        annotation_layer_id,
+        annotation_id,
+    }): Path<PathParams>,
+    Json(Body {
+        geometry,
+        properties,
+    }): Json<Body>,
+) -> Response {
+    if geometry
+        .as_ref()
+        .is_some_and(|geometry| geometry.bounds().is_none())
+    {
+        return logger.error(
+            StatusCode::BAD_REQUEST,
+            Error::RequestIntegrity,
+            "IAU-E00",
+            "Annotation geometry has no coordinates.",
+            None,
+        );
+    }
+
+    // Only a new geometry changes what is drawn.
+    let rebuild = geometry.is_some();
+
+    // [DATABASE]: Update annotation in the database.
+    let annotation = match crate::db::annotation::update(
+        &dbm,
+        store_id,
+        image_id,
+        annotation_layer_id,
+        annotation_id,
+        geometry,
+        properties,
+    ) {
+        Ok(Some(annotation)) => {
+            logger.log("Annotation updated in the database.");
+            annotation
+        }
+        Ok(None) => {
+            return logger.error(
+                StatusCode::NOT_FOUND,
+                Error::ResourceExistence,
+                "IAU-E01",
+                "Annotation does not exist.",
+                None,
+            );
+        }
+        Err(e) => {
+            return logger.error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Error::DatabaseInsertion,
+                "IAU-E02",
+                "Failed to update annotation in the database.",
+                Some(e),
+            );
+        }
+    };
+
+    // [IO]: Rebuild the GLB of the layer. Triangulating is blocking.
+    if rebuild {
+        let result = tokio::task::spawn_blocking(move || {
+            crate::geometry::rebuild_glb(&dbm, store_id, image_id, annotation_layer_id)
+        })
+        .await;
+
+        match result {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => {
+                return logger.error(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Error::ResourceCreation,
+                    "IAU-E03",
+                    "Failed to compute annotation geometry.",
+                    Some(e),
+                );
+            }
+            Err(e) => {
+                return logger.error(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Error::ResourceCreation,
+                    "IAU-E05",
+                    "Annotation geometry task failed.",
+                    Some(e.into()),
+                );
+            }
+        }
+    }
+
+    // [COMMS]: Broadcast annotation update message to connected clients.
+    let broadcast = match annotation.to_json() {
+        Ok((geometry, properties)) => {
+            csm.broadcast(
+                store_id,
+                ServerMsg::Annotation(AnnotationServerMsg::Update {
+                    store_id,
+                    image_id,
+                    layer_id: annotation_layer_id,
+                    id: annotation_id,
+                    geometry,
+                    properties,
+                }),
+            )
+            .await
+        }
+        Err(e) => Err(e.into()),
+    };
+
+    match broadcast {
+        Ok(()) => {
+            logger.success(StatusCode::OK, "Annotation updated successfully.");
+            Json(annotation).into_response()
+        }
+        Err(e) => logger.error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Error::ResponseIntegrity,
+            "IAU-E04",
+            "Failed to encode annotation update message.",
+            Some(e),
+        ),
+    }
+}
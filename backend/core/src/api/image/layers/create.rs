@@ -0,0 +1,116 @@
+use crate::api::prelude::*;
+
+#[derive(Deserialize)]
+pub struct PathParams {
+    store_id: u32,
+    image_id: u32,
+}
+
+#[derive(Deserialize)]
+pub struct Body {
+    tag: String,
+    fill: Option<String>,
+}
+
+pub async fn create(
+    Extension(csm): Extension<Arc<ClientSocketManager>>,
+    Extension(dbm): Extension<Arc<DatabaseManager>>,
+    Extension(mut logger): Extension<Logger<'_>>,
+    Path(PathParams { store_id, image_id }): Path<PathParams>,
+    Json(Body { tag, fill }): Json<Body>,
+) -> Response {
+    if tag.trim().is_empty() {
+        return logger.error(
+            StatusCode::BAD_REQUEST,
+            Error::RequestIntegrity,
+            "ILC-E00",
+            "Annotation layer tag cannot be empty.",
+            None,
+        );
+    }
+
+    // [DATABASE]: Insert annotation layer into the database.
+    let layer = match crate::db::annotation::create_layer(&dbm, store_id, image_id, &tag, fill) {
+        Ok(Some(layer)) => {
+            logger.log("Annotation layer inserted into the database.");
+            layer
+        }
+        Ok(None) => {
+            return logger.error(
+                StatusCode::NOT_FOUND,
+                Error::ResourceExistence,
+                "ILC-E01",
+                "Image does not exist.",
+                None,
+            );
+        }
+        Err(e) => {
+            return logger.error(
+                StatusCode::CONFLICT,
+                Error::DatabaseInsertion,
+                "ILC-E02",
+                "Failed to insert annotation layer into the database.",
+                Some(e),
+            );
+        }
+    };
+
+    // Ids of layers created here are allocated by the database as u32.
+    #[allow(clippy::cast_possible_truncation)]
+    let layer_id = layer.id as u32;
+
+    // [IO]: Write the GLB of the empty layer, so it can be loaded like any other.
+    // Writing it is blocking.
+    let result = tokio::task::spawn_blocking(move || {
+        crate::geometry::rebuild_glb(&dbm, store_id, image_id, layer_id)
+    })
+    .await;
+
+    match result {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => {
+            return logger.error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Error::ResourceCreation,
+                "ILC-E03",
+                "Failed to compute annotation geometry.",
+                Some(e),
+            );
+        }
+        Err(e) => {
+            return logger.error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Error::ResourceCreation,
+                "ILC-E05",
+                "Annotation geometry task failed.",
+                Some(e.into()),
+            );
+        }
+    }
+
+    // [COMMS]: Broadcast annotation layer create message to connected clients.
+    match csm
+        .broadcast(
+            store_id,
+            ServerMsg::AnnotationLayer(AnnotationLayerServerMsg::create(
+                store_id, image_id, &layer,
+            )),
+        )
+        .await
+    {
+        Ok(()) => {
+            logger.success(
+                StatusCode::CREATED,
+                "Annotation layer created successfully.",
+            );
+            (StatusCode::CREATED, Json(layer)).into_response()
+        }
+        Err(e) => logger.error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Error::ResponseIntegrity,
+            "ILC-E04",
+            "Failed to encode annotation layer create message.",
+            Some(e),
+        ),
+    }
+}
@@ -0,0 +1,80 @@
+use crate::api::prelude::*;
+
+#[derive(Deserialize)]
+pub struct PathParams {
+    store_id: u32,
+    image_id: u32,
+    annotation_layer_id: u32,
+}
+
+pub async fn delete(
+    Extension(csm): Extension<Arc<ClientSocketManager>>,
+    Extension(dbm): Extension<Arc<DatabaseManager>>,
+    Extension(mut logger): Extension<Logger<'_>>,
+    Path(PathParams {
+        store_id,
+        image_id,
+        annotation_layer_id,
+    }): Path<PathParams>,
+) -> Response {
+    // [DATABASE]: Delete annotation layer and its annotations from the database.
+    match crate::db::annotation::delete_layer(&dbm, store_id, image_id, annotation_layer_id) {
+        Ok(true) => logger.log("Annotation layer deleted from the database."),
+        Ok(false) => {
+            return logger.error(
+                StatusCode::NOT_FOUND,
+                Error::ResourceExistence,
+                "ILD-E00",
+                "Annotation layer does not exist.",
+                None,
+            );
+        }
+        Err(e) => {
+            return logger.error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Error::DatabaseDeletion,
+                "ILD-E01",
+                "Failed to delete annotation layer from the database.",
+                Some(e),
+            );
+        }
+    }
+
+    // [IO]: Remove the GLB of the layer. Layers without annotations may have none.
+    let removed = crate::db::image::annotation_path(&dbm, store_id, image_id, annotation_layer_id)
+        .and_then(|path| match std::fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        });
+    if let Err(e) = removed {
+        return logger.error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Error::ResourceDeletion,
+            "ILD-E02",
+            "Failed to remove GLB annotation layer file.",
+            Some(e),
+        );
+    }
+
+    // [COMMS]: Broadcast annotation layer delete message to connected clients.
+    match csm
+        .broadcast(
+            store_id,
+            ServerMsg::AnnotationLayer(AnnotationLayerServerMsg::Delete {
+                store_id,
+                image_id,
+                id: annotation_layer_id,
+            }),
+        )
+        .await
+    {
+        Ok(()) => logger.success(StatusCode::OK, "Annotation layer deleted successfully."),
+        Err(e) => logger.error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Error::ResponseIntegrity,
+            "ILD-E03",
+            "Failed to encode annotation layer delete message.",
+            Some(e),
+        ),
+    }
+}
@@ -0,0 +1,107 @@
+use crate::api::prelude::*;
+use crate::types::annotation::{LayerChanges, is_colour};
+
+#[derive(Deserialize)]
+pub struct PathParams {
+    store_id: u32,
+    image_id: u32,
+    annotation_layer_id: u32,
+}
+
+/// Renames a layer or changes its style, which is shared by everyone viewing the image.
+pub async fn update(
+    Extension(csm): Extension<Arc<ClientSocketManager>>,
+    Extension(dbm): Extension<Arc<DatabaseManager>>,
+    Extension(mut logger): Extension<Logger<'_>>,
+    Path(PathParams {
+        store_id,
+        image_id,
+        annotation_layer_id,
+    }): Path<PathParams>,
+    Json(changes): Json<LayerChanges>,
+) -> Response {
+    if let Some(tag) = &changes.tag
+        && tag.trim().is_empty()
+    {
+        return logger.error(
+            StatusCode::BAD_REQUEST,
+            Error::RequestIntegrity,
+            "ILU-E00",
+            "Annotation layer tag cannot be empty.",
+            None,
+        );
+    }
+
+    let valid_opacity = changes
+        .opacity
+        .is_none_or(|opacity| (0.0..=1.0).contains(&opacity));
+    let valid_colours = [&changes.fill, &changes.stroke]
+        .into_iter()
+        .flatten()
+        .all(|colour| is_colour(colour));
+    if !valid_opacity || !valid_colours {
+        return logger.error(
+            StatusCode::BAD_REQUEST,
+            Error::RequestIntegrity,
+            "ILU-E01",
+            "Opacity must be between 0 and 1 and colours must be #RRGGBB.",
+            None,
+        );
+    }
+
+    // [DATABASE]: Update annotation layer in the database.
+    // The GLB is left as is, since the style is applied by the viewer.
+    let layer = match crate::db::annotation::update_layer(
+        &dbm,
+        store_id,
+        image_id,
+        annotation_layer_id,
+        changes,
+    ) {
+        Ok(Some(layer)) => {
+            logger.log("Annotation layer updated in the database.");
+            layer
+        }
+        Ok(None) => {
+            return logger.error(
+                StatusCode::NOT_FOUND,
+                Error::ResourceExistence,
+                "ILU-E02",
+                "Annotation layer does not exist.",
+                None,
+            );
+        }
+        Err(e) => {
+            return logger.error(
+                StatusCode::CONFLICT,
+                Error::DatabaseInsertion,
+                "ILU-E03",
+                "Failed to update annotation layer in the database.",
+                Some(e),
+            );
+        }
+    };
+
+    // [COMMS]: Broadcast annotation layer update message to connected clients.
+    match csm
+        .broadcast(
+            store_id,
+            ServerMsg::AnnotationLayer(AnnotationLayerServerMsg::update(
+                store_id, image_id, &layer,
+            )),
+        )
+        .await
+    {
+        Ok(()) => {
+            logger.success(StatusCode::OK, "Annotation layer updated successfully.");
+            Json(layer).into_response()
+        }
+        Err(e) => logger.error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Error::ResponseIntegrity,
+            "ILU-E04",
+            "Failed to encode annotation layer update message.",
+            Some(e),
+        ),
+    }
+}
@@ -1,7 +1,9 @@
 pub mod annotations;
 pub mod delete;
+pub mod layers;
 pub mod r#move;
 pub mod properties;
+pub mod region;
 pub mod thumbnail;
 pub mod tiles;
 pub mod upload;
@@ -0,0 +1,243 @@
+use crate::api::prelude::*;
+use crate::constants::MAX_REGION_SIZE;
+use crate::types::messages::TileFormat;
+use axum::{
+    body::Bytes,
+    http::header::{CACHE_CONTROL, CONTENT_TYPE},
+};
+use shared::types::{Address, Region, Size};
+
+#[derive(Deserialize)]
+pub struct PathParams {
+    store_id: u32,
+    image_id: u32,
+}
+
+#[derive(Deserialize)]
+pub struct QueryParams {
+    // Rectangle in level 0 pixels.
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    // Output pixels per level 0 pixel.
+    scale: Option<f64>,
+    // Output resolution in microns per pixel, in place of `scale`.
+    mpp: Option<f64>,
+    format: Option<String>,
+}
+
+/// Reads an arbitrary rectangle of an image, resampled to the requested scale or
+/// physical resolution.
+pub async fn region(
+    Extension(dbm): Extension<Arc<DatabaseManager>>,
+    Extension(mut logger): Extension<Logger<'_>>,
+    Path(PathParams { store_id, image_id }): Path<PathParams>,
+    Query(QueryParams {
+        x,
+        y,
+        width,
+        height,
+        scale,
+        mpp,
+        format,
+    }): Query<QueryParams>,
+) -> Response {
+    let format = match format.as_deref().unwrap_or("png") {
+        "png" => TileFormat::Png,
+        "tif" | "tiff" => TileFormat::Tiff,
+        "raw" => TileFormat::Raw,
+        _ => {
+            return logger.error(
+                StatusCode::BAD_REQUEST,
+                Error::RequestIntegrity,
+                "IR-E00",
+                "Format must be one of png, tiff or raw.",
+                None,
+            );
+        }
+    };
+
+    if scale.is_some() && mpp.is_some() {
+        return logger.error(
+            StatusCode::BAD_REQUEST,
+            Error::RequestIntegrity,
+            "IR-E09",
+            "Only one of scale and mpp may be given.",
+            None,
+        );
+    }
+
+    let invalid = |value: f64| !value.is_finite() || value <= 0.0;
+    if scale.is_some_and(invalid) || mpp.is_some_and(invalid) {
+        return logger.error(
+            StatusCode::BAD_REQUEST,
+            Error::RequestIntegrity,
+            "IR-E01",
+            "Scale and mpp must be greater than zero.",
+            None,
+        );
+    }
+
+    let layers = match crate::db::image::metadata_layers(&dbm, store_id, image_id) {
+        Ok(layers) if !layers.is_empty() => layers,
+        Ok(_) => {
+            return logger.error(
+                StatusCode::NOT_FOUND,
+                Error::ResourceExistence,
+                "IR-E02",
+                "Image does not exist.",
+                None,
+            );
+        }
+        Err(e) => {
+            return logger.error(
+                StatusCode::NOT_FOUND,
+                Error::DatabaseQuery,
+                "IR-E03",
+                "Failed to retrieve image metadata.",
+                Some(e),
+            );
+        }
+    };
+
+    // A physical resolution is relative to that of the slide.
+    let scale = match mpp {
+        Some(mpp) => match crate::db::image::microns_per_pixel(&dbm, store_id, image_id) {
+            Ok(Some(image_mpp)) => image_mpp / mpp,
+            Ok(None) => {
+                return logger.error(
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    Error::RequestIntegrity,
+                    "IR-E10",
+                    "Image does not record its resolution, use scale instead.",
+                    None,
+                );
+            }
+            Err(e) => {
+                return logger.error(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Error::DatabaseQuery,
+                    "IR-E11",
+                    "Failed to retrieve image resolution.",
+                    Some(e),
+                );
+            }
+        },
+        None => scale.unwrap_or(1.0),
+    };
+
+    // Crop the rectangle to the bounds of the image.
+    let (image_width, image_height) = (layers[0].width, layers[0].height);
+    if width == 0 || height == 0 || x >= image_width || y >= image_height {
+        return logger.error(
+            StatusCode::BAD_REQUEST,
+            Error::RequestIntegrity,
+            "IR-E04",
+            "Region is empty or outside the image.",
+            None,
+        );
+    }
+
+    let region = Region {
+        size: Size {
+            width: width.min(image_width - x),
+            height: height.min(image_height - y),
+        },
+        level: 0,
+        address: Address { x, y },
+    };
+
+    let Some(output) = output_size(&region.size, scale) else {
+        return logger.error(
+            StatusCode::BAD_REQUEST,
+            Error::RequestIntegrity,
+            "IR-E05",
+            "Requested output exceeds the maximum region size.",
+            None,
+        );
+    };
+
+    if !crate::io::within_source_limit(&layers, &region, &output) {
+        return logger.error(
+            StatusCode::BAD_REQUEST,
+            Error::RequestIntegrity,
+            "IR-E12",
+            "Requested region reads too many source pixels.",
+            None,
+        );
+    }
+
+    logger.report(Check::RequestIntegrity, "Region is within bounds.");
+
+    let path = match crate::db::image::image_path(&dbm, store_id, image_id) {
+        Ok(path) => path,
+        Err(e) => {
+            return logger.error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Error::DatabaseQuery,
+                "IR-E06",
+                "Failed to retrieve image path.",
+                Some(e),
+            );
+        }
+    };
+
+    // Reading and resampling the region is blocking.
+    let result = tokio::task::spawn_blocking(move || {
+        let image = crate::io::region(&path, &layers, &region, &output)?;
+        crate::io::encode(image, format)
+    })
+    .await;
+
+    let buffer = match result {
+        Ok(Ok(buffer)) => buffer,
+        Ok(Err(e)) => {
+            return logger.error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Error::ResourceRead,
+                "IR-E07",
+                "Failed to read image region.",
+                Some(e),
+            );
+        }
+        Err(e) => {
+            return logger.error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Error::ResourceRead,
+                "IR-E08",
+                "Image region task failed.",
+                Some(e.into()),
+            );
+        }
+    };
+
+    logger.success(StatusCode::OK, "Retrieved image region successfully.");
+
+    (
+        StatusCode::OK,
+        [
+            (CONTENT_TYPE, format.content_type()),
+            (CACHE_CONTROL, "private, max-age=86400"),
+        ],
+        Bytes::from(buffer),
+    )
+        .into_response()
+}
+
+/// Dimensions of the scaled region, or `None` if they exceed `MAX_REGION_SIZE`.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn output_size(size: &Size, scale: f64) -> Option<Size> {
+    let width = (f64::from(size.width) * scale).round().max(1.0);
+    let height = (f64::from(size.height) * scale).round().max(1.0);
+
+    let limit = f64::from(MAX_REGION_SIZE);
+    if width > limit || height > limit {
+        return None;
+    }
+
+    Some(Size {
+        width: width as u32,
+        height: height as u32,
+    })
+}
@@ -1,37 +1,281 @@
 use crate::api::prelude::*;
-use crate::types::messages::{TileClientMsg, TileServerMsg};
+use crate::types::{
+    messages::{TileClientMsg, TileFormat, TileServerMsg, ViewportBounds, ViewportClientMsg},
+    socket::SocketError,
+};
+use axum::{
+    body::Bytes,
+    http::{
+        HeaderMap,
+        header::{CACHE_CONTROL, CONTENT_TYPE, ETAG, IF_NONE_MATCH},
+    },
+};
+use shared::constants::TILE_SIZE;
+
+#[derive(Deserialize)]
+pub struct PathParams {
+    store_id: u32,
+    image_id: u32,
+    level: u32,
+    x: u32,
+    // Tile row and extension, e.g. `3.jpeg`.
+    file: String,
+}
+
+/// Serves a single tile over HTTP so it can be fetched and cached without the websocket.
+pub async fn tile(
+    Extension(dbm): Extension<Arc<DatabaseManager>>,
+    Extension(mut logger): Extension<Logger<'_>>,
+    Path(PathParams {
+        store_id,
+        image_id,
+        level,
+        x,
+        file,
+    }): Path<PathParams>,
+    headers: HeaderMap,
+) -> Response {
+    let Some((y, format)) = file.split_once('.').and_then(|(y, extension)| {
+        Some((
+            y.parse::<u32>().ok()?,
+            TileFormat::from_extension(extension)?,
+        ))
+    }) else {
+        return logger.error(
+            StatusCode::BAD_REQUEST,
+            Error::RequestIntegrity,
+            "ITL-E00",
+            "Tile must be requested as {y}.{ext} with a supported extension.",
+            None,
+        );
+    };
+
+    // [CHECK]: Tile must exist at the requested level.
+    match crate::db::image::metadata_layer(&dbm, store_id, image_id, level) {
+        Ok(layer) if x < layer.cols && y < layer.rows => {
+            logger.report(Check::ResourceExistence, "Tile is within image bounds.");
+        }
+        Ok(_) | Err(_) => {
+            return logger.error(
+                StatusCode::NOT_FOUND,
+                Error::ResourceExistence,
+                "ITL-E01",
+                "Tile does not exist.",
+                None,
+            );
+        }
+    }
+
+    // Images are never re-encoded in place, so a tile's identity is enough to validate it.
+    let etag = format!("\"s{store_id}-i{image_id}-l{level}-{x}-{file}\"");
+
+    if headers
+        .get(IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.split(',').any(|tag| tag.trim() == etag))
+    {
+        logger.success(StatusCode::NOT_MODIFIED, "Tile not modified.");
+        return (StatusCode::NOT_MODIFIED, [(ETAG, etag)]).into_response();
+    }
+
+    let path = match crate::db::image::image_path(&dbm, store_id, image_id) {
+        Ok(path) => path,
+        Err(e) => {
+            return logger.error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Error::DatabaseQuery,
+                "ITL-E02",
+                "Failed to retrieve image path.",
+                Some(e),
+            );
+        }
+    };
+
+    let buffer =
+        match tokio::task::spawn_blocking(move || crate::io::retrieve(&path, level, x, y, format))
+            .await
+        {
+            Ok(Ok(buffer)) => buffer,
+            Ok(Err(e)) => {
+                return logger.error(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Error::ResourceRead,
+                    "ITL-E03",
+                    "Failed to retrieve tile.",
+                    Some(e),
+                );
+            }
+            Err(e) => {
+                return logger.error(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Error::ResourceRead,
+                    "ITL-E04",
+                    "Tile retrieval task failed.",
+                    Some(e.into()),
+                );
+            }
+        };
+
+    logger.success(StatusCode::OK, "Retrieved tile successfully.");
+
+    (
+        StatusCode::OK,
+        [
+            (CONTENT_TYPE, format.content_type().to_string()),
+            (
+                CACHE_CONTROL,
+                "public, max-age=31536000, immutable".to_string(),
+            ),
+            (ETAG, etag),
+        ],
+        Bytes::from(buffer),
+    )
+        .into_response()
+}
 
-// TODO: Capture large rectangles of selections rather than individual tiles.
 // TODO: Cache in an in-memory HashMap?
 pub fn tiles(
     dbm: &DatabaseManager,
     TileClientMsg {
+        request_id,
         store_id,
-        id,
+        image_id,
         level,
         x,
         y,
+        format,
     }: TileClientMsg,
-) -> Result<TileServerMsg, String> {
-    let path = match crate::db::image::image_path(dbm, store_id, id) {
+) -> Result<TileServerMsg, SocketError> {
+    let format = format.unwrap_or_default();
+
+    let path = match crate::db::image::image_path(dbm, store_id, image_id) {
         Ok(path) => path,
         Err(e) => {
-            println!("WebSocket Error: Failed to retrieve path for image with id: {id}. {e}");
-            return Err(format!(
-                "WebSocket Error: Failed to retrieve path for image with id: {id}. {e}"
+            return Err(SocketError::new(
+                Error::DatabaseQuery,
+                "ITL-E05",
+                "Failed to retrieve image path.",
+                Some(e),
             ));
         }
     };
 
-    let tile = match crate::io::retrieve(&path, level, x, y) {
-        Ok(tile) => tile,
+    let buffer = match crate::io::retrieve(&path, level, x, y, format) {
+        Ok(buffer) => buffer,
         Err(e) => {
-            println!("WebSocket Error: Failed to retrieve tile for image with id: {id}. {e}");
-            return Err(format!(
-                "WebSocket Error: Failed to retrieve tile for image with id: {id}. {e}"
+            return Err(SocketError::new(
+                Error::ResourceRead,
+                "ITL-E06",
+                "Failed to retrieve tile.",
+                Some(e),
             ));
         }
     };
 
-    Ok(tile)
+    // Echo the full request identity so clients can route the tile to the right view.
+    Ok(TileServerMsg {
+        request_id,
+        store_id,
+        image_id,
+        level,
+        x,
+        y,
+        format,
+        buffer,
+    })
+}
+
+/// Expands a viewport request into the individual tiles it covers.
+pub fn viewport(
+    dbm: &DatabaseManager,
+    ViewportClientMsg {
+        request_id,
+        store_id,
+        image_id,
+        level,
+        bounds,
+        format,
+    }: ViewportClientMsg,
+) -> Result<Vec<TileClientMsg>, SocketError> {
+    let layer = match crate::db::image::metadata_layer(dbm, store_id, image_id, level) {
+        Ok(layer) => layer,
+        Err(e) => {
+            return Err(SocketError::new(
+                Error::ResourceExistence,
+                "ITL-E07",
+                "Level does not exist.",
+                Some(e),
+            ));
+        }
+    };
+
+    if layer.cols == 0 || layer.rows == 0 {
+        return Ok(vec![]);
+    }
+
+    let (x_start, y_start, x_end, y_end) = match bounds {
+        ViewportBounds::Tiles {
+            x_start,
+            y_start,
+            x_end,
+            y_end,
+        } => (x_start, y_start, x_end, y_end),
+        ViewportBounds::Region {
+            x,
+            y,
+            width,
+            height,
+        } => {
+            let base = match crate::db::image::metadata_layer(dbm, store_id, image_id, 0) {
+                Ok(base) => base,
+                Err(e) => {
+                    return Err(SocketError::new(
+                        Error::DatabaseQuery,
+                        "ITL-E08",
+                        "Failed to retrieve image metadata.",
+                        Some(e),
+                    ));
+                }
+            };
+
+            // Scale level 0 coordinates down to the requested level.
+            let x_scale = f64::from(layer.width) / f64::from(base.width);
+            let y_scale = f64::from(layer.height) / f64::from(base.height);
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let to_tile = |value: u32, scale: f64| {
+                (f64::from(value) * scale / f64::from(TILE_SIZE)).floor() as u32
+            };
+
+            (
+                to_tile(x, x_scale),
+                to_tile(y, y_scale),
+                to_tile(x.saturating_add(width.saturating_sub(1)), x_scale),
+                to_tile(y.saturating_add(height.saturating_sub(1)), y_scale),
+            )
+        }
+    };
+
+    // Clamp the range to the tiles that exist at this level.
+    let x_end = x_end.min(layer.cols - 1);
+    let y_end = y_end.min(layer.rows - 1);
+
+    if x_start > x_end || y_start > y_end {
+        return Ok(vec![]);
+    }
+
+    let tiles = (y_start..=y_end)
+        .flat_map(|y| {
+            (x_start..=x_end).map(move |x| TileClientMsg {
+                request_id,
+                store_id,
+                image_id,
+                level,
+                x,
+                y,
+                format,
+            })
+        })
+        .collect();
+
+    Ok(tiles)
 }
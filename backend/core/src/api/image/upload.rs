@@ -1,15 +1,14 @@
 use crate::api::prelude::*;
 use crate::constants::{
-    ANNOTATIONS_PATH_PREFIX, BIN_ID, IMAGE_NAME, THUMBNAIL_NAME, TRANSLATED_ANNOTATIONS_PATH,
-    UPLOADED_ANNOTATIONS_PATH, UPLOADED_IMAGE_PATH,
+    ANNOTATIONS_PATH_PREFIX, BIN_ID, IMAGE_NAME, THUMBNAIL_NAME, UPLOADED_ANNOTATIONS_PATH,
+    UPLOADED_IMAGE_PATH,
 };
-use anyhow::anyhow;
+use crate::types::{generator::GeneratorRegistry, user::User};
 use axum_typed_multipart::{FieldData, TryFromMultipart, TypedMultipart};
 use shared::{
     traits::{Encoder, Generator},
     types::{AnnotationLayer, MetadataLayer},
 };
-use std::{fs, process::Command};
 use tempfile::NamedTempFile;
 
 #[derive(Deserialize)]
@@ -46,7 +45,10 @@ fn extract_extension(filename: &Option<String>) -> Option<String> {
 // TODO: Perform checks on files before saving them to avoid malware.
 // TODO: Sanitise file name.
 pub async fn upload(
+    Extension(user): Extension<User>,
+    Extension(csm): Extension<Arc<ClientSocketManager>>,
     Extension(dbm): Extension<Arc<DatabaseManager>>,
+    Extension(registry): Extension<Arc<GeneratorRegistry>>,
     Extension(mut logger): Extension<Logger<'_>>,
     Path(PathParams {
         store_id,
@@ -131,7 +133,7 @@ pub async fn upload(
     };
 
     // Get the generator object that will be used to translate or generate annotations.
-    let generator_object = match generator.as_ref().map(|g| generators::export::get(g)) {
+    let generator_object = match generator.as_ref().map(|g| registry.get(g)) {
         Some(Some(generator)) => {
             logger.report(Check::ResourceExistence, "Generator found.");
             Some(generator)
@@ -172,23 +174,31 @@ pub async fn upload(
         }
     };
 
+    // If user provides annotations file, translate it and compute buffer geometries.
+    // Else generate annotations once the image is stored.
     let mut annotation_layers = Vec::new();
-    if let Some(generator_object) = generator_object {
-        if let Some(uploaded_annotations_extension) = &uploaded_annotations_extension {
-            annotation_layers = match handle_annotations(
+    let mut pending_generator = None;
+    match (generator_object, annotations_file) {
+        (Some(generator_object), Some(annotations_file)) => {
+            let extension = uploaded_annotations_extension
+                .as_deref()
+                .unwrap_or_default();
+            annotation_layers = match translate_annotations(
                 &mut logger,
                 &path,
-                uploaded_annotations_extension,
+                extension,
                 annotations_file,
-                generator_object,
+                &generator_object,
             ) {
                 Ok(layers) => layers,
                 Err(response) => return response,
             };
         }
-    };
+        (Some(generator_object), None) => pending_generator = Some(generator_object),
+        (None, _) => {}
+    }
 
-    let (decoder, metadata_layers) = match handle_image(
+    let (decoder, microns_per_pixel, metadata_layers) = match handle_image(
         &mut logger,
         image_file,
         &path,
@@ -210,6 +220,7 @@ pub async fn upload(
         generator.as_deref(),
         &uploaded_image_extension,
         uploaded_annotations_extension.as_deref(),
+        microns_per_pixel,
         metadata_layers,
         annotation_layers,
     ) {
@@ -230,6 +241,19 @@ pub async fn upload(
         }
     }
 
+    if let Some(generator_object) = pending_generator {
+        crate::api::image::annotations::generate::spawn(
+            csm,
+            dbm,
+            user.id,
+            store_id,
+            image_id,
+            generator_object,
+            (path.join(UPLOADED_IMAGE_PATH), uploaded_image_extension),
+        );
+        logger.log("Started generating annotations.");
+    }
+
     logger.success(StatusCode::CREATED, "Successfully uploaded assets.");
 
     (StatusCode::OK).into_response()
@@ -241,7 +265,7 @@ fn handle_image(
     path: &std::path::Path,
     extension: &str,
     encoder: &Box<dyn Encoder>,
-) -> Result<(String, Vec<MetadataLayer>), Response> {
+) -> Result<(String, Option<f64>, Vec<MetadataLayer>), Response> {
     // Path where the uploaded image will be stored.
     let uploaded_image_path = path.join(UPLOADED_IMAGE_PATH);
 
@@ -273,9 +297,9 @@ fn handle_image(
         &thumbnail_path,
         encoder,
     ) {
-        Ok((decoder, metadata)) => {
+        Ok(converted) => {
             logger.log("Successfully converted image to Zarr.");
-            Ok((decoder, metadata))
+            Ok(converted)
         }
         Err(e) => {
             return Err(logger.error(
@@ -289,31 +313,6 @@ fn handle_image(
     }
 }
 
-fn handle_annotations(
-    logger: &mut Logger<'_>,
-    path: &std::path::Path,
-    extension: &str,
-    file: Option<FieldData<NamedTempFile>>,
-    generator: Box<dyn Generator>,
-) -> Result<Vec<AnnotationLayer>, Response> {
-    // If user provides annotations file, translate it and compute buffer geometries.
-    // Else generate annotations and compute buffer geometries.
-    match file {
-        Some(file) => translate_annotations(logger, path, extension, file, &generator),
-        None => generate_annotations(logger, path, extension, generator),
-    }
-}
-
-// TODO: Generate annotations.
-fn generate_annotations(
-    _logger: &mut Logger<'_>,
-    _path: &std::path::Path,
-    _extension: &str,
-    _generator: Box<dyn Generator>,
-) -> Result<Vec<AnnotationLayer>, Response> {
-    todo!("Generating annotations not supported yet.")
-}
-
 fn translate_annotations(
     logger: &mut Logger<'_>,
     path: &std::path::Path,
@@ -324,9 +323,6 @@ fn translate_annotations(
     // Path where the uploaded annotations file will be stored.
     let uploaded_annotations_path = path.join(format!("{UPLOADED_ANNOTATIONS_PATH}.{extension}"));
 
-    // Path where intermediate Three.js buffer geometries will be stored.
-    let translated_annotations_path = path.join(TRANSLATED_ANNOTATIONS_PATH);
-
     // Path where the GLB annotations will be stored.
     let final_annotations_path = path.join(ANNOTATIONS_PATH_PREFIX);
 
@@ -361,72 +357,19 @@ fn translate_annotations(
         }
     };
 
-    // TODO: Use capnproto rather than storing an intermediate translated annotations json file.
-    // TODO: Or, try writing own buffer geometry creator/gltf lib in Rust.
-    // Serialize annotation layers.
-    let Ok(serialized_annotation_layers) = serde_json::to_string(&annotation_layers) else {
-        return Err(logger.error(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Error::ResourceCreation,
-            "IUTA-E02",
-            "Failed to serialise annotations file.",
-            None,
-        ));
-    };
-
-    fs::write(&translated_annotations_path, serialized_annotation_layers).map_err(|e| {
-        return logger.error(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Error::ResourceCreation,
-            "IUTA-E03",
-            "Failed to save translated annotations file to disk.",
-            Some(e.into()),
-        );
-    })?;
-
-    // Compute annotation positions and normals.
-    match Command::new("node")
-        .arg("--max-old-space-size=4096")
-        .arg("./geometry-computer/index.js")
-        .arg(translated_annotations_path)
-        .arg(final_annotations_path)
-        .output()
-    {
-        Ok(output) => {
-            if output.status.success() {
-                logger.log(
-                    "Successfully computed annotation positions and normals and saved to disk.",
-                );
-            } else {
-                let e = String::from_utf8(output.stderr).map_err(|e| {
-                    return logger.error(
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Error::ResourceCreation,
-                        "IUTA-E04",
-                        "Failed to convert stderr to string.",
-                        Some(e.into()),
-                    );
-                })?;
-
-                return Err(logger.error(
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Error::ResourceCreation,
-                    "IUTA-E05",
-                    "Failed to compute annotation positions and normals.",
-                    Some(anyhow!(e)),
-                ));
-            }
-        }
-        Err(e) => {
+    // Triangulate annotations into a GLB per layer.
+    for layer in &annotation_layers {
+        if let Err(e) = crate::geometry::write_glb(layer, &final_annotations_path) {
             return Err(logger.error(
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Error::ResourceCreation,
-                "IUTA-E06",
-                "Failed to run geometry computation.",
-                Some(e.into()),
+                "IUTA-E02",
+                "Failed to compute annotation geometry.",
+                Some(e),
             ));
         }
     }
+    logger.log("Successfully computed annotation geometries and saved to disk.");
 
     Ok(annotation_layers)
 }
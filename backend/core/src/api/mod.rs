@@ -1,5 +1,7 @@
+pub mod deepzoom;
 pub mod directory;
 pub mod generators;
+pub mod iiif;
 pub mod image;
 mod prelude;
 pub mod registry;
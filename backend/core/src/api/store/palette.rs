@@ -0,0 +1,60 @@
+use crate::api::prelude::*;
+use crate::types::annotation::is_colour;
+
+#[derive(Deserialize)]
+pub struct Params {
+    store_id: u32,
+}
+
+/// Colours given to new annotation layers of the store, in turn.
+pub async fn palette(
+    Extension(dbm): Extension<Arc<DatabaseManager>>,
+    Extension(mut logger): Extension<Logger<'_>>,
+    Path(Params { store_id }): Path<Params>,
+) -> Response {
+    match crate::db::palette::get(&dbm, store_id) {
+        Ok(palette) => {
+            logger.success(StatusCode::OK, "Retrieved palette.");
+            Json(palette).into_response()
+        }
+        Err(e) => logger.error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Error::DatabaseQuery,
+            "SP-E00",
+            "Failed to retrieve palette.",
+            Some(e),
+        ),
+    }
+}
+
+/// Replaces the palette of the store. Existing layers keep their colours.
+pub async fn update(
+    Extension(dbm): Extension<Arc<DatabaseManager>>,
+    Extension(mut logger): Extension<Logger<'_>>,
+    Path(Params { store_id }): Path<Params>,
+    Json(palette): Json<Vec<String>>,
+) -> Response {
+    if palette.is_empty() || !palette.iter().all(|colour| is_colour(colour)) {
+        return logger.error(
+            StatusCode::BAD_REQUEST,
+            Error::RequestIntegrity,
+            "SPU-E00",
+            "Palette must hold at least one colour, each as #RRGGBB.",
+            None,
+        );
+    }
+
+    match crate::db::palette::set(&dbm, store_id, &palette) {
+        Ok(()) => {
+            logger.success(StatusCode::OK, "Updated palette.");
+            Json(palette).into_response()
+        }
+        Err(e) => logger.error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Error::DatabaseInsertion,
+            "SPU-E01",
+            "Failed to update palette.",
+            Some(e),
+        ),
+    }
+}
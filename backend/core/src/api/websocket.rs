@@ -1,16 +1,31 @@
 use crate::api::prelude::*;
+use crate::constants::{
+    MAX_CONCURRENT_TILES, WEBSOCKET_HEARTBEAT_INTERVAL, WEBSOCKET_IDLE_TIMEOUT,
+    WEBSOCKET_QUEUE_SIZE,
+};
 use crate::types::{
-    messages::{ClientMsg, ServerMsg},
+    messages::{
+        CancelClientMsg, CapabilitiesServerMsg, ClientMsg, ErrorServerMsg, FormatClientMsg,
+        HelloClientMsg, HelloServerMsg, ServerMsg, SubscribeClientMsg, TileClientMsg, TileFormat,
+        TileServerMsg, UnsubscribeClientMsg,
+    },
+    protocol::{MIN_PROTOCOL_VERSION, PROTOCOL_VERSION},
+    scheduler::{TileKey, TileScheduler},
+    socket::SocketError,
     user::User,
 };
-use axum::extract::{WebSocketUpgrade, ws::Message};
+use axum::{
+    body::Bytes,
+    extract::{WebSocketUpgrade, ws::Message},
+    http::Method,
+};
 use futures_util::{SinkExt, StreamExt};
-use tokio::sync::mpsc;
+use tokio::sync::{broadcast::error::RecvError, mpsc};
 
 pub async fn websocket(
     Extension(user): Extension<User>,
     Extension(db): Extension<Arc<DatabaseManager>>,
-    // Extension(mut logger): Extension<Logger<'_>>,
+    Extension(mut logger): Extension<Logger<'static>>,
     Extension(csm): Extension<Arc<ClientSocketManager>>,
     ws: WebSocketUpgrade,
 ) -> impl IntoResponse {
@@ -18,90 +33,328 @@ pub async fn websocket(
 
     ws.on_upgrade(move |socket| async move {
         let (mut sink, mut stream) = socket.split();
-        let (sender, mut receiver) = mpsc::channel::<Message>(8);
+        let (sender, mut receiver) = mpsc::channel::<Message>(WEBSOCKET_QUEUE_SIZE);
 
         // Insert the sender into connections for usage across other endpoints.
-        csm.add_connection(user.id, sender.clone());
+        // The manager holds the only sender, so removing the connection ends the sink task.
+        let session_id = csm.add_connection(user.id, sender);
 
         let mut broadcast_receiver = csm.broadcast.subscribe();
 
-        tokio::spawn(async move {
-            loop {
-                tokio::select! {
-                    // Send direct messages to user.
-                    msg = receiver.recv() => {
-                        if let Some(msg) = msg {
-                            sink.send(msg).await.ok();
-                        } else {
-                            break;
-                        }
-                    }
-                    // Send broadcasts to user.
-                    Ok(msg) = broadcast_receiver.recv() => {
-                        sink.send(msg).await.ok();
+        let mut sink_task = tokio::spawn({
+            let csm = Arc::clone(&csm);
+
+            async move {
+                let mut heartbeat = tokio::time::interval(WEBSOCKET_HEARTBEAT_INTERVAL);
+
+                loop {
+                    let msg = tokio::select! {
+                        // Send direct messages to user.
+                        msg = receiver.recv() => match msg {
+                            Some(msg) => msg,
+                            None => break,
+                        },
+                        // Send broadcasts for subscribed stores to user.
+                        msg = broadcast_receiver.recv() => match msg {
+                            Ok((store_id, msg)) if csm.is_subscribed(session_id, store_id) => msg,
+                            Ok(_) => continue,
+                            // Broadcasts were dropped, so the client's view may be stale.
+                            Err(RecvError::Lagged(_)) => {
+                                match TryInto::<Message>::try_into(ServerMsg::Resync) {
+                                    Ok(msg) => msg,
+                                    Err(_) => continue,
+                                }
+                            }
+                            Err(RecvError::Closed) => break,
+                        },
+                        // Keep the connection alive through proxies.
+                        _ = heartbeat.tick() => Message::Ping(Bytes::new()),
+                    };
+
+                    if sink.send(msg).await.is_err() {
+                        break;
                     }
                 }
-            }
 
-            // Cleanup on disconnect.
-            sink.close().await.ok();
+                // Cleanup on disconnect.
+                sink.close().await.ok();
+            }
         });
 
-        // Handle incoming messages.
-        while let Some(message) = stream.next().await {
+        // Queue of tile requests for this connection.
+        let scheduler = Arc::new(TileScheduler::new(MAX_CONCURRENT_TILES));
+
+        // Dispatch queued tile requests, a bounded number at a time.
+        {
+            let scheduler = Arc::clone(&scheduler);
             let csm = Arc::clone(&csm);
             let db = Arc::clone(&db);
 
             tokio::spawn(async move {
-                let message = match message {
-                    Ok(Message::Binary(message)) => message,
-                    Ok(Message::Text(_)) => return,
-                    Ok(Message::Ping(_)) => return,
-                    Ok(Message::Pong(_)) => return,
-                    _ => {
-                        csm.remove_connection(user.id);
-                        //logger.success(StatusCode::OK, "Client disconnected");
-                        return;
-                    }
-                };
-
-                let message = match ClientMsg::try_from(message) {
-                    Ok(message) => message,
-                    Err(_) => {
-                        // logger.error(
-                        //     StatusCode::BAD_REQUEST,
-                        //     Error::WebSocketParse,
-                        //     "WS-E00",
-                        //     "Failed to parse client message.",
-                        //     Some(e.into()),
-                        // );
-                        return;
+                while let Some((tile_request, permit)) = scheduler.next().await {
+                    let scheduler = Arc::clone(&scheduler);
+                    let csm = Arc::clone(&csm);
+                    let db = Arc::clone(&db);
+
+                    tokio::spawn(async move {
+                        send_tile(&csm, db, &scheduler, session_id, tile_request).await;
+                        drop(permit);
+                    });
+                }
+            });
+        }
+
+        // Protocol version agreed in the handshake.
+        let mut protocol_version = None;
+
+        // Tile format used when a request does not specify one.
+        let mut format = TileFormat::default();
+
+        // Handle incoming messages.
+        loop {
+            let message = tokio::select! {
+                // Any frame, including pongs to our heartbeat, counts as activity.
+                message = tokio::time::timeout(WEBSOCKET_IDLE_TIMEOUT, stream.next()) => message,
+                // The sink failed or the session was dropped by the manager.
+                _ = &mut sink_task => break,
+            };
+
+            let message = match message {
+                Ok(Some(Ok(Message::Binary(message)))) => message,
+                // Pings are answered by the socket itself.
+                Ok(Some(Ok(Message::Text(_) | Message::Ping(_) | Message::Pong(_)))) => continue,
+                // Closed, errored or idle for too long.
+                _ => break,
+            };
+
+            let message = match ClientMsg::try_from(message) {
+                Ok(message) => message,
+                Err(e) => {
+                    let e = SocketError::new(
+                        Error::WebSocketParse,
+                        "WS-E00",
+                        "Failed to parse client message.",
+                        Some(e.into()),
+                    );
+                    send_error(&csm, session_id, None, e).await;
+                    continue;
+                }
+            };
+
+            // Nothing but the handshake is accepted until a version is agreed.
+            if protocol_version.is_none() && !matches!(message, ClientMsg::Hello(_)) {
+                let e = SocketError::new(
+                    Error::RequestIntegrity,
+                    "WS-E02",
+                    "Handshake required before other messages.",
+                    None,
+                );
+                send_error(&csm, session_id, None, e).await;
+                continue;
+            }
+
+            match message {
+                ClientMsg::Hello(HelloClientMsg { version }) => {
+                    let version = version.min(PROTOCOL_VERSION);
+                    if version < MIN_PROTOCOL_VERSION {
+                        let e = SocketError::new(
+                            Error::RequestIntegrity,
+                            "WS-E03",
+                            "Protocol version is no longer supported.",
+                            Some(anyhow::anyhow!(
+                                "Client speaks up to version {version}, server accepts {MIN_PROTOCOL_VERSION} to {PROTOCOL_VERSION}."
+                            )),
+                        );
+                        send_error(&csm, session_id, None, e).await;
+                        break;
                     }
-                };
-
-                match message {
-                    ClientMsg::Tile(tile_request) => {
-                        match crate::api::image::tiles::tiles(&db, tile_request) {
-                            Ok(tile_response) => {
-                                let _ = csm.send(user.id, ServerMsg::Tile(tile_response)).await;
-                                // else {
-                                //     logger.error(
-                                //         StatusCode::INTERNAL_SERVER_ERROR,
-                                //         Error::WebSocketSend,
-                                //         "WS-E01",
-                                //         "Failed to send message.",
-                                //         None,
-                                //     );
-                                //     return;
-                                // };
-                            }
-                            Err(e) => {
-                                let _ = csm.send(user.id, ServerMsg::Error(e)).await;
-                            }
+
+                    protocol_version = Some(version);
+
+                    let _ = csm
+                        .send(session_id, ServerMsg::Hello(HelloServerMsg { version }))
+                        .await;
+
+                    // Advertise the supported tile formats.
+                    let _ = csm
+                        .send(
+                            session_id,
+                            ServerMsg::Capabilities(CapabilitiesServerMsg {
+                                formats: TileFormat::names(),
+                                default_format: TileFormat::default(),
+                            }),
+                        )
+                        .await;
+                }
+                ClientMsg::Tile(mut tile_request) => {
+                    tile_request.format.get_or_insert(format);
+                    scheduler.push(tile_request);
+                }
+                ClientMsg::Viewport(mut viewport_request) => {
+                    viewport_request.format.get_or_insert(format);
+                    let request_id = viewport_request.request_id;
+
+                    match crate::api::image::tiles::viewport(&db, viewport_request) {
+                        Ok(tile_requests) => {
+                            scheduler.push_viewport(tile_requests);
+                        }
+                        Err(e) => {
+                            send_error(&csm, session_id, Some(request_id), e).await;
                         }
                     }
                 }
-            });
+                ClientMsg::Cancel(CancelClientMsg {
+                    store_id,
+                    image_id,
+                    level,
+                }) => {
+                    scheduler.cancel(store_id, image_id, level);
+                }
+                ClientMsg::Format(FormatClientMsg { format: new_format }) => {
+                    format = new_format;
+                }
+                ClientMsg::Subscribe(SubscribeClientMsg { store_id }) => {
+                    // [CHECK]: Store must exist. Per-user permissions are not implemented yet.
+                    if let Err(e) = db.store_properties(store_id) {
+                        let e = SocketError::new(
+                            Error::ResourceExistence,
+                            "WS-E05",
+                            "Store does not exist.",
+                            Some(e),
+                        );
+                        send_error(&csm, session_id, None, e).await;
+                        continue;
+                    }
+
+                    csm.subscribe(session_id, store_id);
+                }
+                ClientMsg::Unsubscribe(UnsubscribeClientMsg { store_id }) => {
+                    csm.unsubscribe(session_id, store_id);
+                }
+            }
         }
+
+        // Cleanup on every exit path. Stop retrieving tiles nobody is waiting for.
+        csm.remove_connection(session_id);
+        scheduler.close();
+
+        logger.success(StatusCode::OK, "Client disconnected.");
     })
 }
+
+async fn send_tile(
+    csm: &ClientSocketManager,
+    db: Arc<DatabaseManager>,
+    scheduler: &TileScheduler,
+    session_id: u64,
+    tile_request: TileClientMsg,
+) {
+    let key = TileKey::from(&tile_request);
+
+    // Tile retrieval and compression are blocking.
+    let result =
+        tokio::task::spawn_blocking(move || crate::api::image::tiles::tiles(&db, tile_request))
+            .await
+            .unwrap_or_else(|e| {
+                Err(SocketError::new(
+                    Error::ResourceRead,
+                    "WS-E04",
+                    "Tile retrieval task failed.",
+                    Some(e.into()),
+                ))
+            });
+
+    // Requests for the same tile that arrived while it was pending share the reply.
+    let request_ids = scheduler.complete(&key);
+
+    match result {
+        Ok(tile_response) => {
+            for request_id in request_ids {
+                let reply = ServerMsg::Tile(TileServerMsg {
+                    request_id,
+                    ..tile_response.clone()
+                });
+                if let Err(e) = csm.send(session_id, reply).await {
+                    log_error(
+                        session_id,
+                        Error::WebSocketSend,
+                        "WS-E01",
+                        "Failed to send message.",
+                        Some(e),
+                    );
+                }
+            }
+        }
+        Err(e) => {
+            // The failure is logged once, every other request is only told about it.
+            for &request_id in request_ids.iter().skip(1) {
+                let reply = ServerMsg::Error(ErrorServerMsg {
+                    request_id: Some(request_id),
+                    error: format!("{:?}", e.error),
+                    id: e.id.to_string(),
+                    message: e.message.to_string(),
+                });
+                if let Err(e) = csm.send(session_id, reply).await {
+                    log_error(
+                        session_id,
+                        Error::WebSocketSend,
+                        "WS-E01",
+                        "Failed to send message.",
+                        Some(e),
+                    );
+                }
+            }
+            send_error(csm, session_id, request_ids.first().copied(), e).await;
+        }
+    }
+}
+
+/// Logs a failure and reports it to the client, along with the request it belongs to.
+async fn send_error(
+    csm: &ClientSocketManager,
+    session_id: u64,
+    request_id: Option<u32>,
+    SocketError {
+        error,
+        id,
+        message,
+        details,
+    }: SocketError,
+) {
+    let reply = ServerMsg::Error(ErrorServerMsg {
+        request_id,
+        error: format!("{error:?}"),
+        id: id.to_string(),
+        message: message.to_string(),
+    });
+
+    log_error(session_id, error, id, message, details);
+
+    if let Err(e) = csm.send(session_id, reply).await {
+        log_error(
+            session_id,
+            Error::WebSocketSend,
+            "WS-E01",
+            "Failed to send message.",
+            Some(e),
+        );
+    }
+}
+
+/// Prints a failure as it happens. A connection can stay open for as long as the client
+/// likes, so its errors are not kept until it closes.
+fn log_error(
+    session_id: u64,
+    error: Error,
+    id: &'static str,
+    message: &'static str,
+    details: Option<anyhow::Error>,
+) {
+    let mut logger = Logger::start(
+        Method::GET,
+        "/api/websocket".into(),
+        format!("session={session_id}"),
+    );
+    logger.log_error(error, id, message, details);
+    logger.flush();
+}
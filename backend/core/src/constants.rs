@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 pub static LOCAL_STORES_PATH: &str = env!("LOCAL_STORES_PATH");
 pub static LOCAL_DATABASES_PATH: &str = env!("LOCAL_DATABASES_PATH");
 pub static REGISTRY_PATH: &str = concat!(env!("LOCAL_DATABASES_PATH"), "registry.sqlite");
@@ -11,12 +13,58 @@ pub static UPLOADED_DIRECTORY: &str = "uploaded";
 pub static ANNOTATIONS_DIRECTORY: &str = "annotations";
 pub static UPLOADED_IMAGE_PATH: &str = "uploaded/image";
 pub static UPLOADED_ANNOTATIONS_PATH: &str = "uploaded/annotations";
-pub static TRANSLATED_ANNOTATIONS_PATH: &str = "uploaded/annotations.json";
 pub static IMAGE_NAME: &str = "image.zarr";
 pub static THUMBNAIL_NAME: &str = "thumbnail.jpeg";
 pub static ANNOTATIONS_PATH_PREFIX: &str = "annotations/a";
 
+// Colours given to new annotation layers in turn, until a store's palette is changed.
+pub static DEFAULT_PALETTE: [&str; 8] = [
+    "#FF0000", // Red
+    "#FF7F00", // Orange
+    "#FFFF00", // Yellow
+    "#0000FF", // Blue
+    "#FF1493", // DeepPink
+    "#4B0082", // Indigo
+    "#8B00FF", // Violet
+    "#00FF00", // Green
+];
+
+// Size of point and line annotations when drawn, in level 0 pixels.
+pub static ANNOTATION_POINT_RADIUS: f64 = 8.0;
+pub static ANNOTATION_POINT_VERTICES: u32 = 16;
+pub static ANNOTATION_LINE_WIDTH: f64 = 4.0;
+// Detail dropped from annotations below full resolution, in screen pixels.
+pub static ANNOTATION_SIMPLIFY_TOLERANCE: f64 = 0.5;
+pub static ANNOTATION_MIN_EXTENT: f64 = 1.0;
+
+// Number of annotations returned by a query, unless the request asks for fewer or more.
+pub static ANNOTATION_QUERY_LIMIT: u32 = 1000;
+pub static MAX_ANNOTATION_QUERY_LIMIT: u32 = 10000;
+
+// Environment variable naming the JSON file that configures external generators.
+pub static EXTERNAL_GENERATORS_VAR: &str = "EXTERNAL_GENERATORS_PATH";
+// Lines an external generator prints to stdout to report the fraction of work done.
+pub static EXTERNAL_GENERATOR_PROGRESS_PREFIX: &str = "PROGRESS ";
+pub static EXTERNAL_GENERATOR_POLL_INTERVAL: Duration = Duration::from_millis(100);
+// Trailing stderr lines kept in the error of a failed external generator.
+pub static EXTERNAL_GENERATOR_ERROR_LINES: usize = 20;
+
 pub static MAX_THUMBNAIL_SIZE: u32 = 256;
+pub static MAX_CONCURRENT_TILES: usize = 8;
+pub static MAX_REGION_SIZE: u32 = 8192;
+// Pixels read from the image for one region, before it is resampled.
+pub static MAX_REGION_SOURCE_PIXELS: u64 = 8192 * 8192;
+
+pub static WEBSOCKET_QUEUE_SIZE: usize = 32;
+pub static WEBSOCKET_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+pub static WEBSOCKET_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+pub static WEBSOCKET_SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub static IIIF_CONTEXT: &str = "http://iiif.io/api/image/3/context.json";
+pub static IIIF_PROFILE: &str = "http://iiif.io/api/image/3/level1.json";
+
+pub static DEEPZOOM_TILE_SIZE: u32 = 254;
+pub static DEEPZOOM_OVERLAP: u32 = 1;
 
 pub static PRIVILEGED: [u32; 2] = [ROOT_ID, BIN_ID];
 pub static ROOT_ID: u32 = 0;
@@ -0,0 +1,808 @@
+use crate::db::prelude::*;
+use crate::types::annotation::{AnnotationRecord, LayerChanges, LayerRecord, PropertyFilter};
+use anyhow::anyhow;
+use rusqlite::{
+    OptionalExtension, Row, params_from_iter,
+    types::{Type, Value},
+};
+use shared::types::{Annotation, AnnotationLayer, Bounds, Geometry, Properties};
+
+/// Creates the annotation tables, for new stores and stores created before they existed.
+pub fn create_tables(conn: &Connection) -> Result<()> {
+    // Stores created before layer styles were kept lack their columns.
+    let mut stmt =
+        conn.prepare("SELECT 1 FROM pragma_table_info('annotation_layer') WHERE name = 'stroke';")?;
+    if !stmt.exists([])? {
+        conn.execute_batch(
+            r#"
+                ALTER TABLE annotation_layer ADD COLUMN visible INTEGER NOT NULL DEFAULT 1;
+                ALTER TABLE annotation_layer ADD COLUMN opacity REAL NOT NULL DEFAULT 0.5;
+                ALTER TABLE annotation_layer ADD COLUMN stroke TEXT NOT NULL DEFAULT '#000000';
+            "#,
+        )?;
+    }
+
+    conn.execute_batch(
+        r#"
+            CREATE TABLE IF NOT EXISTS annotations (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                image_id INTEGER NOT NULL,
+                layer_id INTEGER NOT NULL,
+                geometry TEXT NOT NULL,
+                properties TEXT NOT NULL,
+                min_x REAL NOT NULL,
+                min_y REAL NOT NULL,
+                max_x REAL NOT NULL,
+                max_y REAL NOT NULL,
+                FOREIGN KEY (image_id) REFERENCES images (id) ON DELETE CASCADE
+            );
+
+            CREATE INDEX IF NOT EXISTS annotations_image_layer
+            ON annotations (image_id, layer_id);
+
+            CREATE TABLE IF NOT EXISTS annotation_layer_counter (
+                image_id INTEGER PRIMARY KEY,
+                next_id INTEGER NOT NULL,
+                FOREIGN KEY (image_id) REFERENCES images (id) ON DELETE CASCADE
+            );
+        "#,
+    )?;
+
+    let mut stmt = conn.prepare("SELECT 1 FROM sqlite_master WHERE name = 'annotations_index';")?;
+    if stmt.exists([])? {
+        return Ok(());
+    }
+
+    // Spatial index of the drawn bounds of annotations, with the image and layer as
+    // extra dimensions so a viewport query only visits one layer.
+    conn.execute_batch(
+        r#"
+            CREATE VIRTUAL TABLE annotations_index USING rtree (
+                id,
+                min_x, max_x,
+                min_y, max_y,
+                min_image, max_image,
+                min_layer, max_layer
+            );
+        "#,
+    )?;
+
+    // Annotations stored before the index existed.
+    let mut stmt = conn.prepare("SELECT id, image_id, layer_id, geometry FROM annotations;")?;
+    let annotations = stmt
+        .query_map([], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, json(row, 3)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for (id, image_id, layer_id, geometry) in annotations {
+        index(conn, id, image_id, layer_id, &geometry)?;
+    }
+
+    Ok(())
+}
+
+/// Indexes the drawn bounds of an annotation, replacing its previous entry.
+fn index(
+    conn: &Connection,
+    id: i64,
+    image_id: u32,
+    layer_id: u32,
+    geometry: &Geometry,
+) -> Result<()> {
+    let bounds = crate::geometry::drawn_bounds(geometry)
+        .ok_or_else(|| anyhow!("Annotation geometry has no coordinates."))?;
+
+    let mut stmt = conn.prepare_cached(
+        "
+            INSERT OR REPLACE INTO annotations_index (id, min_x, max_x, min_y, max_y, min_image, max_image, min_layer, max_layer)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6, ?7, ?7);
+        ",
+    )?;
+
+    stmt.execute((
+        id,
+        bounds.min_x,
+        bounds.max_x,
+        bounds.min_y,
+        bounds.max_y,
+        image_id,
+        layer_id,
+    ))?;
+
+    Ok(())
+}
+
+/// Stores every annotation of the layers, along with its properties and bounding box.
+pub fn insert(
+    conn: &Connection,
+    image_id: u32,
+    annotation_layers: &[AnnotationLayer],
+) -> Result<()> {
+    let mut stmt = conn.prepare_cached(
+        "
+            INSERT INTO annotations (image_id, layer_id, geometry, properties, min_x, min_y, max_x, max_y)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8);
+        ",
+    )?;
+
+    for layer in annotation_layers {
+        for annotation in &layer.annotations {
+            // Geometry without coordinates has nothing to draw or query.
+            let Some(bounds) = annotation.geometry.bounds() else {
+                continue;
+            };
+
+            stmt.execute((
+                image_id,
+                layer.id,
+                serde_json::to_string(&annotation.geometry)?,
+                serde_json::to_string(&annotation.properties)?,
+                bounds.min_x,
+                bounds.min_y,
+                bounds.max_x,
+                bounds.max_y,
+            ))?;
+
+            index(
+                conn,
+                conn.last_insert_rowid(),
+                image_id,
+                u32::try_from(layer.id)?,
+                &annotation.geometry,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn delete_image(conn: &Connection, image_id: u32) -> Result<()> {
+    let mut stmt = conn.prepare_cached(
+        "
+            DELETE FROM annotations_index
+            WHERE id IN (SELECT id FROM annotations WHERE image_id = ?1);
+        ",
+    )?;
+    stmt.execute([image_id])?;
+
+    let mut stmt = conn.prepare_cached("DELETE FROM annotations WHERE image_id = ?1;")?;
+    stmt.execute([image_id])?;
+
+    Ok(())
+}
+
+/// Annotation layer of an image holding all of its annotations, or `None` if it does not exist.
+pub fn layer(conn: &Connection, image_id: u32, layer_id: u32) -> Result<Option<AnnotationLayer>> {
+    let mut stmt = conn.prepare_cached(
+        "
+            SELECT id, tag, visible, opacity, colour, stroke
+            FROM annotation_layer
+            WHERE image_id = ?1 AND id = ?2;
+        ",
+    )?;
+
+    let Some(mut layer) = stmt
+        .query_row([image_id, layer_id], annotation_layer)
+        .optional()?
+    else {
+        return Ok(None);
+    };
+
+    let mut stmt = conn.prepare_cached(
+        "
+            SELECT geometry, properties
+            FROM annotations
+            WHERE image_id = ?1 AND layer_id = ?2
+            ORDER BY id;
+        ",
+    )?;
+
+    layer.annotations = stmt
+        .query_map([image_id, layer_id], |row| {
+            Ok(Annotation {
+                geometry: json(row, 0)?,
+                properties: json(row, 1)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Some(layer))
+}
+
+/// Reserves `count` layer ids of an image, returning the first. Ids keep increasing as
+/// layers are deleted, so clients do not confuse a new layer with a removed one.
+/// Images without a counter yet start after their existing layers.
+fn reserve_layer_ids(conn: &Connection, image_id: u32, count: u32) -> Result<u32> {
+    let mut stmt = conn.prepare_cached(
+        "
+            INSERT INTO annotation_layer_counter (image_id, next_id)
+            SELECT ?1, COALESCE(MAX(id) + 1, 0) + ?2
+            FROM annotation_layer
+            WHERE image_id = ?1
+            ON CONFLICT (image_id) DO UPDATE SET next_id = next_id + ?2
+            RETURNING next_id - ?2;
+        ",
+    )?;
+    let id = stmt.query_row([image_id, count], |row| row.get(0))?;
+
+    Ok(id)
+}
+
+/// Saves a layer without its annotations, giving it a colour from the palette if it has
+/// no fill.
+pub fn insert_layer(conn: &Connection, image_id: u32, layer: &mut AnnotationLayer) -> Result<()> {
+    if layer.fill.is_none() {
+        layer.fill = Some(crate::db::palette::colour(conn, layer.id)?);
+    }
+
+    let mut stmt = conn.prepare_cached(
+        "
+            INSERT INTO annotation_layer (id, image_id, tag, visible, opacity, colour, stroke)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7);
+        ",
+    )?;
+    stmt.execute((
+        layer.id,
+        image_id,
+        &layer.tag,
+        layer.visible,
+        layer.opacity,
+        &layer.fill,
+        &layer.stroke,
+    ))?;
+
+    Ok(())
+}
+
+/// Adds a layer without annotations, or returns `None` if the image does not exist.
+/// Without a fill, the layer takes the next colour of the palette.
+pub fn create_layer(
+    dbm: &DatabaseManager,
+    store_id: u32,
+    image_id: u32,
+    tag: &str,
+    fill: Option<String>,
+) -> Result<Option<AnnotationLayer>> {
+    let conn = dbm.store(store_id)?;
+
+    let mut stmt = conn.prepare_cached("SELECT 1 FROM images WHERE id = ?1;")?;
+    if !stmt.exists([image_id])? {
+        return Ok(None);
+    }
+
+    // Layer ids are only unique within an image.
+    let id = reserve_layer_ids(&conn, image_id, 1)?;
+
+    let mut layer = AnnotationLayer::new(id as usize, tag.into(), fill);
+    insert_layer(&conn, image_id, &mut layer)?;
+
+    Ok(Some(layer))
+}
+
+/// Renames or restyles a layer, or returns `None` if it does not exist.
+pub fn update_layer(
+    dbm: &DatabaseManager,
+    store_id: u32,
+    image_id: u32,
+    layer_id: u32,
+    changes: LayerChanges,
+) -> Result<Option<AnnotationLayer>> {
+    let conn = dbm.store(store_id)?;
+
+    let mut stmt = conn.prepare_cached(
+        "
+            UPDATE annotation_layer
+            SET tag = COALESCE(?1, tag),
+                visible = COALESCE(?2, visible),
+                opacity = COALESCE(?3, opacity),
+                colour = COALESCE(?4, colour),
+                stroke = COALESCE(?5, stroke)
+            WHERE image_id = ?6 AND id = ?7
+            RETURNING id, tag, visible, opacity, colour, stroke;
+        ",
+    )?;
+
+    let layer = stmt
+        .query_row(
+            (
+                changes.tag,
+                changes.visible,
+                changes.opacity,
+                changes.fill,
+                changes.stroke,
+                image_id,
+                layer_id,
+            ),
+            annotation_layer,
+        )
+        .optional()?;
+
+    Ok(layer)
+}
+
+/// Removes a layer along with its annotations, returning whether it existed.
+pub fn delete_layer(
+    dbm: &DatabaseManager,
+    store_id: u32,
+    image_id: u32,
+    layer_id: u32,
+) -> Result<bool> {
+    let mut conn = dbm.store(store_id)?;
+
+    let transaction = conn.transaction()?;
+
+    // Start the counter, if there is none, while the id of the layer is still taken.
+    reserve_layer_ids(&transaction, image_id, 0)?;
+
+    let deleted = transaction.execute(
+        "DELETE FROM annotation_layer WHERE image_id = ?1 AND id = ?2;",
+        [image_id, layer_id],
+    )?;
+    transaction.execute(
+        "
+            DELETE FROM annotations_index
+            WHERE id IN (SELECT id FROM annotations WHERE image_id = ?1 AND layer_id = ?2);
+        ",
+        [image_id, layer_id],
+    )?;
+    transaction.execute(
+        "DELETE FROM annotations WHERE image_id = ?1 AND layer_id = ?2;",
+        [image_id, layer_id],
+    )?;
+
+    transaction.commit()?;
+
+    Ok(deleted > 0)
+}
+
+/// How `attach` combines translated layers with the layers an image already has.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Attach {
+    /// Layers whose tag already exists are merged into it.
+    Merge,
+    /// The existing layers are removed first.
+    Replace,
+    /// Every layer is added as a new one, numbering tags that are already in use.
+    Separate,
+}
+
+/// Adds translated layers to an image, combining them with its layers as `mode` says.
+/// New layers take the next ids of the image. Replaced layers do not have their ids
+/// reused so clients do not confuse them. Returns the ids of removed layers and the
+/// layers that received annotations, or `None` if the image does not exist.
+pub fn attach(
+    dbm: &DatabaseManager,
+    store_id: u32,
+    image_id: u32,
+    mut layers: Vec<AnnotationLayer>,
+    mode: Attach,
+) -> Result<Option<(Vec<u32>, Vec<AnnotationLayer>)>> {
+    let mut conn = dbm.store(store_id)?;
+
+    let transaction = conn.transaction()?;
+
+    if !transaction
+        .prepare_cached("SELECT 1 FROM images WHERE id = ?1;")?
+        .exists([image_id])?
+    {
+        return Ok(None);
+    }
+
+    // Start the counter, if there is none, before replaced layers free their ids.
+    reserve_layer_ids(&transaction, image_id, 0)?;
+
+    let removed = if mode == Attach::Replace {
+        let removed = transaction
+            .prepare("DELETE FROM annotation_layer WHERE image_id = ?1 RETURNING id;")?
+            .query_map([image_id], |row| row.get(0))?
+            .collect::<Result<Vec<u32>, _>>()?;
+        delete_image(&transaction, image_id)?;
+        removed
+    } else {
+        vec![]
+    };
+
+    for layer in &mut layers {
+        if mode == Attach::Separate {
+            layer.tag = free_tag(&transaction, image_id, &layer.tag)?;
+        }
+
+        let existing = transaction
+            .prepare_cached(
+                "
+                    SELECT id, tag, visible, opacity, colour, stroke
+                    FROM annotation_layer
+                    WHERE image_id = ?1 AND tag = ?2;
+                ",
+            )?
+            .query_row((image_id, &layer.tag), annotation_layer)
+            .optional()?;
+
+        // Merged layers keep their id and style.
+        if let Some(existing) = existing {
+            let annotations = std::mem::take(&mut layer.annotations);
+            *layer = AnnotationLayer {
+                annotations,
+                ..existing
+            };
+        } else {
+            layer.id = reserve_layer_ids(&transaction, image_id, 1)? as usize;
+            insert_layer(&transaction, image_id, layer)?;
+        }
+    }
+
+    insert(&transaction, image_id, &layers)?;
+
+    transaction.commit()?;
+
+    Ok(Some((removed, layers)))
+}
+
+/// First of `tag`, `tag (2)`, `tag (3)`, ... that no layer of the image uses.
+fn free_tag(conn: &Connection, image_id: u32, tag: &str) -> Result<String> {
+    let mut stmt =
+        conn.prepare_cached("SELECT 1 FROM annotation_layer WHERE image_id = ?1 AND tag = ?2;")?;
+
+    let mut candidate = tag.to_string();
+    for number in 2.. {
+        if !stmt.exists((image_id, &candidate))? {
+            break;
+        }
+        candidate = format!("{tag} ({number})");
+    }
+
+    Ok(candidate)
+}
+
+/// Adds an annotation to a layer, or returns `None` if the layer does not exist.
+pub fn create(
+    dbm: &DatabaseManager,
+    store_id: u32,
+    image_id: u32,
+    layer_id: u32,
+    annotation: Annotation,
+) -> Result<Option<AnnotationRecord>> {
+    let mut conn = dbm.store(store_id)?;
+
+    let transaction = conn.transaction()?;
+
+    let exists = transaction
+        .prepare_cached(
+            "
+                SELECT 1
+                FROM annotation_layer
+                WHERE image_id = ?1 AND id = ?2;
+            ",
+        )?
+        .exists([image_id, layer_id])?;
+    if !exists {
+        return Ok(None);
+    }
+
+    let bounds = bounds(&annotation.geometry)?;
+
+    let id: u32 = transaction
+        .prepare_cached(
+            "
+                INSERT INTO annotations (image_id, layer_id, geometry, properties, min_x, min_y, max_x, max_y)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                RETURNING id;
+            ",
+        )?
+        .query_row(
+            (
+                image_id,
+                layer_id,
+                serde_json::to_string(&annotation.geometry)?,
+                serde_json::to_string(&annotation.properties)?,
+                bounds.min_x,
+                bounds.min_y,
+                bounds.max_x,
+                bounds.max_y,
+            ),
+            |row| row.get(0),
+        )?;
+
+    index(
+        &transaction,
+        i64::from(id),
+        image_id,
+        layer_id,
+        &annotation.geometry,
+    )?;
+
+    transaction.commit()?;
+
+    Ok(Some(AnnotationRecord {
+        id,
+        layer_id,
+        geometry: annotation.geometry,
+        properties: annotation.properties,
+        bounds,
+    }))
+}
+
+/// Replaces the geometry and/or properties of an annotation,
+/// or returns `None` if it is not in the layer.
+pub fn update(
+    dbm: &DatabaseManager,
+    store_id: u32,
+    image_id: u32,
+    layer_id: u32,
+    annotation_id: u32,
+    geometry: Option<Geometry>,
+    properties: Option<Properties>,
+) -> Result<Option<AnnotationRecord>> {
+    let mut conn = dbm.store(store_id)?;
+
+    let transaction = conn.transaction()?;
+
+    let Some(mut annotation) = transaction
+        .prepare_cached(
+            "
+                SELECT id, layer_id, geometry, properties, min_x, min_y, max_x, max_y
+                FROM annotations
+                WHERE image_id = ?1 AND layer_id = ?2 AND id = ?3;
+            ",
+        )?
+        .query_row([image_id, layer_id, annotation_id], record)
+        .optional()?
+    else {
+        return Ok(None);
+    };
+
+    if let Some(geometry) = geometry {
+        annotation.bounds = bounds(&geometry)?;
+        annotation.geometry = geometry;
+    }
+    if let Some(properties) = properties {
+        annotation.properties = properties;
+    }
+
+    transaction.execute(
+        "
+            UPDATE annotations
+            SET geometry = ?1,
+                properties = ?2,
+                min_x = ?3,
+                min_y = ?4,
+                max_x = ?5,
+                max_y = ?6
+            WHERE id = ?7;
+        ",
+        (
+            serde_json::to_string(&annotation.geometry)?,
+            serde_json::to_string(&annotation.properties)?,
+            annotation.bounds.min_x,
+            annotation.bounds.min_y,
+            annotation.bounds.max_x,
+            annotation.bounds.max_y,
+            annotation_id,
+        ),
+    )?;
+
+    index(
+        &transaction,
+        i64::from(annotation_id),
+        image_id,
+        layer_id,
+        &annotation.geometry,
+    )?;
+
+    transaction.commit()?;
+
+    Ok(Some(annotation))
+}
+
+/// Removes an annotation from a layer, returning whether it existed.
+pub fn delete(
+    dbm: &DatabaseManager,
+    store_id: u32,
+    image_id: u32,
+    layer_id: u32,
+    annotation_id: u32,
+) -> Result<bool> {
+    let mut conn = dbm.store(store_id)?;
+
+    let transaction = conn.transaction()?;
+
+    let deleted = transaction.execute(
+        "DELETE FROM annotations WHERE image_id = ?1 AND layer_id = ?2 AND id = ?3;",
+        [image_id, layer_id, annotation_id],
+    )?;
+    if deleted > 0 {
+        transaction.execute(
+            "DELETE FROM annotations_index WHERE id = ?1;",
+            [annotation_id],
+        )?;
+    }
+
+    transaction.commit()?;
+
+    Ok(deleted > 0)
+}
+
+/// Geometries of a layer drawn within the region, skipping those that span less than
+/// `min_extent` in both directions.
+pub fn region(
+    dbm: &DatabaseManager,
+    store_id: u32,
+    image_id: u32,
+    layer_id: u32,
+    region: Bounds,
+    min_extent: f64,
+) -> Result<Vec<Geometry>> {
+    let conn = dbm.store(store_id)?;
+
+    let mut stmt = conn.prepare_cached(
+        "
+            SELECT annotations.geometry
+            FROM annotations_index
+            JOIN annotations ON annotations.id = annotations_index.id
+            WHERE annotations_index.min_image <= ?1 AND annotations_index.max_image >= ?1
+                AND annotations_index.min_layer <= ?2 AND annotations_index.max_layer >= ?2
+                AND annotations_index.max_x >= ?3 AND annotations_index.min_x <= ?4
+                AND annotations_index.max_y >= ?5 AND annotations_index.min_y <= ?6
+                AND MAX(
+                    annotations_index.max_x - annotations_index.min_x,
+                    annotations_index.max_y - annotations_index.min_y
+                ) >= ?7
+            ORDER BY annotations.id;
+        ",
+    )?;
+
+    let geometries = stmt
+        .query_map(
+            (
+                image_id,
+                layer_id,
+                region.min_x,
+                region.max_x,
+                region.min_y,
+                region.max_y,
+                min_extent,
+            ),
+            |row| json(row, 0),
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(geometries)
+}
+
+/// Annotations of an image matching every filter, optionally restricted to one layer.
+pub fn query(
+    dbm: &DatabaseManager,
+    store_id: u32,
+    image_id: u32,
+    layer_id: Option<u32>,
+    filters: &[PropertyFilter],
+    limit: u32,
+    offset: u32,
+) -> Result<Vec<AnnotationRecord>> {
+    let conn = dbm.store(store_id)?;
+
+    let mut sql = String::from(
+        "
+            SELECT id, layer_id, geometry, properties, min_x, min_y, max_x, max_y
+            FROM annotations
+            WHERE image_id = ?
+        ",
+    );
+    let mut params: Vec<Value> = vec![image_id.into()];
+
+    if let Some(layer_id) = layer_id {
+        sql.push_str(" AND layer_id = ?");
+        params.push(layer_id.into());
+    }
+
+    for filter in filters {
+        sql.push_str(" AND json_extract(properties, ?) ");
+        sql.push_str(filter.comparison.sql());
+        sql.push_str(" ?");
+        params.push(filter.json_path().into());
+        params.push(filter.value.clone());
+    }
+
+    sql.push_str(" ORDER BY id LIMIT ? OFFSET ?;");
+    params.push(limit.into());
+    params.push(offset.into());
+
+    let mut stmt = conn.prepare_cached(&sql)?;
+
+    let annotations = stmt
+        .query_map(params_from_iter(params), record)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(annotations)
+}
+
+/// Layers of an image with all of their annotations, optionally only one layer.
+/// Returns `None` if the image does not exist.
+pub fn layers(
+    dbm: &DatabaseManager,
+    store_id: u32,
+    image_id: u32,
+    layer_id: Option<u32>,
+) -> Result<Option<Vec<LayerRecord>>> {
+    let conn = dbm.store(store_id)?;
+
+    let mut stmt = conn.prepare_cached("SELECT 1 FROM images WHERE id = ?1;")?;
+    if !stmt.exists([image_id])? {
+        return Ok(None);
+    }
+
+    let mut stmt = conn.prepare_cached(
+        "
+            SELECT id, tag, colour
+            FROM annotation_layer
+            WHERE image_id = ?1 AND (?2 IS NULL OR id = ?2)
+            ORDER BY id;
+        ",
+    )?;
+    let mut layers = stmt
+        .query_map((image_id, layer_id), |row| {
+            Ok(LayerRecord {
+                id: row.get(0)?,
+                tag: row.get(1)?,
+                fill: row.get(2)?,
+                annotations: vec![],
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut stmt = conn.prepare_cached(
+        "
+            SELECT id, layer_id, geometry, properties, min_x, min_y, max_x, max_y
+            FROM annotations
+            WHERE image_id = ?1 AND layer_id = ?2
+            ORDER BY id;
+        ",
+    )?;
+    for layer in &mut layers {
+        layer.annotations = stmt
+            .query_map([image_id, layer.id], record)?
+            .collect::<Result<Vec<_>, _>>()?;
+    }
+
+    Ok(Some(layers))
+}
+
+/// Layer without its annotations, from the columns `id, tag, visible, opacity, colour, stroke`.
+pub fn annotation_layer(row: &Row) -> Result<AnnotationLayer, rusqlite::Error> {
+    Ok(AnnotationLayer {
+        id: row.get(0)?,
+        tag: row.get(1)?,
+        visible: row.get(2)?,
+        opacity: row.get(3)?,
+        fill: row.get(4)?,
+        stroke: row.get(5)?,
+        annotations: vec![],
+    })
+}
+
+fn record(row: &Row) -> Result<AnnotationRecord, rusqlite::Error> {
+    Ok(AnnotationRecord {
+        id: row.get(0)?,
+        layer_id: row.get(1)?,
+        geometry: json(row, 2)?,
+        properties: json(row, 3)?,
+        bounds: Bounds {
+            min_x: row.get(4)?,
+            min_y: row.get(5)?,
+            max_x: row.get(6)?,
+            max_y: row.get(7)?,
+        },
+    })
+}
+
+fn json<T: serde::de::DeserializeOwned>(row: &Row, index: usize) -> Result<T, rusqlite::Error> {
+    serde_json::from_str(&row.get::<_, String>(index)?)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(index, Type::Text, e.into()))
+}
+
+fn bounds(geometry: &Geometry) -> Result<Bounds> {
+    geometry
+        .bounds()
+        .ok_or_else(|| anyhow!("Annotation geometry has no coordinates."))
+}
@@ -1,8 +1,22 @@
-use crate::constants::{BIN_ID, IMAGE_NAME, THUMBNAIL_NAME};
+use crate::constants::{
+    ANNOTATIONS_PATH_PREFIX, BIN_ID, IMAGE_NAME, THUMBNAIL_NAME, UPLOADED_IMAGE_PATH,
+};
 use crate::db::prelude::*;
 use chrono::Utc;
+use rusqlite::OptionalExtension;
 use shared::types::{AnnotationLayer, ImageProperties, MetadataLayer};
 
+/// Adds the columns that stores created before them lack.
+pub fn create_columns(conn: &Connection) -> Result<()> {
+    let mut stmt = conn
+        .prepare("SELECT 1 FROM pragma_table_info('images') WHERE name = 'microns_per_pixel';")?;
+    if !stmt.exists([])? {
+        conn.execute("ALTER TABLE images ADD COLUMN microns_per_pixel REAL;", ())?;
+    }
+
+    Ok(())
+}
+
 pub fn image_path(dbm: &DatabaseManager, store_id: u32, image_id: u32) -> Result<PathBuf> {
     Ok(dbm
         .store_properties(store_id)?
@@ -16,10 +30,17 @@ pub fn annotation_path(
     image_id: u32,
     annotation_layer_id: u32,
 ) -> Result<PathBuf> {
+    Ok(dbm.store_properties(store_id)?.path.join(format!(
+        "i{image_id}/{ANNOTATIONS_PATH_PREFIX}{annotation_layer_id}.glb"
+    )))
+}
+
+/// Prefix of the GLB files of an image, completed by the id of a layer.
+pub fn annotations_prefix(dbm: &DatabaseManager, store_id: u32, image_id: u32) -> Result<PathBuf> {
     Ok(dbm
         .store_properties(store_id)?
         .path
-        .join(format!("i{image_id}/a{annotation_layer_id}.glb")))
+        .join(format!("i{image_id}/{ANNOTATIONS_PATH_PREFIX}")))
 }
 
 pub fn thumbnail_path(dbm: &DatabaseManager, store_id: u32, image_id: u32) -> Result<PathBuf> {
@@ -29,6 +50,51 @@ pub fn thumbnail_path(dbm: &DatabaseManager, store_id: u32, image_id: u32) -> Re
         .join(format!("i{image_id}/{THUMBNAIL_NAME}")))
 }
 
+/// Path and extension of the image as uploaded, or `None` if the image does not exist.
+pub fn uploaded_image(
+    dbm: &DatabaseManager,
+    store_id: u32,
+    image_id: u32,
+) -> Result<Option<(PathBuf, String)>> {
+    let path = dbm
+        .store_properties(store_id)?
+        .path
+        .join(format!("i{image_id}/{UPLOADED_IMAGE_PATH}"));
+
+    let conn = dbm.store(store_id)?;
+
+    let mut stmt = conn.prepare_cached(
+        "
+            SELECT uploaded_image_extension
+            FROM images
+            WHERE id = ?1;
+        ",
+    )?;
+
+    let extension = stmt.query_row([image_id], |row| row.get(0)).optional()?;
+
+    Ok(extension.map(|extension| (path, extension)))
+}
+
+/// Physical size of a level 0 pixel, if the decoder could read it from the image.
+pub fn microns_per_pixel(
+    dbm: &DatabaseManager,
+    store_id: u32,
+    image_id: u32,
+) -> Result<Option<f64>> {
+    let conn = dbm.store(store_id)?;
+
+    let mut stmt = conn.prepare_cached(
+        "
+            SELECT microns_per_pixel
+            FROM images
+            WHERE id = ?1;
+        ",
+    )?;
+
+    Ok(stmt.query_row([image_id], |row| row.get(0))?)
+}
+
 pub fn get_parent(dbm: &DatabaseManager, store_id: u32, image_id: u32) -> Result<u32> {
     let conn = dbm.store(store_id)?;
 
@@ -83,6 +149,7 @@ pub fn soft_delete(dbm: &DatabaseManager, store_id: u32, image_id: u32) -> Resul
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn insert(
     dbm: &DatabaseManager,
     store_id: u32,
@@ -94,8 +161,9 @@ pub fn insert(
     generator: Option<&str>,
     uploaded_image_extension: &str,
     uploaded_annotations_extension: Option<&str>,
+    microns_per_pixel: Option<f64>,
     metadata_layers: Vec<MetadataLayer>,
-    annotation_layers: Vec<AnnotationLayer>,
+    mut annotation_layers: Vec<AnnotationLayer>,
 ) -> Result<()> {
     let mut conn = dbm.store(store_id)?;
 
@@ -104,8 +172,8 @@ pub fn insert(
     {
         let mut stmt =  transaction.prepare_cached(
         "
-            INSERT INTO images (id, parent_id, name, created_at, updated_at, decoder, encoder, generator, uploaded_image_extension, uploaded_annotations_extension)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10);
+            INSERT INTO images (id, parent_id, name, created_at, updated_at, decoder, encoder, generator, uploaded_image_extension, uploaded_annotations_extension, microns_per_pixel)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11);
         ",
     )?;
 
@@ -122,6 +190,7 @@ pub fn insert(
             generator,
             uploaded_image_extension,
             uploaded_annotations_extension,
+            microns_per_pixel,
         ))?;
     }
 
@@ -138,19 +207,12 @@ pub fn insert(
         }
     }
 
-    {
-        let mut stmt = transaction.prepare_cached(
-            "
-            INSERT INTO annotation_layer (id, image_id, tag, colour)
-            VALUES (?1, ?2, ?3, ?4);
-        ",
-        )?;
-
-        for a in annotation_layers {
-            stmt.execute((a.id, image_id, a.tag, a.fill))?;
-        }
+    for layer in &mut annotation_layers {
+        crate::db::annotation::insert_layer(&transaction, image_id, layer)?;
     }
 
+    crate::db::annotation::insert(&transaction, image_id, &annotation_layers)?;
+
     transaction.commit()?;
 
     Ok(())
@@ -162,10 +224,46 @@ pub fn delete(dbm: &DatabaseManager, store_id: u32, image_id: u32) -> Result<()>
     let mut stmt = conn.prepare_cached("DELETE FROM images WHERE id = ?1;")?;
     stmt.execute([image_id])?;
 
+    // Foreign keys are not enforced, so annotations are not removed by the cascade.
+    crate::db::annotation::delete_image(&conn, image_id)?;
+
     Ok(())
 }
 
-pub fn properties(dbm: &DatabaseManager, store_id: u32, image_id: u32) -> Result<ImageProperties> {
+pub fn metadata_layer(
+    dbm: &DatabaseManager,
+    store_id: u32,
+    image_id: u32,
+    level: u32,
+) -> Result<MetadataLayer> {
+    let conn = dbm.store(store_id)?;
+
+    let mut stmt = conn.prepare_cached(
+        "
+            SELECT level, cols, rows, width, height
+            FROM metadata_layer
+            WHERE image_id = ?1 AND level = ?2;
+        ",
+    )?;
+
+    let metadata_layer = stmt.query_row([image_id, level], |row| {
+        Ok(MetadataLayer {
+            level: row.get(0)?,
+            cols: row.get(1)?,
+            rows: row.get(2)?,
+            width: row.get(3)?,
+            height: row.get(4)?,
+        })
+    })?;
+
+    Ok(metadata_layer)
+}
+
+pub fn metadata_layers(
+    dbm: &DatabaseManager,
+    store_id: u32,
+    image_id: u32,
+) -> Result<Vec<MetadataLayer>> {
     let conn = dbm.store(store_id)?;
 
     let mut stmt = conn.prepare_cached(
@@ -189,18 +287,24 @@ pub fn properties(dbm: &DatabaseManager, store_id: u32, image_id: u32) -> Result
         })?
         .collect::<Result<Vec<_>, _>>()?;
 
+    Ok(metadata_layers)
+}
+
+pub fn properties(dbm: &DatabaseManager, store_id: u32, image_id: u32) -> Result<ImageProperties> {
+    let metadata_layers = metadata_layers(dbm, store_id, image_id)?;
+
+    let conn = dbm.store(store_id)?;
+
     let mut stmt = conn.prepare_cached(
         "
-            SELECT id, tag, colour
+            SELECT id, tag, visible, opacity, colour, stroke
             FROM annotation_layer
             WHERE image_id = ?1;
         ",
     )?;
 
     let annotation_layers = stmt
-        .query_map([image_id], |row| {
-            Ok(AnnotationLayer::new(row.get(0)?, row.get(1)?, row.get(2)?))
-        })?
+        .query_map([image_id], crate::db::annotation::annotation_layer)?
         .collect::<Result<Vec<_>, _>>()?;
 
     Ok(ImageProperties {
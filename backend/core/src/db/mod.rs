@@ -1,6 +1,42 @@
+pub mod annotation;
 pub mod counter;
 pub mod directory;
 pub mod image;
+pub mod palette;
 mod prelude;
 pub mod registry;
 pub mod stores;
+
+/// Whether a query failed on a constraint of the database, such as a duplicate key,
+/// rather than on the database itself.
+pub fn is_constraint_violation(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<rusqlite::Error>(),
+        Some(rusqlite::Error::SqliteFailure(failure, _))
+            if failure.code == rusqlite::ErrorCode::ConstraintViolation
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::Connection;
+
+    #[test]
+    fn detects_constraint_violations() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE tags (tag TEXT UNIQUE); INSERT INTO tags VALUES ('a');")
+            .unwrap();
+
+        let duplicate = conn
+            .execute("INSERT INTO tags VALUES ('a');", [])
+            .unwrap_err();
+        let missing = conn
+            .execute("INSERT INTO layers VALUES ('a');", [])
+            .unwrap_err();
+
+        assert!(is_constraint_violation(&duplicate.into()));
+        assert!(!is_constraint_violation(&missing.into()));
+        assert!(!is_constraint_violation(&anyhow::anyhow!("Disk full.")));
+    }
+}
@@ -0,0 +1,68 @@
+use crate::constants::DEFAULT_PALETTE;
+use crate::db::prelude::*;
+use anyhow::anyhow;
+
+/// Creates the palette of a store, starting with the default colours.
+pub fn create_table(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+            CREATE TABLE IF NOT EXISTS palette (
+                position INTEGER PRIMARY KEY,
+                colour TEXT NOT NULL
+            );
+        "#,
+    )?;
+
+    if conn.prepare("SELECT 1 FROM palette;")?.exists([])? {
+        return Ok(());
+    }
+
+    insert(conn, DEFAULT_PALETTE)
+}
+
+fn insert<T: AsRef<str>>(conn: &Connection, colours: impl IntoIterator<Item = T>) -> Result<()> {
+    let mut stmt =
+        conn.prepare_cached("INSERT INTO palette (position, colour) VALUES (?1, ?2);")?;
+    for (position, colour) in colours.into_iter().enumerate() {
+        stmt.execute((position, colour.as_ref()))?;
+    }
+
+    Ok(())
+}
+
+/// Colours given to new annotation layers of the store, in turn.
+pub fn get(dbm: &DatabaseManager, store_id: u32) -> Result<Vec<String>> {
+    let conn = dbm.store(store_id)?;
+    colours(&conn)
+}
+
+/// Replaces the palette of the store. Existing layers keep their colours.
+pub fn set(dbm: &DatabaseManager, store_id: u32, colours: &[String]) -> Result<()> {
+    let mut conn = dbm.store(store_id)?;
+
+    let transaction = conn.transaction()?;
+    transaction.execute("DELETE FROM palette;", ())?;
+    insert(&transaction, colours)?;
+    transaction.commit()?;
+
+    Ok(())
+}
+
+fn colours(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare_cached("SELECT colour FROM palette ORDER BY position;")?;
+    let colours = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(colours)
+}
+
+/// Palette colour of the layer with the given id, cycling through the palette.
+pub fn colour(conn: &Connection, layer_id: usize) -> Result<String> {
+    let colours = colours(conn)?;
+    if colours.is_empty() {
+        return Err(anyhow!("Palette has no colours."));
+    }
+
+    Ok(colours[layer_id % colours.len()].clone())
+}
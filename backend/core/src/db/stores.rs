@@ -67,6 +67,7 @@ pub fn create(registry_conn: &Connection, r#type: &Interface, name: &str) -> Res
             generator TEXT,
             uploaded_image_extension TEXT NOT NULL,
             uploaded_annotations_extension TEXT,
+            microns_per_pixel REAL,
             FOREIGN KEY (parent_id) REFERENCES directories (id) ON DELETE CASCADE,
             UNIQUE (parent_id, name) -- Enforce that for each parent directory, there is only one file with a given name.
         );
@@ -95,7 +96,10 @@ pub fn create(registry_conn: &Connection, r#type: &Interface, name: &str) -> Res
             id INTEGER NOT NULL,
             image_id INTEGER NOT NULL,
             tag TEXT NOT NULL,
+            visible INTEGER NOT NULL DEFAULT 1,
+            opacity REAL NOT NULL DEFAULT 0.5,
             colour TEXT NOT NULL,
+            stroke TEXT NOT NULL DEFAULT '#000000',
             FOREIGN KEY (image_id) REFERENCES images (id) ON DELETE CASCADE,
             UNIQUE (image_id, tag)
         );
@@ -103,6 +107,9 @@ pub fn create(registry_conn: &Connection, r#type: &Interface, name: &str) -> Res
         (),
     )?;
 
+    crate::db::annotation::create_tables(&transaction)?;
+    crate::db::palette::create_table(&transaction)?;
+
     // Create virtual root directory.
     transaction.execute(
         "INSERT INTO directories (id, name) VALUES (?1, ?2);",
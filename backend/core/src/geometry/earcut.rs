@@ -0,0 +1,1004 @@
+//! Polygon triangulation by ear clipping, ported from mapbox/earcut 2.2.4.
+//! This is the triangulator Three.js uses for `ShapeGeometry`, so meshes match
+//! those previously built by the geometry computer.
+
+#[derive(Clone, Copy)]
+struct Node {
+    // Index of the vertex in the input.
+    i: usize,
+    x: f64,
+    y: f64,
+    prev: usize,
+    next: usize,
+    // Position on the z-order curve, and neighbours in z-order.
+    z: u32,
+    prev_z: Option<usize>,
+    next_z: Option<usize>,
+    // Whether this is a single-point hole.
+    steiner: bool,
+}
+
+/// Bounding box used to hash nodes onto the z-order curve.
+#[derive(Clone, Copy)]
+struct Hash {
+    min_x: f64,
+    min_y: f64,
+    inv_size: f64,
+}
+
+#[derive(Default)]
+struct Earcut {
+    nodes: Vec<Node>,
+    triangles: Vec<usize>,
+}
+
+/// Triangulates a polygon given as flat `[x0, y0, x1, y1, ...]` coordinates.
+/// `hole_indices` are the vertex indices at which each hole starts.
+/// Returns triples of vertex indices.
+pub fn earcut(data: &[f64], hole_indices: &[usize]) -> Vec<usize> {
+    let mut earcut = Earcut::default();
+
+    let outer_len = hole_indices.first().map_or(data.len(), |index| index * 2);
+    let Some(mut outer_node) = earcut.linked_list(data, 0, outer_len, true) else {
+        return vec![];
+    };
+    if earcut.next(outer_node) == earcut.prev(outer_node) {
+        return vec![];
+    }
+
+    if !hole_indices.is_empty() {
+        outer_node = earcut.eliminate_holes(data, hole_indices, outer_node);
+    }
+
+    // Hash larger polygons onto a z-order curve to speed up ear checks.
+    let mut hash = None;
+    if data.len() > 80 * 2 {
+        let (mut min_x, mut min_y) = (f64::INFINITY, f64::INFINITY);
+        let (mut max_x, mut max_y) = (f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for point in data[..outer_len].chunks_exact(2) {
+            min_x = min_x.min(point[0]);
+            min_y = min_y.min(point[1]);
+            max_x = max_x.max(point[0]);
+            max_y = max_y.max(point[1]);
+        }
+
+        let size = (max_x - min_x).max(max_y - min_y);
+        if size != 0.0 {
+            hash = Some(Hash {
+                min_x,
+                min_y,
+                inv_size: 32767.0 / size,
+            });
+        }
+    }
+
+    earcut.earcut_linked(Some(outer_node), hash, 0);
+
+    earcut.triangles
+}
+
+impl Earcut {
+    fn prev(&self, node: usize) -> usize {
+        self.nodes[node].prev
+    }
+
+    fn next(&self, node: usize) -> usize {
+        self.nodes[node].next
+    }
+
+    fn point(&self, node: usize) -> (f64, f64) {
+        (self.nodes[node].x, self.nodes[node].y)
+    }
+
+    /// Creates a circular doubly linked list from polygon points in the given winding.
+    fn linked_list(
+        &mut self,
+        data: &[f64],
+        start: usize,
+        end: usize,
+        clockwise: bool,
+    ) -> Option<usize> {
+        let mut last = None;
+        if start == end {
+            return last;
+        }
+
+        if clockwise == (signed_area(data, start, end) > 0.0) {
+            for i in (start..end).step_by(2) {
+                last = Some(self.insert_node(i / 2, data[i], data[i + 1], last));
+            }
+        } else {
+            for i in (start..end).step_by(2).rev() {
+                last = Some(self.insert_node(i / 2, data[i], data[i + 1], last));
+            }
+        }
+
+        if let Some(node) = last
+            && self.equals(node, self.next(node))
+        {
+            self.remove_node(node);
+            last = Some(self.next(node));
+        }
+
+        last
+    }
+
+    /// Eliminates colinear or duplicate points.
+    fn filter_points(&mut self, start: usize, end: Option<usize>) -> usize {
+        let mut end = end.unwrap_or(start);
+        let mut p = start;
+
+        loop {
+            let mut again = false;
+
+            let (prev, next) = (self.prev(p), self.next(p));
+            if !self.nodes[p].steiner && (self.equals(p, next) || self.area(prev, p, next) == 0.0) {
+                self.remove_node(p);
+                p = self.prev(p);
+                end = p;
+                if p == self.next(p) {
+                    break;
+                }
+                again = true;
+            } else {
+                p = next;
+            }
+
+            if !again && p == end {
+                break;
+            }
+        }
+
+        end
+    }
+
+    /// Main ear slicing loop which triangulates a polygon.
+    fn earcut_linked(&mut self, ear: Option<usize>, hash: Option<Hash>, pass: u8) {
+        let Some(mut ear) = ear else {
+            return;
+        };
+
+        // Interlink polygon nodes in z-order.
+        if pass == 0
+            && let Some(hash) = hash
+        {
+            self.index_curve(ear, hash);
+        }
+
+        let mut stop = ear;
+
+        // Iterate through ears, slicing them one by one.
+        while self.prev(ear) != self.next(ear) {
+            let prev = self.prev(ear);
+            let next = self.next(ear);
+
+            let is_ear = match hash {
+                Some(hash) => self.is_ear_hashed(ear, hash),
+                None => self.is_ear(ear),
+            };
+
+            if is_ear {
+                self.triangles
+                    .extend([self.nodes[prev].i, self.nodes[ear].i, self.nodes[next].i]);
+
+                self.remove_node(ear);
+
+                // Skipping the next vertex leads to less sliver triangles.
+                ear = self.next(next);
+                stop = ear;
+                continue;
+            }
+
+            ear = next;
+
+            // If we looped through the whole remaining polygon and can't find any more ears.
+            if ear == stop {
+                match pass {
+                    // Try filtering points and slicing again.
+                    0 => {
+                        let ear = self.filter_points(ear, None);
+                        self.earcut_linked(Some(ear), hash, 1);
+                    }
+                    // If this didn't work, try curing all small self-intersections locally.
+                    1 => {
+                        let ear = self.filter_points(ear, None);
+                        let ear = self.cure_local_intersections(ear);
+                        self.earcut_linked(Some(ear), hash, 2);
+                    }
+                    // As a last resort, try splitting the remaining polygon into two.
+                    _ => self.split_earcut(ear, hash),
+                }
+
+                break;
+            }
+        }
+    }
+
+    /// Whether a polygon node forms a valid ear with adjacent nodes.
+    fn is_ear(&self, ear: usize) -> bool {
+        let (a, b, c) = (self.prev(ear), ear, self.next(ear));
+
+        // Reflex, can't be an ear.
+        if self.area(a, b, c) >= 0.0 {
+            return false;
+        }
+
+        let ((ax, ay), (bx, by), (cx, cy)) = (self.point(a), self.point(b), self.point(c));
+        let (x0, y0) = (ax.min(bx).min(cx), ay.min(by).min(cy));
+        let (x1, y1) = (ax.max(bx).max(cx), ay.max(by).max(cy));
+
+        // Now make sure we don't have other points inside the potential ear.
+        let mut p = self.next(c);
+        while p != a {
+            let (px, py) = self.point(p);
+            if px >= x0
+                && px <= x1
+                && py >= y0
+                && py <= y1
+                && point_in_triangle(ax, ay, bx, by, cx, cy, px, py)
+                && self.area(self.prev(p), p, self.next(p)) >= 0.0
+            {
+                return false;
+            }
+            p = self.next(p);
+        }
+
+        true
+    }
+
+    fn is_ear_hashed(&self, ear: usize, hash: Hash) -> bool {
+        let (a, b, c) = (self.prev(ear), ear, self.next(ear));
+
+        if self.area(a, b, c) >= 0.0 {
+            return false;
+        }
+
+        let ((ax, ay), (bx, by), (cx, cy)) = (self.point(a), self.point(b), self.point(c));
+        let (x0, y0) = (ax.min(bx).min(cx), ay.min(by).min(cy));
+        let (x1, y1) = (ax.max(bx).max(cx), ay.max(by).max(cy));
+
+        // Z-order range for the current triangle bounding box.
+        let min_z = z_order(x0, y0, hash);
+        let max_z = z_order(x1, y1, hash);
+
+        let inside = |p: usize| {
+            let (px, py) = self.point(p);
+            px >= x0
+                && px <= x1
+                && py >= y0
+                && py <= y1
+                && p != a
+                && p != c
+                && point_in_triangle(ax, ay, bx, by, cx, cy, px, py)
+                && self.area(self.prev(p), p, self.next(p)) >= 0.0
+        };
+
+        let mut back = self.nodes[ear].prev_z;
+        let mut forward = self.nodes[ear].next_z;
+
+        // Look for points inside the triangle in both directions.
+        while let (Some(prev), Some(next)) = (back, forward) {
+            if self.nodes[prev].z < min_z || self.nodes[next].z > max_z {
+                break;
+            }
+            if inside(prev) {
+                return false;
+            }
+            back = self.nodes[prev].prev_z;
+
+            if inside(next) {
+                return false;
+            }
+            forward = self.nodes[next].next_z;
+        }
+
+        // Look for remaining points in decreasing z-order.
+        while let Some(prev) = back {
+            if self.nodes[prev].z < min_z {
+                break;
+            }
+            if inside(prev) {
+                return false;
+            }
+            back = self.nodes[prev].prev_z;
+        }
+
+        // Look for remaining points in increasing z-order.
+        while let Some(next) = forward {
+            if self.nodes[next].z > max_z {
+                break;
+            }
+            if inside(next) {
+                return false;
+            }
+            forward = self.nodes[next].next_z;
+        }
+
+        true
+    }
+
+    /// Goes through all polygon nodes and cures small local self-intersections.
+    fn cure_local_intersections(&mut self, start: usize) -> usize {
+        let mut start = start;
+        let mut p = start;
+
+        loop {
+            let a = self.prev(p);
+            let b = self.next(self.next(p));
+
+            if !self.equals(a, b)
+                && self.intersects(a, p, self.next(p), b)
+                && self.locally_inside(a, b)
+                && self.locally_inside(b, a)
+            {
+                self.triangles
+                    .extend([self.nodes[a].i, self.nodes[p].i, self.nodes[b].i]);
+
+                // Remove two nodes involved.
+                self.remove_node(p);
+                self.remove_node(self.next(p));
+
+                p = b;
+                start = b;
+            }
+
+            p = self.next(p);
+            if p == start {
+                break;
+            }
+        }
+
+        self.filter_points(p, None)
+    }
+
+    /// Tries splitting the polygon into two and triangulating them independently.
+    fn split_earcut(&mut self, start: usize, hash: Option<Hash>) {
+        // Look for a valid diagonal that divides the polygon into two.
+        let mut a = start;
+        loop {
+            let mut b = self.next(self.next(a));
+            while b != self.prev(a) {
+                if self.nodes[a].i != self.nodes[b].i && self.is_valid_diagonal(a, b) {
+                    // Split the polygon in two by the diagonal.
+                    let c = self.split_polygon(a, b);
+
+                    // Filter colinear points around the cuts.
+                    let a = self.filter_points(a, Some(self.next(a)));
+                    let c = self.filter_points(c, Some(self.next(c)));
+
+                    // Run earcut on each half.
+                    self.earcut_linked(Some(a), hash, 0);
+                    self.earcut_linked(Some(c), hash, 0);
+                    return;
+                }
+                b = self.next(b);
+            }
+
+            a = self.next(a);
+            if a == start {
+                break;
+            }
+        }
+    }
+
+    /// Links every hole into the outer loop, producing a single-ring polygon without holes.
+    fn eliminate_holes(
+        &mut self,
+        data: &[f64],
+        hole_indices: &[usize],
+        outer_node: usize,
+    ) -> usize {
+        let mut queue = Vec::with_capacity(hole_indices.len());
+
+        for (i, hole_index) in hole_indices.iter().enumerate() {
+            let start = hole_index * 2;
+            let end = hole_indices
+                .get(i + 1)
+                .map_or(data.len(), |index| index * 2);
+
+            let Some(list) = self.linked_list(data, start, end, false) else {
+                continue;
+            };
+            if list == self.next(list) {
+                self.nodes[list].steiner = true;
+            }
+            queue.push(self.get_leftmost(list));
+        }
+
+        queue.sort_by(|a, b| self.nodes[*a].x.total_cmp(&self.nodes[*b].x));
+
+        // Process holes from left to right.
+        let mut outer_node = outer_node;
+        for hole in queue {
+            outer_node = self.eliminate_hole(hole, outer_node);
+        }
+
+        outer_node
+    }
+
+    /// Finds a bridge between a hole and the outer polygon, and links them.
+    fn eliminate_hole(&mut self, hole: usize, outer_node: usize) -> usize {
+        let Some(bridge) = self.find_hole_bridge(hole, outer_node) else {
+            return outer_node;
+        };
+
+        let bridge_reverse = self.split_polygon(bridge, hole);
+
+        // Filter colinear points around the cuts.
+        self.filter_points(bridge_reverse, Some(self.next(bridge_reverse)));
+        self.filter_points(bridge, Some(self.next(bridge)))
+    }
+
+    /// David Eberly's algorithm for finding a bridge between a hole and the outer polygon.
+    // Coordinates are compared exactly, as in mapbox/earcut, so triangulations match it.
+    #[allow(clippy::float_cmp)]
+    fn find_hole_bridge(&self, hole: usize, outer_node: usize) -> Option<usize> {
+        let (hx, hy) = self.point(hole);
+        let mut qx = f64::NEG_INFINITY;
+        let mut m = None;
+
+        // Find a segment intersected by a ray from the hole's leftmost point to the left;
+        // the segment's endpoint with the lesser x will be a potential connection point.
+        let mut p = outer_node;
+        loop {
+            let (px, py) = self.point(p);
+            let (nx, ny) = self.point(self.next(p));
+            if hy <= py && hy >= ny && ny != py {
+                let x = px + (hy - py) * (nx - px) / (ny - py);
+                if x <= hx && x > qx {
+                    qx = x;
+                    m = Some(if px < nx { p } else { self.next(p) });
+                    if x == hx {
+                        // The hole touches the outer segment; pick the leftmost endpoint.
+                        return m;
+                    }
+                }
+            }
+            p = self.next(p);
+            if p == outer_node {
+                break;
+            }
+        }
+
+        let mut m = m?;
+
+        // Look for points inside the triangle of hole point, segment intersection and endpoint;
+        // if there are no points found, we have a valid connection;
+        // otherwise choose the point of the minimum angle with the ray as connection point.
+        let stop = m;
+        let (mx, my) = self.point(m);
+        let mut tan_min = f64::INFINITY;
+
+        p = m;
+        loop {
+            let (px, py) = self.point(p);
+            if hx >= px
+                && px >= mx
+                && hx != px
+                && point_in_triangle(
+                    if hy < my { hx } else { qx },
+                    hy,
+                    mx,
+                    my,
+                    if hy < my { qx } else { hx },
+                    hy,
+                    px,
+                    py,
+                )
+            {
+                let tan = (hy - py).abs() / (hx - px);
+                let (m_x, _) = self.point(m);
+
+                if self.locally_inside(p, hole)
+                    && (tan < tan_min
+                        || (tan == tan_min
+                            && (px > m_x || (px == m_x && self.sector_contains_sector(m, p)))))
+                {
+                    m = p;
+                    tan_min = tan;
+                }
+            }
+
+            p = self.next(p);
+            if p == stop {
+                break;
+            }
+        }
+
+        Some(m)
+    }
+
+    /// Whether sector in vertex m contains sector in vertex p in the same coordinates.
+    fn sector_contains_sector(&self, m: usize, p: usize) -> bool {
+        self.area(self.prev(m), m, self.prev(p)) < 0.0
+            && self.area(self.next(p), m, self.next(m)) < 0.0
+    }
+
+    /// Interlinks polygon nodes in z-order.
+    fn index_curve(&mut self, start: usize, hash: Hash) {
+        let mut p = start;
+        loop {
+            if self.nodes[p].z == 0 {
+                self.nodes[p].z = z_order(self.nodes[p].x, self.nodes[p].y, hash);
+            }
+            self.nodes[p].prev_z = Some(self.prev(p));
+            self.nodes[p].next_z = Some(self.next(p));
+            p = self.next(p);
+            if p == start {
+                break;
+            }
+        }
+
+        if let Some(prev_z) = self.nodes[p].prev_z {
+            self.nodes[prev_z].next_z = None;
+        }
+        self.nodes[p].prev_z = None;
+
+        self.sort_linked(p);
+    }
+
+    /// Simon Tatham's linked list merge sort algorithm.
+    fn sort_linked(&mut self, list: usize) {
+        let mut list = Some(list);
+        let mut in_size = 1;
+
+        loop {
+            let mut p = list;
+            list = None;
+            let mut tail: Option<usize> = None;
+            let mut num_merges = 0;
+
+            while let Some(mut pp) = p {
+                num_merges += 1;
+                let mut q = Some(pp);
+                let mut p_size = 0;
+                for _ in 0..in_size {
+                    p_size += 1;
+                    q = q.and_then(|q| self.nodes[q].next_z);
+                    if q.is_none() {
+                        break;
+                    }
+                }
+                let mut q_size = in_size;
+
+                while p_size > 0 || (q_size > 0 && q.is_some()) {
+                    let e;
+                    let take_p = p_size != 0
+                        && (q_size == 0 || q.is_none_or(|q| self.nodes[pp].z <= self.nodes[q].z));
+
+                    if take_p {
+                        e = pp;
+                        p = self.nodes[pp].next_z;
+                        p_size -= 1;
+                    } else {
+                        let qq = q.expect("checked above");
+                        e = qq;
+                        q = self.nodes[qq].next_z;
+                        q_size -= 1;
+                    }
+
+                    match tail {
+                        Some(tail) => self.nodes[tail].next_z = Some(e),
+                        None => list = Some(e),
+                    }
+                    self.nodes[e].prev_z = tail;
+                    tail = Some(e);
+
+                    if let Some(next) = p {
+                        pp = next;
+                    }
+                }
+
+                p = q;
+            }
+
+            if let Some(tail) = tail {
+                self.nodes[tail].next_z = None;
+            }
+
+            if num_merges <= 1 {
+                break;
+            }
+            in_size *= 2;
+        }
+    }
+
+    /// Finds the leftmost node of a polygon ring.
+    // Coordinates are compared exactly, as in mapbox/earcut, so triangulations match it.
+    #[allow(clippy::float_cmp)]
+    fn get_leftmost(&self, start: usize) -> usize {
+        let mut p = start;
+        let mut leftmost = start;
+        loop {
+            let (px, py) = self.point(p);
+            let (lx, ly) = self.point(leftmost);
+            if px < lx || (px == lx && py < ly) {
+                leftmost = p;
+            }
+            p = self.next(p);
+            if p == start {
+                break;
+            }
+        }
+        leftmost
+    }
+
+    /// Whether a diagonal between two polygon nodes is valid (lies in polygon interior).
+    fn is_valid_diagonal(&self, a: usize, b: usize) -> bool {
+        let (ai, bi) = (self.nodes[a].i, self.nodes[b].i);
+
+        // Doesn't intersect other edges.
+        self.nodes[self.next(a)].i != bi
+            && self.nodes[self.prev(a)].i != ai
+            && self.nodes[self.prev(a)].i != bi
+            && !self.intersects_polygon(a, b)
+            && ((self.locally_inside(a, b)
+                && self.locally_inside(b, a)
+                && self.middle_inside(a, b)
+                // Does not create opposite-facing sectors.
+                && (self.area(self.prev(a), a, self.prev(b)) != 0.0
+                    || self.area(a, self.prev(b), b) != 0.0))
+                // Special zero-length case.
+                || (self.equals(a, b)
+                    && self.area(self.prev(a), a, self.next(a)) > 0.0
+                    && self.area(self.prev(b), b, self.next(b)) > 0.0))
+    }
+
+    /// Signed area of a triangle.
+    fn area(&self, p: usize, q: usize, r: usize) -> f64 {
+        let ((px, py), (qx, qy), (rx, ry)) = (self.point(p), self.point(q), self.point(r));
+        (qy - py) * (rx - qx) - (qx - px) * (ry - qy)
+    }
+
+    fn equals(&self, p1: usize, p2: usize) -> bool {
+        self.point(p1) == self.point(p2)
+    }
+
+    /// Whether two segments intersect.
+    fn intersects(&self, p1: usize, q1: usize, p2: usize, q2: usize) -> bool {
+        let o1 = sign(self.area(p1, q1, p2));
+        let o2 = sign(self.area(p1, q1, q2));
+        let o3 = sign(self.area(p2, q2, p1));
+        let o4 = sign(self.area(p2, q2, q1));
+
+        // General case.
+        if o1 != o2 && o3 != o4 {
+            return true;
+        }
+
+        // Colinear cases.
+        (o1 == 0 && self.on_segment(p1, p2, q1))
+            || (o2 == 0 && self.on_segment(p1, q2, q1))
+            || (o3 == 0 && self.on_segment(p2, p1, q2))
+            || (o4 == 0 && self.on_segment(p2, q1, q2))
+    }
+
+    /// For colinear points p, q, r, whether point q lies on segment pr.
+    fn on_segment(&self, p: usize, q: usize, r: usize) -> bool {
+        let ((px, py), (qx, qy), (rx, ry)) = (self.point(p), self.point(q), self.point(r));
+        qx <= px.max(rx) && qx >= px.min(rx) && qy <= py.max(ry) && qy >= py.min(ry)
+    }
+
+    /// Whether a polygon diagonal intersects any polygon segments.
+    fn intersects_polygon(&self, a: usize, b: usize) -> bool {
+        let (ai, bi) = (self.nodes[a].i, self.nodes[b].i);
+        let mut p = a;
+        loop {
+            let next = self.next(p);
+            let (pi, ni) = (self.nodes[p].i, self.nodes[next].i);
+            if pi != ai && ni != ai && pi != bi && ni != bi && self.intersects(p, next, a, b) {
+                return true;
+            }
+            p = next;
+            if p == a {
+                break;
+            }
+        }
+        false
+    }
+
+    /// Whether a polygon diagonal is locally inside the polygon.
+    fn locally_inside(&self, a: usize, b: usize) -> bool {
+        let (prev, next) = (self.prev(a), self.next(a));
+        if self.area(prev, a, next) < 0.0 {
+            self.area(a, b, next) >= 0.0 && self.area(a, prev, b) >= 0.0
+        } else {
+            self.area(a, b, prev) < 0.0 || self.area(a, next, b) < 0.0
+        }
+    }
+
+    /// Whether the middle point of a polygon diagonal is inside the polygon.
+    // Coordinates are compared exactly, as in mapbox/earcut, so triangulations match it.
+    #[allow(clippy::float_cmp)]
+    fn middle_inside(&self, a: usize, b: usize) -> bool {
+        let ((ax, ay), (bx, by)) = (self.point(a), self.point(b));
+        let (px, py) = (f64::midpoint(ax, bx), f64::midpoint(ay, by));
+
+        let mut inside = false;
+        let mut node = a;
+        loop {
+            let (x, y) = self.point(node);
+            let (nx, ny) = self.point(self.next(node));
+            if (y > py) != (ny > py) && ny != y && px < (nx - x) * (py - y) / (ny - y) + x {
+                inside = !inside;
+            }
+            node = self.next(node);
+            if node == a {
+                break;
+            }
+        }
+        inside
+    }
+
+    /// Links two polygon vertices with a bridge. If the vertices belong to the same ring,
+    /// it splits the polygon into two; if one belongs to the outer ring and another to a
+    /// hole, it merges it into a single ring.
+    fn split_polygon(&mut self, a: usize, b: usize) -> usize {
+        let a2 = self.new_node(self.nodes[a].i, self.nodes[a].x, self.nodes[a].y);
+        let b2 = self.new_node(self.nodes[b].i, self.nodes[b].x, self.nodes[b].y);
+        let an = self.next(a);
+        let bp = self.prev(b);
+
+        self.nodes[a].next = b;
+        self.nodes[b].prev = a;
+
+        self.nodes[a2].next = an;
+        self.nodes[an].prev = a2;
+
+        self.nodes[b2].next = a2;
+        self.nodes[a2].prev = b2;
+
+        self.nodes[bp].next = b2;
+        self.nodes[b2].prev = bp;
+
+        b2
+    }
+
+    fn new_node(&mut self, i: usize, x: f64, y: f64) -> usize {
+        let index = self.nodes.len();
+        self.nodes.push(Node {
+            i,
+            x,
+            y,
+            prev: index,
+            next: index,
+            z: 0,
+            prev_z: None,
+            next_z: None,
+            steiner: false,
+        });
+        index
+    }
+
+    /// Creates a node and optionally links it with the previous one in a circular list.
+    fn insert_node(&mut self, i: usize, x: f64, y: f64, last: Option<usize>) -> usize {
+        let p = self.new_node(i, x, y);
+
+        if let Some(last) = last {
+            let last_next = self.next(last);
+            self.nodes[p].next = last_next;
+            self.nodes[p].prev = last;
+            self.nodes[last_next].prev = p;
+            self.nodes[last].next = p;
+        }
+
+        p
+    }
+
+    fn remove_node(&mut self, p: usize) {
+        let Node {
+            prev,
+            next,
+            prev_z,
+            next_z,
+            ..
+        } = self.nodes[p];
+
+        self.nodes[next].prev = prev;
+        self.nodes[prev].next = next;
+
+        if let Some(prev_z) = prev_z {
+            self.nodes[prev_z].next_z = next_z;
+        }
+        if let Some(next_z) = next_z {
+            self.nodes[next_z].prev_z = prev_z;
+        }
+    }
+}
+
+/// Z-order of a point given coords and inverse of the longer side of the data bbox.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn z_order(x: f64, y: f64, hash: Hash) -> u32 {
+    // Coords are transformed into non-negative 15-bit integer range.
+    let mut x = ((x - hash.min_x) * hash.inv_size) as u32;
+    let mut y = ((y - hash.min_y) * hash.inv_size) as u32;
+
+    x = (x | (x << 8)) & 0x00FF_00FF;
+    x = (x | (x << 4)) & 0x0F0F_0F0F;
+    x = (x | (x << 2)) & 0x3333_3333;
+    x = (x | (x << 1)) & 0x5555_5555;
+
+    y = (y | (y << 8)) & 0x00FF_00FF;
+    y = (y | (y << 4)) & 0x0F0F_0F0F;
+    y = (y | (y << 2)) & 0x3333_3333;
+    y = (y | (y << 1)) & 0x5555_5555;
+
+    x | (y << 1)
+}
+
+/// Whether a point lies within a triangle.
+#[allow(clippy::too_many_arguments)]
+fn point_in_triangle(
+    ax: f64,
+    ay: f64,
+    bx: f64,
+    by: f64,
+    cx: f64,
+    cy: f64,
+    px: f64,
+    py: f64,
+) -> bool {
+    (cx - px) * (ay - py) >= (ax - px) * (cy - py)
+        && (ax - px) * (by - py) >= (bx - px) * (ay - py)
+        && (bx - px) * (cy - py) >= (cx - px) * (by - py)
+}
+
+fn sign(value: f64) -> i8 {
+    if value > 0.0 {
+        1
+    } else if value < 0.0 {
+        -1
+    } else {
+        0
+    }
+}
+
+fn signed_area(data: &[f64], start: usize, end: usize) -> f64 {
+    let mut sum = 0.0;
+    let mut j = end - 2;
+    for i in (start..end).step_by(2) {
+        sum += (data[j] - data[i]) * (data[i + 1] + data[j + 1]);
+        j = i;
+    }
+    sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Flattens rings given as in the mapbox/earcut fixtures, the outer ring first.
+    fn flatten(fixture: &str) -> (Vec<f64>, Vec<usize>) {
+        let rings: Vec<Vec<[f64; 2]>> = serde_json::from_str(fixture).unwrap();
+        let mut data = Vec::new();
+        let mut hole_indices = Vec::new();
+        for (i, ring) in rings.iter().enumerate() {
+            if i > 0 {
+                hole_indices.push(data.len() / 2);
+            }
+            data.extend(ring.iter().flatten());
+        }
+        (data, hole_indices)
+    }
+
+    /// Relative difference between the polygon area and that of its triangles,
+    /// computed as in the mapbox/earcut test suite.
+    fn deviation(data: &[f64], hole_indices: &[usize], triangles: &[usize]) -> f64 {
+        let outer_len = hole_indices.first().map_or(data.len(), |index| index * 2);
+        let mut polygon_area = signed_area(data, 0, outer_len).abs();
+        for (i, &start) in hole_indices.iter().enumerate() {
+            let end = hole_indices
+                .get(i + 1)
+                .map_or(data.len(), |index| index * 2);
+            polygon_area -= signed_area(data, start * 2, end).abs();
+        }
+
+        // Both are doubled, as signed_area is.
+        let triangles_area: f64 = triangles
+            .chunks_exact(3)
+            .map(|triangle| {
+                let [a, b, c] = [triangle[0] * 2, triangle[1] * 2, triangle[2] * 2];
+                ((data[a] - data[c]) * (data[b + 1] - data[a + 1])
+                    - (data[a] - data[b]) * (data[c + 1] - data[a + 1]))
+                    .abs()
+            })
+            .sum();
+
+        if polygon_area < f64::EPSILON && triangles_area < f64::EPSILON {
+            0.0
+        } else {
+            ((triangles_area - polygon_area) / polygon_area).abs()
+        }
+    }
+
+    /// Checks the triangle count and covered area mapbox/earcut expects of a fixture.
+    fn assert_triangulates(fixture: &str, triangles: usize, max_deviation: f64) {
+        let (data, hole_indices) = flatten(fixture);
+        let indices = earcut(&data, &hole_indices);
+
+        assert_eq!(indices.len() % 3, 0);
+        assert_eq!(indices.len() / 3, triangles);
+        assert!(indices.iter().all(|&index| index < data.len() / 2));
+        let deviation = deviation(&data, &hole_indices, &indices);
+        assert!(
+            deviation <= max_deviation,
+            "deviation {deviation} exceeds {max_deviation}"
+        );
+    }
+
+    #[test]
+    fn matches_mapbox_indices() {
+        let indices = earcut(&[10.0, 0.0, 0.0, 50.0, 60.0, 60.0, 70.0, 10.0], &[]);
+        assert_eq!(indices, [1, 0, 3, 3, 2, 1]);
+    }
+
+    #[test]
+    fn triangulates_square() {
+        assert_triangulates("[[[0,0],[10,0],[10,10],[0,10]]]", 2, 0.0);
+    }
+
+    #[test]
+    fn triangulates_square_with_hole() {
+        assert_triangulates(
+            "[[[0,0],[10,0],[10,10],[0,10]],[[2,2],[2,8],[8,8],[8,2]]]",
+            8,
+            0.0,
+        );
+    }
+
+    // mapbox/earcut fixture `touching-holes`.
+    #[test]
+    fn triangulates_touching_holes() {
+        assert_triangulates(
+            r"[
+                [[3694,2061],[3794,2035],[3812,2123],[3784,2123],[3708,2139],[3694,2061]],
+                [[3752,2109],[3740,2102],[3712,2109],[3715,2125],[3723,2128],[3740,2124],[3742,2112],[3752,2109]],
+                [[3797,2101],[3787,2096],[3780,2106],[3788,2114],[3797,2101]],
+                [[3734,2099],[3732,2091],[3719,2094],[3721,2102],[3734,2099]],
+                [[3777,2082],[3774,2071],[3772,2086],[3765,2091],[3748,2088],[3749,2062],[3738,2081],[3745,2095],[3761,2099],[3777,2082]],
+                [[3719,2079],[3712,2079],[3706,2091],[3712,2097],[3721,2080],[3719,2079]],
+                [[3773,2067],[3761,2053],[3753,2061],[3753,2071],[3756,2075],[3773,2067]],
+                [[3708,2079],[3712,2079],[3714,2076],[3719,2079],[3722,2079],[3718,2088],[3723,2089],[3734,2075],[3730,2068],[3717,2065],[3708,2079]]
+            ]",
+            57,
+            0.0,
+        );
+    }
+
+    // mapbox/earcut fixture `degenerate`: every point lies on one line.
+    #[test]
+    fn skips_degenerate_ring() {
+        assert_triangulates(
+            "[[[100,100],[100,100],[200,100],[200,200],[200,100],[0,100]]]",
+            0,
+            0.0,
+        );
+    }
+
+    // mapbox/earcut fixture `empty-square`: the hole covers the whole ring.
+    #[test]
+    fn skips_fully_covered_ring() {
+        assert_triangulates(
+            "[[[0,0],[4000,0],[4000,4000],[0,4000]],[[0,0],[4000,0],[4000,4000],[0,4000]]]",
+            0,
+            0.0,
+        );
+    }
+
+    // mapbox/earcut fixture `shared-points`: the ring passes through two points twice.
+    #[test]
+    fn triangulates_self_touching_ring() {
+        assert_triangulates(
+            "[[[4136,1016],[4112,1016],[4104,976],[4136,1016],[4144,984],\
+               [4104,976],[4144,968],[4144,984],[4168,992],[4152,1064]]]",
+            4,
+            0.0,
+        );
+    }
+
+    #[test]
+    fn skips_short_input() {
+        assert!(earcut(&[], &[]).is_empty());
+        assert!(earcut(&[0.0, 0.0, 1.0, 1.0], &[]).is_empty());
+    }
+}
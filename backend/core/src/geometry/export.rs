@@ -0,0 +1,450 @@
+use crate::types::annotation::{AnnotationRecord, LayerRecord};
+use serde_json::{Map, Value, json};
+use shared::types::{Geometry, Polygon};
+use std::{borrow::Cow, collections::BTreeSet};
+
+/// `GeoJSON` feature collection of the layers. Features carry the tag of their layer as a
+/// `layer` member, or as a `classification` property in `QuPath`'s flavour.
+pub fn geojson(layers: &[LayerRecord], qupath: bool) -> Value {
+    let features: Vec<Value> = layers
+        .iter()
+        .flat_map(|layer| {
+            layer
+                .annotations
+                .iter()
+                .map(move |annotation| feature(layer, annotation, qupath))
+        })
+        .collect();
+
+    json!({
+        "type": "FeatureCollection",
+        "features": features,
+    })
+}
+
+fn feature(layer: &LayerRecord, annotation: &AnnotationRecord, qupath: bool) -> Value {
+    let geometry = geometry(&annotation.geometry);
+
+    if !qupath {
+        return json!({
+            "type": "Feature",
+            "id": annotation.id,
+            "layer": layer.tag,
+            "geometry": geometry,
+            "properties": annotation.properties,
+        });
+    }
+
+    // QuPath assigns its own UUIDs, so the id is left out.
+    let mut classification = Map::new();
+    classification.insert("name".into(), layer.tag.clone().into());
+    if let Some(colour) = rgb(&layer.fill) {
+        classification.insert("color".into(), json!(colour));
+    }
+
+    let mut properties = annotation.properties.clone();
+    properties
+        .entry("objectType")
+        .or_insert_with(|| "annotation".into());
+    properties.insert("classification".into(), classification.into());
+
+    json!({
+        "type": "Feature",
+        "geometry": geometry,
+        "properties": properties,
+    })
+}
+
+fn geometry(geometry: &Geometry) -> Value {
+    match geometry {
+        Geometry::Point(point) => json!({ "type": "Point", "coordinates": point }),
+        Geometry::LineString(points) => json!({ "type": "LineString", "coordinates": points }),
+        Geometry::Polygon(polygon) => json!({ "type": "Polygon", "coordinates": rings(polygon) }),
+        Geometry::MultiPolygon(polygons) => json!({
+            "type": "MultiPolygon",
+            "coordinates": polygons.iter().map(rings).collect::<Vec<_>>(),
+        }),
+    }
+}
+
+/// `GeoJSON` rings are closed, with the exterior first and holes after.
+// Closing points repeat the first exactly.
+#[allow(clippy::float_cmp)]
+fn rings(polygon: &Polygon) -> Vec<Vec<[f64; 2]>> {
+    std::iter::once(&polygon.exterior)
+        .chain(&polygon.interiors)
+        .map(|ring| {
+            let mut ring = ring.clone();
+            if let (Some(&first), Some(&last)) = (ring.first(), ring.last())
+                && first != last
+            {
+                ring.push(first);
+            }
+            ring
+        })
+        .collect()
+}
+
+/// `#RRGGBB` as the `[r, g, b]` `QuPath` uses.
+fn rgb(colour: &str) -> Option<[u8; 3]> {
+    let hex = colour.strip_prefix('#').filter(|hex| hex.len() == 6)?;
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+/// One row per annotation with its id, class, centroid and area, followed by a column
+/// per top-level property. Nested property values are written as JSON.
+pub fn csv(layers: &[LayerRecord]) -> String {
+    let keys: BTreeSet<&str> = layers
+        .iter()
+        .flat_map(|layer| &layer.annotations)
+        .flat_map(|annotation| annotation.properties.keys())
+        .map(String::as_str)
+        .collect();
+
+    let mut csv = String::new();
+    let header = ["id", "class", "centroid_x", "centroid_y", "area"];
+    let columns = property_columns(&header, &keys);
+    push_row(
+        &mut csv,
+        header.into_iter().chain(columns.iter().map(String::as_str)),
+    );
+
+    for layer in layers {
+        for annotation in &layer.annotations {
+            let [x, y] = centroid(&annotation.geometry);
+            let mut row = vec![
+                annotation.id.to_string(),
+                layer.tag.clone(),
+                x.to_string(),
+                y.to_string(),
+                area(&annotation.geometry).to_string(),
+            ];
+            row.extend(
+                keys.iter()
+                    .map(|key| match annotation.properties.get(*key) {
+                        None | Some(Value::Null) => String::new(),
+                        Some(Value::String(value)) => value.clone(),
+                        Some(value) => value.to_string(),
+                    }),
+            );
+
+            push_row(&mut csv, row.iter().map(String::as_str));
+        }
+    }
+
+    csv
+}
+
+/// Names of the property columns. Properties that would repeat the name of another
+/// column are prefixed with `property_` until they no longer do.
+fn property_columns(header: &[&str], keys: &BTreeSet<&str>) -> Vec<String> {
+    let mut names: BTreeSet<String> = header.iter().map(|name| (*name).to_string()).collect();
+
+    keys.iter()
+        .map(|key| {
+            let mut name = (*key).to_string();
+            while names.contains(&name) || (name != *key && keys.contains(name.as_str())) {
+                name = format!("property_{name}");
+            }
+            names.insert(name.clone());
+            name
+        })
+        .collect()
+}
+
+fn push_row<'a>(csv: &mut String, fields: impl Iterator<Item = &'a str>) {
+    let fields: Vec<Cow<str>> = fields.map(escape).collect();
+    csv.push_str(&fields.join(","));
+    csv.push('\n');
+}
+
+/// Quotes fields holding separators, quotes or line breaks.
+fn escape(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\"")).into()
+    } else {
+        field.into()
+    }
+}
+
+/// Area enclosed by the geometry, which is zero for points and lines.
+fn area(geometry: &Geometry) -> f64 {
+    polygons(geometry)
+        .iter()
+        .map(|polygon| polygon_moments(polygon).0)
+        // `sum` would give -0 for points and lines.
+        .fold(0.0, |area, polygon_area| area + polygon_area)
+}
+
+/// Centre of mass of the enclosed area, or the mean of the vertices without area.
+fn centroid(geometry: &Geometry) -> [f64; 2] {
+    let (area, moment_x, moment_y) = polygons(geometry)
+        .iter()
+        .map(polygon_moments)
+        .fold((0.0, 0.0, 0.0), |(a, x, y), (da, dx, dy)| {
+            (a + da, x + dx, y + dy)
+        });
+    if area > 0.0 {
+        return [moment_x / area, moment_y / area];
+    }
+
+    let vertices: &[[f64; 2]] = match geometry {
+        Geometry::Point(point) => std::slice::from_ref(point),
+        Geometry::LineString(points) => points,
+        Geometry::Polygon(polygon) => &polygon.exterior,
+        Geometry::MultiPolygon(polygons) => polygons
+            .first()
+            .map_or(&[][..], |polygon| &polygon.exterior),
+    };
+    let (sum_x, sum_y, count) = vertices
+        .iter()
+        .fold((0.0, 0.0, 0.0), |(x, y, n), [vx, vy]| {
+            (x + vx, y + vy, n + 1.0)
+        });
+
+    if count > 0.0 {
+        [sum_x / count, sum_y / count]
+    } else {
+        [0.0, 0.0]
+    }
+}
+
+fn polygons(geometry: &Geometry) -> &[Polygon] {
+    match geometry {
+        Geometry::Polygon(polygon) => std::slice::from_ref(polygon),
+        Geometry::MultiPolygon(polygons) => polygons,
+        Geometry::Point(_) | Geometry::LineString(_) => &[],
+    }
+}
+
+/// Area and first moments of a polygon, with holes subtracted.
+fn polygon_moments(polygon: &Polygon) -> (f64, f64, f64) {
+    std::iter::once((&polygon.exterior, 1.0))
+        .chain(polygon.interiors.iter().map(|ring| (ring, -1.0)))
+        .map(|(ring, sign)| {
+            let (area, moment_x, moment_y) = ring_moments(ring);
+            // Rings may wind either way, so the sign comes from their role.
+            let sign = sign * area.signum();
+            (sign * area, sign * moment_x, sign * moment_y)
+        })
+        .fold((0.0, 0.0, 0.0), |(a, x, y), (da, dx, dy)| {
+            (a + da, x + dx, y + dy)
+        })
+}
+
+/// Signed area and first moments of a ring, by the shoelace formula.
+fn ring_moments(ring: &[[f64; 2]]) -> (f64, f64, f64) {
+    let (mut area, mut moment_x, mut moment_y) = (0.0, 0.0, 0.0);
+
+    for (i, &[x0, y0]) in ring.iter().enumerate() {
+        let [x1, y1] = ring[(i + 1) % ring.len()];
+        let cross = x0 * y1 - x1 * y0;
+        area += cross;
+        moment_x += (x0 + x1) * cross;
+        moment_y += (y0 + y1) * cross;
+    }
+
+    (area / 2.0, moment_x / 6.0, moment_y / 6.0)
+}
+
+#[cfg(test)]
+// Fixtures use small integer coordinates, whose results are exact.
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::*;
+    use shared::types::{Bounds, Properties};
+
+    fn square(x: f64, y: f64, size: f64) -> Vec<[f64; 2]> {
+        vec![[x, y], [x + size, y], [x + size, y + size], [x, y + size]]
+    }
+
+    fn reversed(mut ring: Vec<[f64; 2]>) -> Vec<[f64; 2]> {
+        ring.reverse();
+        ring
+    }
+
+    fn record(id: u32, geometry: Geometry, properties: Value) -> AnnotationRecord {
+        let Value::Object(properties) = properties else {
+            panic!("Properties must be an object.");
+        };
+        AnnotationRecord {
+            id,
+            layer_id: 0,
+            geometry,
+            properties: properties as Properties,
+            bounds: Bounds {
+                min_x: 0.0,
+                min_y: 0.0,
+                max_x: 0.0,
+                max_y: 0.0,
+            },
+        }
+    }
+
+    fn layer(tag: &str, annotations: Vec<AnnotationRecord>) -> LayerRecord {
+        LayerRecord {
+            id: 0,
+            tag: tag.into(),
+            fill: "#FF8000".into(),
+            annotations,
+        }
+    }
+
+    #[test]
+    fn ring_moments_are_signed_by_winding() {
+        let (area, moment_x, moment_y) = ring_moments(&square(0.0, 0.0, 2.0));
+        assert_eq!((area, moment_x / area, moment_y / area), (4.0, 1.0, 1.0));
+
+        let (area, moment_x, moment_y) = ring_moments(&reversed(square(0.0, 0.0, 2.0)));
+        assert_eq!((area, moment_x / area, moment_y / area), (-4.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn holes_are_subtracted_whichever_way_rings_wind() {
+        // A 4x4 square with a 2x2 hole in its left half.
+        for (exterior, hole) in [
+            (square(0.0, 0.0, 4.0), square(0.0, 1.0, 2.0)),
+            (square(0.0, 0.0, 4.0), reversed(square(0.0, 1.0, 2.0))),
+            (reversed(square(0.0, 0.0, 4.0)), square(0.0, 1.0, 2.0)),
+        ] {
+            let geometry = Geometry::Polygon(Polygon {
+                exterior,
+                interiors: vec![hole],
+            });
+
+            assert_eq!(area(&geometry), 12.0);
+            let [x, y] = centroid(&geometry);
+            assert!((x - 7.0 / 3.0).abs() < 1e-12, "{x}");
+            assert_eq!(y, 2.0);
+        }
+    }
+
+    #[test]
+    fn multi_polygons_combine_their_parts() {
+        let geometry = Geometry::MultiPolygon(vec![
+            Polygon::new(square(0.0, 0.0, 2.0)),
+            Polygon::new(reversed(square(4.0, 0.0, 2.0))),
+        ]);
+
+        assert_eq!(area(&geometry), 8.0);
+        assert_eq!(centroid(&geometry), [3.0, 1.0]);
+    }
+
+    #[test]
+    fn points_and_lines_have_no_area() {
+        let point = Geometry::Point([3.0, 4.0]);
+        let line = Geometry::LineString(vec![[0.0, 0.0], [4.0, 2.0]]);
+
+        assert_eq!(area(&point).to_string(), "0");
+        assert_eq!(area(&line).to_string(), "0");
+        assert_eq!(centroid(&point), [3.0, 4.0]);
+        assert_eq!(centroid(&line), [2.0, 1.0]);
+    }
+
+    #[test]
+    fn escapes_csv_fields() {
+        assert_eq!(escape("plain"), "plain");
+        assert_eq!(escape("a,b"), "\"a,b\"");
+        assert_eq!(escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(escape("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn csv_has_a_column_per_property() {
+        let layers = [layer(
+            "Tumour, grade 2",
+            vec![
+                record(
+                    1,
+                    Geometry::Polygon(Polygon::new(square(0.0, 0.0, 2.0))),
+                    json!({"score": 0.5, "meta": {"by": "A"}}),
+                ),
+                record(2, Geometry::Point([1.0, 2.0]), json!({"note": null})),
+            ],
+        )];
+
+        assert_eq!(
+            csv(&layers),
+            "id,class,centroid_x,centroid_y,area,meta,note,score\n\
+             1,\"Tumour, grade 2\",1,1,4,\"{\"\"by\"\":\"\"A\"\"}\",,0.5\n\
+             2,\"Tumour, grade 2\",1,2,0,,,\n"
+        );
+    }
+
+    #[test]
+    fn csv_prefixes_properties_clashing_with_columns() {
+        let layers = [layer(
+            "Cells",
+            vec![record(
+                7,
+                Geometry::Point([0.0, 0.0]),
+                json!({"id": "external", "area": 12, "property_id": "kept"}),
+            )],
+        )];
+
+        let csv = csv(&layers);
+        let mut lines = csv.lines();
+
+        assert_eq!(
+            lines.next(),
+            Some(
+                "id,class,centroid_x,centroid_y,area,property_area,property_property_id,property_id"
+            )
+        );
+        assert_eq!(lines.next(), Some("7,Cells,0,0,0,12,external,kept"));
+    }
+
+    #[test]
+    fn qupath_features_carry_classification() {
+        let layers = [layer(
+            "Tumour",
+            vec![record(
+                3,
+                Geometry::Polygon(Polygon::new(square(0.0, 0.0, 1.0))),
+                json!({"score": 1}),
+            )],
+        )];
+
+        let document = geojson(&layers, true);
+
+        assert_eq!(
+            document["features"][0],
+            json!({
+                "type": "Feature",
+                "geometry": {
+                    "type": "Polygon",
+                    "coordinates": [[[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0], [0.0, 0.0]]],
+                },
+                "properties": {
+                    "score": 1,
+                    "objectType": "annotation",
+                    "classification": {"name": "Tumour", "color": [255, 128, 0]},
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn geojson_features_carry_id_and_layer() {
+        let layers = [layer(
+            "Cells",
+            vec![record(5, Geometry::Point([1.0, 2.0]), json!({}))],
+        )];
+
+        let document = geojson(&layers, false);
+
+        assert_eq!(document["type"], "FeatureCollection");
+        assert_eq!(
+            document["features"][0],
+            json!({
+                "type": "Feature",
+                "id": 5,
+                "layer": "Cells",
+                "geometry": {"type": "Point", "coordinates": [1.0, 2.0]},
+                "properties": {},
+            })
+        );
+    }
+}
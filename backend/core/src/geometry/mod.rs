@@ -0,0 +1,435 @@
+mod earcut;
+pub mod export;
+mod simplify;
+
+pub use simplify::simplify;
+
+use crate::{
+    constants::{ANNOTATION_LINE_WIDTH, ANNOTATION_POINT_RADIUS, ANNOTATION_POINT_VERTICES},
+    types::database::DatabaseManager,
+};
+use anyhow::{Result, anyhow};
+use serde_json::json;
+use shared::types::{AnnotationLayer, Bounds, Geometry, Polygon};
+use std::{f64::consts::TAU, fs, path::Path};
+
+// glTF constants.
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const GLB_VERSION: u32 = 2;
+const CHUNK_JSON: &[u8; 4] = b"JSON";
+const CHUNK_BIN: &[u8; 4] = b"BIN\0";
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const ARRAY_BUFFER: u32 = 34962;
+const UNSIGNED_INT: u32 = 5125;
+const FLOAT: u32 = 5126;
+
+/// Triangulated annotations of a layer, merged into a single mesh.
+#[derive(Default)]
+pub struct Mesh {
+    pub indices: Vec<u32>,
+    pub positions: Vec<[f32; 3]>,
+    pub uvs: Vec<[f32; 2]>,
+}
+
+impl Mesh {
+    /// Triangulates a geometry and appends it to the mesh.
+    /// Points and lines have no area, so they are drawn as discs and thin quads.
+    pub fn push(&mut self, geometry: &Geometry) {
+        match geometry {
+            Geometry::Point(point) => self.push_point(*point),
+            Geometry::LineString(points) => self.push_line(points),
+            Geometry::Polygon(polygon) => self.push_polygon(&polygon.exterior, &polygon.interiors),
+            Geometry::MultiPolygon(polygons) => {
+                for Polygon {
+                    exterior,
+                    interiors,
+                } in polygons
+                {
+                    self.push_polygon(exterior, interiors);
+                }
+            }
+        }
+    }
+
+    fn push_point(&mut self, [x, y]: [f64; 2]) {
+        let disc: Vec<[f64; 2]> = (0..ANNOTATION_POINT_VERTICES)
+            .map(|i| {
+                let angle = TAU * f64::from(i) / f64::from(ANNOTATION_POINT_VERTICES);
+                [
+                    x + ANNOTATION_POINT_RADIUS * angle.cos(),
+                    y + ANNOTATION_POINT_RADIUS * angle.sin(),
+                ]
+            })
+            .collect();
+
+        self.push_polygon(&disc, &[]);
+    }
+
+    fn push_line(&mut self, points: &[[f64; 2]]) {
+        for segment in points.windows(2) {
+            let ([x0, y0], [x1, y1]) = (segment[0], segment[1]);
+            let length = (x1 - x0).hypot(y1 - y0);
+            if length < f64::EPSILON {
+                continue;
+            }
+
+            // Offset perpendicular to the segment by half the line width.
+            let scale = ANNOTATION_LINE_WIDTH / 2.0 / length;
+            let (dx, dy) = (-(y1 - y0) * scale, (x1 - x0) * scale);
+
+            self.push_polygon(
+                &[
+                    [x0 + dx, y0 + dy],
+                    [x1 + dx, y1 + dy],
+                    [x1 - dx, y1 - dy],
+                    [x0 - dx, y0 - dy],
+                ],
+                &[],
+            );
+        }
+    }
+
+    /// Mirrors Three.js `ShapeGeometry`: the image y axis is flipped, the contour
+    /// is wound clockwise and its holes counter-clockwise.
+    fn push_polygon(&mut self, contour: &[[f64; 2]], holes: &[Vec<[f64; 2]>]) {
+        let mut contour = ring(contour);
+        if contour.len() < 3 {
+            return;
+        }
+        if !is_clockwise(&contour) {
+            contour.reverse();
+        }
+
+        let mut vertices = contour;
+        let mut hole_indices = Vec::with_capacity(holes.len());
+        for hole in holes {
+            let mut hole = ring(hole);
+            if hole.len() < 3 {
+                continue;
+            }
+            if is_clockwise(&hole) {
+                hole.reverse();
+            }
+            hole_indices.push(vertices.len());
+            vertices.extend(hole);
+        }
+
+        let data: Vec<f64> = vertices.iter().flatten().copied().collect();
+        let triangles = earcut::earcut(&data, &hole_indices);
+        if triangles.is_empty() {
+            return;
+        }
+
+        let offset = u32::try_from(self.positions.len()).expect("Mesh exceeds u32 indices");
+        self.indices.extend(
+            triangles
+                .into_iter()
+                .map(|i| offset + u32::try_from(i).expect("Mesh exceeds u32 indices")),
+        );
+
+        #[allow(clippy::cast_possible_truncation)]
+        for [x, y] in vertices {
+            self.positions.push([x as f32, y as f32, 0.0]);
+            self.uvs.push([x as f32, y as f32]);
+        }
+    }
+
+    /// Encodes the mesh as a binary glTF document.
+    pub fn to_glb(&self) -> Vec<u8> {
+        let mut bin = Vec::with_capacity(
+            self.indices.len() * 4 + self.positions.len() * 12 + self.uvs.len() * 8,
+        );
+        for index in &self.indices {
+            bin.extend(index.to_le_bytes());
+        }
+        let positions_offset = bin.len();
+        for position in self.positions.iter().flatten() {
+            bin.extend(position.to_le_bytes());
+        }
+        let uvs_offset = bin.len();
+        for uv in self.uvs.iter().flatten() {
+            bin.extend(uv.to_le_bytes());
+        }
+
+        let mut node = json!({ "name": "MyNode", "translation": [0, 0, 0] });
+        let mut document = json!({
+            "asset": { "version": "2.0", "generator": "magie" },
+            "scene": 0,
+            "scenes": [{ "name": "MyScene", "nodes": [0] }],
+            "materials": [{
+                "pbrMetallicRoughness": { "baseColorFactor": [1, 0.5, 0.5, 1] }
+            }],
+        });
+
+        // An empty layer has nothing to draw, but still loads as an empty scene.
+        if !self.indices.is_empty() {
+            let (min, max) = self.bounds();
+
+            node["mesh"] = json!(0);
+            document["meshes"] = json!([{
+                "name": "MyMesh",
+                "primitives": [{
+                    "attributes": { "POSITION": 1, "TEXCOORD_0": 2 },
+                    "indices": 0,
+                    "material": 0,
+                }],
+            }]);
+            document["accessors"] = json!([
+                {
+                    "bufferView": 0,
+                    "componentType": UNSIGNED_INT,
+                    "count": self.indices.len(),
+                    "type": "SCALAR",
+                },
+                {
+                    "bufferView": 1,
+                    "componentType": FLOAT,
+                    "count": self.positions.len(),
+                    "type": "VEC3",
+                    "min": min,
+                    "max": max,
+                },
+                {
+                    "bufferView": 2,
+                    "componentType": FLOAT,
+                    "count": self.uvs.len(),
+                    "type": "VEC2",
+                },
+            ]);
+            document["bufferViews"] = json!([
+                {
+                    "buffer": 0,
+                    "byteOffset": 0,
+                    "byteLength": positions_offset,
+                    "target": ELEMENT_ARRAY_BUFFER,
+                },
+                {
+                    "buffer": 0,
+                    "byteOffset": positions_offset,
+                    "byteLength": uvs_offset - positions_offset,
+                    "target": ARRAY_BUFFER,
+                },
+                {
+                    "buffer": 0,
+                    "byteOffset": uvs_offset,
+                    "byteLength": bin.len() - uvs_offset,
+                    "target": ARRAY_BUFFER,
+                },
+            ]);
+            document["buffers"] = json!([{ "byteLength": bin.len() }]);
+        }
+        document["nodes"] = json!([node]);
+
+        // Chunks are 4-byte aligned: JSON is padded with spaces, binary with zeros.
+        let mut json = document.to_string().into_bytes();
+        json.resize(json.len().next_multiple_of(4), b' ');
+        bin.resize(bin.len().next_multiple_of(4), 0);
+
+        let mut chunks = vec![(CHUNK_JSON, json)];
+        if !bin.is_empty() {
+            chunks.push((CHUNK_BIN, bin));
+        }
+
+        let length = 12 + chunks.iter().map(|(_, data)| 8 + data.len()).sum::<usize>();
+        let mut glb = Vec::with_capacity(length);
+        glb.extend(GLB_MAGIC);
+        glb.extend(GLB_VERSION.to_le_bytes());
+        glb.extend(chunk_length(length).to_le_bytes());
+        for (kind, data) in chunks {
+            glb.extend(chunk_length(data.len()).to_le_bytes());
+            glb.extend(kind);
+            glb.extend(data);
+        }
+
+        glb
+    }
+
+    fn bounds(&self) -> ([f32; 3], [f32; 3]) {
+        self.positions.iter().fold(
+            ([f32::INFINITY; 3], [f32::NEG_INFINITY; 3]),
+            |(mut min, mut max), position| {
+                for axis in 0..3 {
+                    min[axis] = min[axis].min(position[axis]);
+                    max[axis] = max[axis].max(position[axis]);
+                }
+                (min, max)
+            },
+        )
+    }
+}
+
+/// Triangulates every annotation of the layer and saves it to `{prefix}{id}.glb`.
+pub fn write_glb(layer: &AnnotationLayer, prefix: &Path) -> Result<()> {
+    let mut mesh = Mesh::default();
+    for annotation in &layer.annotations {
+        mesh.push(&annotation.geometry);
+    }
+
+    let mut path = prefix.as_os_str().to_owned();
+    path.push(format!("{}.glb", layer.id));
+    fs::write(path, mesh.to_glb())?;
+
+    Ok(())
+}
+
+/// Bounds of what is drawn for a geometry, which for points and lines extends past their coordinates.
+pub fn drawn_bounds(geometry: &Geometry) -> Option<Bounds> {
+    let padding = match geometry {
+        Geometry::Point(_) => ANNOTATION_POINT_RADIUS,
+        Geometry::LineString(_) => ANNOTATION_LINE_WIDTH / 2.0,
+        Geometry::Polygon(_) | Geometry::MultiPolygon(_) => 0.0,
+    };
+
+    geometry.bounds().map(|bounds| Bounds {
+        min_x: bounds.min_x - padding,
+        min_y: bounds.min_y - padding,
+        max_x: bounds.max_x + padding,
+        max_y: bounds.max_y + padding,
+    })
+}
+
+/// Rewrites the GLB of a layer from the annotations stored in the database.
+pub fn rebuild_glb(
+    dbm: &DatabaseManager,
+    store_id: u32,
+    image_id: u32,
+    layer_id: u32,
+) -> Result<()> {
+    let prefix = crate::db::image::annotations_prefix(dbm, store_id, image_id)?;
+
+    // The store stays locked until the file is written, so concurrent edits cannot
+    // overwrite it with an older state of the layer.
+    let conn = dbm.store(store_id)?;
+    let layer = crate::db::annotation::layer(&conn, image_id, layer_id)?
+        .ok_or_else(|| anyhow!("Annotation layer does not exist."))?;
+
+    write_glb(&layer, &prefix)
+}
+
+/// Flips the y axis and drops repeated points, including a closing point equal to the first.
+fn ring(points: &[[f64; 2]]) -> Vec<[f64; 2]> {
+    let mut ring: Vec<[f64; 2]> = Vec::with_capacity(points.len());
+    for &[x, y] in points {
+        let point = [x, -y];
+        if ring.last() != Some(&point) {
+            ring.push(point);
+        }
+    }
+    if ring.len() > 1 && ring.first() == ring.last() {
+        ring.pop();
+    }
+    ring
+}
+
+/// Clockwise in Three.js terms, i.e. negative shoelace area.
+fn is_clockwise(ring: &[[f64; 2]]) -> bool {
+    let mut area = 0.0;
+    let mut j = ring.len() - 1;
+    for (i, point) in ring.iter().enumerate() {
+        area += ring[j][0] * point[1] - point[0] * ring[j][1];
+        j = i;
+    }
+    area < 0.0
+}
+
+fn chunk_length(length: usize) -> u32 {
+    u32::try_from(length).expect("GLB exceeds 4 GiB")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    fn u32_at(glb: &[u8], offset: usize) -> usize {
+        u32::from_le_bytes(glb[offset..offset + 4].try_into().unwrap()) as usize
+    }
+
+    /// Splits a GLB into its JSON document and binary chunk, checking the layout.
+    fn parse_glb(glb: &[u8]) -> (Value, Option<&[u8]>) {
+        assert_eq!(&glb[0..4], GLB_MAGIC);
+        assert_eq!(u32_at(glb, 4), GLB_VERSION as usize);
+        assert_eq!(u32_at(glb, 8), glb.len());
+
+        let mut chunks = Vec::new();
+        let mut offset = 12;
+        while offset < glb.len() {
+            let length = u32_at(glb, offset);
+            assert_eq!(length % 4, 0);
+            chunks.push((
+                &glb[offset + 4..offset + 8],
+                &glb[offset + 8..offset + 8 + length],
+            ));
+            offset += 8 + length;
+        }
+        assert_eq!(offset, glb.len());
+
+        let (kind, json) = chunks[0];
+        assert_eq!(kind, CHUNK_JSON);
+        let document = serde_json::from_slice(json).unwrap();
+        let bin = chunks.get(1).map(|&(kind, bin)| {
+            assert_eq!(kind, CHUNK_BIN);
+            bin
+        });
+        assert!(chunks.len() <= 2);
+
+        (document, bin)
+    }
+
+    #[test]
+    fn encodes_glb_layout() {
+        let mut mesh = Mesh::default();
+        mesh.push(&Geometry::Polygon(Polygon {
+            exterior: vec![
+                [0.0, 0.0],
+                [10.0, 0.0],
+                [10.0, 10.0],
+                [0.0, 10.0],
+                [0.0, 0.0],
+            ],
+            interiors: vec![],
+        }));
+        assert_eq!(mesh.indices.len(), 6);
+        assert_eq!(mesh.positions.len(), 4);
+
+        let glb = mesh.to_glb();
+        let (document, bin) = parse_glb(&glb);
+        let bin = bin.expect("GLB has no binary chunk");
+
+        let views = document["bufferViews"].as_array().unwrap();
+        let lengths = [6 * 4, 4 * 12, 4 * 8];
+        let mut offset = 0usize;
+        for (view, length) in views.iter().zip(lengths) {
+            assert_eq!(view["byteOffset"], offset);
+            assert_eq!(view["byteLength"], length);
+            offset += length;
+        }
+        assert_eq!(document["buffers"][0]["byteLength"], offset);
+        assert_eq!(bin.len(), offset.next_multiple_of(4));
+
+        let indices: Vec<u32> = bin[..6 * 4]
+            .chunks_exact(4)
+            .map(|index| u32::from_le_bytes(index.try_into().unwrap()))
+            .collect();
+        assert_eq!(indices, mesh.indices);
+        assert_eq!(document["accessors"][0]["count"], 6);
+        assert_eq!(
+            document["accessors"][1]["min"],
+            serde_json::json!([0.0, -10.0, 0.0])
+        );
+        assert_eq!(
+            document["accessors"][1]["max"],
+            serde_json::json!([10.0, 0.0, 0.0])
+        );
+    }
+
+    #[test]
+    fn encodes_empty_glb() {
+        let glb = Mesh::default().to_glb();
+        let (document, bin) = parse_glb(&glb);
+
+        assert!(bin.is_none());
+        assert!(document.get("meshes").is_none());
+        assert_eq!(document["nodes"][0]["name"], "MyNode");
+    }
+}
@@ -0,0 +1,203 @@
+use shared::types::{Geometry, Polygon};
+
+/// Removes vertices that deviate less than `tolerance` from the simplified outline,
+/// using Douglas-Peucker. Returns `None` if nothing drawable is left.
+pub fn simplify(geometry: &Geometry, tolerance: f64) -> Option<Geometry> {
+    match geometry {
+        Geometry::Point(point) => Some(Geometry::Point(*point)),
+        Geometry::LineString(points) => {
+            let points = simplify_line(points, tolerance);
+            (points.len() >= 2).then_some(Geometry::LineString(points))
+        }
+        Geometry::Polygon(polygon) => simplify_polygon(polygon, tolerance).map(Geometry::Polygon),
+        Geometry::MultiPolygon(polygons) => {
+            let polygons: Vec<Polygon> = polygons
+                .iter()
+                .filter_map(|polygon| simplify_polygon(polygon, tolerance))
+                .collect();
+            (!polygons.is_empty()).then_some(Geometry::MultiPolygon(polygons))
+        }
+    }
+}
+
+fn simplify_polygon(polygon: &Polygon, tolerance: f64) -> Option<Polygon> {
+    let exterior = simplify_ring(&polygon.exterior, tolerance)?;
+    let interiors = polygon
+        .interiors
+        .iter()
+        .filter_map(|interior| simplify_ring(interior, tolerance))
+        .collect();
+
+    Some(Polygon {
+        exterior,
+        interiors,
+    })
+}
+
+/// Simplifies a ring, dropping it if it collapses to fewer than three vertices.
+// Closing points repeat the first exactly.
+#[allow(clippy::float_cmp)]
+fn simplify_ring(points: &[[f64; 2]], tolerance: f64) -> Option<Vec<[f64; 2]>> {
+    // Rings may or may not repeat their first point at the end.
+    let open = match points {
+        [first, .., last] if first == last => &points[..points.len() - 1],
+        _ => points,
+    };
+    if open.len() < 3 {
+        return None;
+    }
+
+    // Closing the ring makes both ends the same vertex, which is always kept.
+    let mut closed = open.to_vec();
+    closed.push(open[0]);
+
+    let mut simplified = simplify_line(&closed, tolerance);
+    simplified.pop();
+
+    (simplified.len() >= 3).then_some(simplified)
+}
+
+fn simplify_line(points: &[[f64; 2]], tolerance: f64) -> Vec<[f64; 2]> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+
+    // Spans still to be simplified, as indices of their kept end vertices.
+    let mut spans = vec![(0, points.len() - 1)];
+    while let Some((start, end)) = spans.pop() {
+        let farthest = (start + 1..end)
+            .map(|i| (i, distance(points[i], points[start], points[end])))
+            .max_by(|(_, a), (_, b)| a.total_cmp(b));
+
+        if let Some((i, distance)) = farthest
+            && distance > tolerance
+        {
+            keep[i] = true;
+            spans.push((start, i));
+            spans.push((i, end));
+        }
+    }
+
+    points
+        .iter()
+        .zip(keep)
+        .filter_map(|(point, keep)| keep.then_some(*point))
+        .collect()
+}
+
+/// Distance from a point to the segment between `a` and `b`.
+fn distance([x, y]: [f64; 2], [ax, ay]: [f64; 2], [bx, by]: [f64; 2]) -> f64 {
+    let (dx, dy) = (bx - ax, by - ay);
+    let length = dx * dx + dy * dy;
+
+    let t = if length == 0.0 {
+        0.0
+    } else {
+        (((x - ax) * dx + (y - ay) * dy) / length).clamp(0.0, 1.0)
+    };
+
+    (x - (ax + t * dx)).hypot(y - (ay + t * dy))
+}
+
+#[cfg(test)]
+// Kept vertices are copied, so they compare exactly.
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::*;
+
+    // A square with a vertex in the middle of each side, at most `bump` off the side.
+    fn square(bump: f64) -> Vec<[f64; 2]> {
+        vec![
+            [0.0, 0.0],
+            [5.0, bump],
+            [10.0, 0.0],
+            [10.0, 10.0],
+            [5.0, 10.0],
+            [0.0, 10.0],
+        ]
+    }
+
+    #[test]
+    fn drops_vertices_within_tolerance() {
+        let line = [[0.0, 0.0], [1.0, 0.1], [2.0, -0.1], [3.0, 5.0], [4.0, 0.0]];
+
+        assert_eq!(
+            simplify_line(&line, 0.5),
+            [[0.0, 0.0], [2.0, -0.1], [3.0, 5.0], [4.0, 0.0]]
+        );
+        assert_eq!(simplify_line(&line, 10.0), [[0.0, 0.0], [4.0, 0.0]]);
+    }
+
+    #[test]
+    fn simplifies_rings_whether_closed_or_not() {
+        let open = square(0.1);
+        let mut closed = open.clone();
+        closed.push(open[0]);
+
+        let expected = [[0.0, 0.0], [10.0, 0.0], [10.0, 10.0], [0.0, 10.0]];
+        assert_eq!(simplify_ring(&open, 0.5).unwrap(), expected);
+        assert_eq!(simplify_ring(&closed, 0.5).unwrap(), expected);
+    }
+
+    #[test]
+    fn keeps_vertices_beyond_tolerance() {
+        assert_eq!(simplify_ring(&square(2.0), 0.5).unwrap().len(), 5);
+    }
+
+    #[test]
+    fn drops_rings_collapsing_below_three_vertices() {
+        let sliver = [[0.0, 0.0], [5.0, 0.1], [10.0, 0.0], [5.0, -0.1]];
+
+        assert_eq!(simplify_ring(&sliver, 0.5), None);
+        assert_eq!(
+            simplify_ring(&[[0.0, 0.0], [1.0, 1.0], [0.0, 0.0]], 0.0),
+            None
+        );
+        assert!(simplify(&Geometry::Polygon(Polygon::new(sliver.to_vec())), 0.5).is_none());
+    }
+
+    #[test]
+    fn drops_holes_that_collapse() {
+        let polygon = Polygon {
+            exterior: square(0.0),
+            interiors: vec![
+                vec![[4.0, 4.0], [6.0, 4.1], [8.0, 4.0], [6.0, 3.9]],
+                vec![[2.0, 2.0], [2.0, 8.0], [8.0, 8.0]],
+            ],
+        };
+
+        let Some(Geometry::Polygon(simplified)) = simplify(&Geometry::Polygon(polygon), 0.5) else {
+            panic!("Expected a polygon.");
+        };
+
+        assert_eq!(simplified.exterior.len(), 4);
+        assert_eq!(
+            simplified.interiors,
+            [vec![[2.0, 2.0], [2.0, 8.0], [8.0, 8.0]]]
+        );
+    }
+
+    #[test]
+    fn drops_collapsed_parts_of_multi_polygons() {
+        let sliver = vec![[0.0, 0.0], [5.0, 0.1], [10.0, 0.0], [5.0, -0.1]];
+
+        assert!(
+            simplify(
+                &Geometry::MultiPolygon(vec![Polygon::new(sliver.clone())]),
+                0.5
+            )
+            .is_none()
+        );
+        assert!(matches!(
+            simplify(
+                &Geometry::MultiPolygon(vec![Polygon::new(sliver), Polygon::new(square(0.0))]),
+                0.5
+            ),
+            Some(Geometry::MultiPolygon(polygons)) if polygons.len() == 1
+        ));
+    }
+}
@@ -1,19 +1,24 @@
 use crate::{
     constants::{
-        ANNOTATIONS_DIRECTORY, LOCAL_DATABASES_PATH, LOCAL_STORES_PATH, MAX_THUMBNAIL_SIZE,
-        UPLOADED_DIRECTORY,
+        ANNOTATIONS_DIRECTORY, LOCAL_DATABASES_PATH, LOCAL_STORES_PATH, MAX_REGION_SOURCE_PIXELS,
+        MAX_THUMBNAIL_SIZE, UPLOADED_DIRECTORY,
     },
-    types::messages::TileServerMsg,
+    types::messages::{Subsampling, TileFormat},
 };
 use anyhow::Result;
-use image::RgbImage;
+use image::{
+    ExtendedColorType, ImageEncoder, RgbImage,
+    codecs::{png::PngEncoder, tiff::TiffEncoder, webp::WebPEncoder},
+    imageops::{self, FilterType},
+};
 use shared::{
     constants::{TILE_SIZE, TILE_SPLIT_LENGTH},
     traits::Encoder,
-    types::{MetadataLayer, Size},
+    types::{Address, MetadataLayer, Region, Size},
 };
 use std::{
     fs,
+    io::Cursor,
     path::{Path, PathBuf},
 };
 use tempfile::NamedTempFile;
@@ -68,7 +73,7 @@ pub fn save_asset(file: NamedTempFile, path: &Path) -> Result<()> {
 }
 
 // TODO: Remove encoder hardcode.
-pub fn retrieve(path: &Path, level: u32, x: u32, y: u32) -> Result<TileServerMsg> {
+pub fn retrieve(path: &Path, level: u32, x: u32, y: u32, format: TileFormat) -> Result<Vec<u8>> {
     let Some(encoder) = encoders::export::get("OMEZarr") else {
         return Err(anyhow::anyhow!("Could not get encoder."));
     };
@@ -76,30 +81,172 @@ pub fn retrieve(path: &Path, level: u32, x: u32, y: u32) -> Result<TileServerMsg
     let mut rgb_buffer = vec![0_u8; TILE_SPLIT_LENGTH].into_boxed_slice();
     encoder.retrieve(&mut rgb_buffer, path, level, x, y)?;
 
-    let Some(bmp_buffer) = RgbImage::from_raw(TILE_SIZE, TILE_SIZE, rgb_buffer.into()) else {
+    let Some(tile) = RgbImage::from_raw(TILE_SIZE, TILE_SIZE, rgb_buffer.into()) else {
         return Err(anyhow::anyhow!("RGB data doesn't fit into image buffer."));
     };
 
-    let jpeg_buffer = turbojpeg::compress_image(&bmp_buffer, 70, turbojpeg::Subsamp::Sub2x2)?;
+    encode(tile, format)
+}
+
+/// Maps a level 0 region onto the lowest resolution level that still has enough
+/// detail for the requested output size.
+pub fn source_region(layers: &[MetadataLayer], region: &Region, output: &Size) -> Result<Region> {
+    let Some(base) = layers.iter().find(|layer| layer.level == 0) else {
+        return Err(anyhow::anyhow!("Image has no level 0."));
+    };
+
+    let Region {
+        size: Size { width, height },
+        address: Address { x, y },
+        ..
+    } = *region;
+
+    if width == 0 || height == 0 || output.width == 0 || output.height == 0 {
+        return Err(anyhow::anyhow!("Region is empty."));
+    }
+
+    // Pick the smallest level that is at least as detailed as the output.
+    let required_scale = f64::from(output.width) / f64::from(width);
+    let layer = layers
+        .iter()
+        .filter(|layer| f64::from(layer.width) / f64::from(base.width) >= required_scale)
+        .min_by_key(|layer| layer.width)
+        .unwrap_or(base);
 
-    // TODO: Fix hardcoding
-    Ok(TileServerMsg {
-        store_id: 0,
-        id: 0,
-        level,
-        x,
-        y,
-        buffer: jpeg_buffer.to_vec(),
+    // Map the region onto the chosen level, clamped to its bounds.
+    let x_scale = f64::from(layer.width) / f64::from(base.width);
+    let y_scale = f64::from(layer.height) / f64::from(base.height);
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let scale = |value: u32, scale: f64| (f64::from(value) * scale).floor() as u32;
+
+    let level_x = scale(x, x_scale).min(layer.width - 1);
+    let level_y = scale(y, y_scale).min(layer.height - 1);
+
+    Ok(Region {
+        size: Size {
+            width: scale(width, x_scale).max(1).min(layer.width - level_x),
+            height: scale(height, y_scale).max(1).min(layer.height - level_y),
+        },
+        level: layer.level,
+        address: Address {
+            x: level_x,
+            y: level_y,
+        },
+    })
+}
+
+/// Whether reading `region` for `output` stays within `MAX_REGION_SOURCE_PIXELS`.
+/// Regions that can't be mapped are left for `region` to reject.
+pub fn within_source_limit(layers: &[MetadataLayer], region: &Region, output: &Size) -> bool {
+    source_region(layers, region, output).map_or(true, |source| {
+        u64::from(source.size.width) * u64::from(source.size.height) <= MAX_REGION_SOURCE_PIXELS
     })
 }
 
+/// Reads a level 0 region of an image and resamples it to the requested size.
+/// Pixels are taken from the lowest resolution level that still has enough detail.
+pub fn region(
+    path: &Path,
+    layers: &[MetadataLayer],
+    region: &Region,
+    output: &Size,
+) -> Result<RgbImage> {
+    let source = source_region(layers, region, output)?;
+    let Size {
+        width: level_width,
+        height: level_height,
+    } = source.size;
+
+    if u64::from(level_width) * u64::from(level_height) > MAX_REGION_SOURCE_PIXELS {
+        return Err(anyhow::anyhow!("Region reads too many pixels."));
+    }
+
+    // TODO: Remove encoder hardcode.
+    let Some(encoder) = encoders::export::get("OMEZarr") else {
+        return Err(anyhow::anyhow!("Could not get encoder."));
+    };
+
+    let rgb_buffer = encoder.retrieve_region(path, &source)?;
+
+    let Some(pixels) = RgbImage::from_raw(level_width, level_height, rgb_buffer) else {
+        return Err(anyhow::anyhow!("RGB data doesn't fit into image buffer."));
+    };
+
+    if pixels.dimensions() == (output.width, output.height) {
+        return Ok(pixels);
+    }
+
+    Ok(imageops::resize(
+        &pixels,
+        output.width,
+        output.height,
+        FilterType::Triangle,
+    ))
+}
+
+pub fn encode(image: RgbImage, format: TileFormat) -> Result<Vec<u8>> {
+    let (width, height) = image.dimensions();
+
+    match format {
+        TileFormat::Jpeg {
+            quality,
+            subsampling,
+        } => {
+            let subsampling = match subsampling {
+                Subsampling::Sub1x1 => turbojpeg::Subsamp::None,
+                Subsampling::Sub2x1 => turbojpeg::Subsamp::Sub2x1,
+                Subsampling::Sub2x2 => turbojpeg::Subsamp::Sub2x2,
+            };
+
+            let jpeg_buffer =
+                turbojpeg::compress_image(&image, quality.clamp(1, 100).into(), subsampling)?;
+
+            Ok(jpeg_buffer.to_vec())
+        }
+        TileFormat::Png => {
+            let mut png_buffer = Vec::new();
+            PngEncoder::new(&mut png_buffer).write_image(
+                &image,
+                width,
+                height,
+                ExtendedColorType::Rgb8,
+            )?;
+
+            Ok(png_buffer)
+        }
+        TileFormat::WebP => {
+            let mut webp_buffer = Vec::new();
+            WebPEncoder::new_lossless(&mut webp_buffer).write_image(
+                &image,
+                width,
+                height,
+                ExtendedColorType::Rgb8,
+            )?;
+
+            Ok(webp_buffer)
+        }
+        TileFormat::Raw => Ok(image.into_raw()),
+        TileFormat::Tiff => {
+            let mut tiff_buffer = Cursor::new(Vec::new());
+            TiffEncoder::new(&mut tiff_buffer).write_image(
+                &image,
+                width,
+                height,
+                ExtendedColorType::Rgb8,
+            )?;
+
+            Ok(tiff_buffer.into_inner())
+        }
+    }
+}
+
 pub fn convert(
     source_path: &Path,
     source_extension: &str,
     destination_path: &Path,
     thumbnail_path: &Path,
     encoder: &Box<dyn Encoder>,
-) -> Result<(String, Vec<MetadataLayer>)> {
+) -> Result<(String, Option<f64>, Vec<MetadataLayer>)> {
     // Open the image with a decoder.
     let Some(decoder) = decoders::export::get(source_extension, source_path) else {
         return Err(anyhow::anyhow!("No decoders found for image."));
@@ -123,8 +270,103 @@ pub fn convert(
             // Save thumbnail to disk.
             fs::write(thumbnail_path, thumbnail_jpeg)?;
 
-            Ok((decoder.name().to_string(), metadata))
+            Ok((
+                decoder.name().to_string(),
+                decoder.get_microns_per_pixel()?,
+                metadata,
+            ))
         }
         Err(e) => Err(anyhow::anyhow!("Failed to encode image: {e}")),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layers(sizes: &[(u32, u32)]) -> Vec<MetadataLayer> {
+        sizes
+            .iter()
+            .zip(0..)
+            .map(|(&(width, height), level)| MetadataLayer {
+                level,
+                cols: width.div_ceil(TILE_SIZE),
+                rows: height.div_ceil(TILE_SIZE),
+                width,
+                height,
+            })
+            .collect()
+    }
+
+    fn region(x: u32, y: u32, width: u32, height: u32) -> Region {
+        Region {
+            size: Size { width, height },
+            level: 0,
+            address: Address { x, y },
+        }
+    }
+
+    #[test]
+    fn reads_from_the_smallest_detailed_enough_level() {
+        let layers = layers(&[(40000, 40000), (10000, 10000), (2500, 2500)]);
+        let output = Size {
+            width: 1000,
+            height: 1000,
+        };
+
+        let source = source_region(&layers, &region(4000, 8000, 4000, 4000), &output).unwrap();
+
+        assert_eq!(source.level, 1);
+        assert_eq!((source.address.x, source.address.y), (1000, 2000));
+        assert_eq!((source.size.width, source.size.height), (1000, 1000));
+    }
+
+    #[test]
+    fn clamps_source_to_level_bounds() {
+        let layers = layers(&[(1000, 500)]);
+        let output = Size {
+            width: 100,
+            height: 100,
+        };
+
+        let source = source_region(&layers, &region(900, 450, 400, 400), &output).unwrap();
+
+        assert_eq!((source.size.width, source.size.height), (100, 50));
+    }
+
+    #[test]
+    fn rejects_empty_regions() {
+        let layers = layers(&[(1000, 1000)]);
+        let output = Size {
+            width: 0,
+            height: 10,
+        };
+
+        assert!(source_region(&layers, &region(0, 0, 10, 10), &output).is_err());
+    }
+
+    #[test]
+    fn limits_source_pixels() {
+        let layers = layers(&[(120_000, 120_000), (30000, 30000), (7500, 7500)]);
+        let output = Size {
+            width: 8192,
+            height: 8192,
+        };
+
+        // A scale of about 0.26 has to read level 0.
+        assert!(!within_source_limit(
+            &layers,
+            &region(0, 0, 31500, 31500),
+            &output
+        ));
+        // Reading a whole level at its own size stays within the limit.
+        assert!(within_source_limit(
+            &layers,
+            &region(0, 0, 30000 * 4, 30000 * 4),
+            &Size {
+                width: 7500,
+                height: 7500,
+            }
+        ));
+    }
+}
@@ -37,6 +37,10 @@ pub enum Log<'a> {
         message: &'a str,
         details: Option<String>,
     },
+    Output {
+        source: &'a str,
+        line: String,
+    },
     Completed {
         status_code: StatusCode,
     },
@@ -119,12 +123,7 @@ impl<'a> Logger<'a> {
         message: &'a str,
         details: Option<anyhow::Error>,
     ) -> Response<Body> {
-        self.logs.push(Log::Error {
-            error: error.clone(),
-            id,
-            message,
-            details: details.map(|x| x.to_string()),
-        });
+        self.log_error(error.clone(), id, message, details);
 
         // Only end on error if not websocket related.
         match error {
@@ -136,9 +135,40 @@ impl<'a> Logger<'a> {
         (status_code, message.to_string()).into_response()
     }
 
+    // Record a line printed by a process the handler ran.
+    pub fn output(&mut self, source: &'a str, line: String) {
+        self.logs.push(Log::Output { source, line });
+    }
+
+    // Record an error without returning, for connections that outlive it.
+    pub fn log_error(
+        &mut self,
+        error: Error,
+        id: &'a str,
+        message: &'a str,
+        details: Option<anyhow::Error>,
+    ) {
+        self.logs.push(Log::Error {
+            error,
+            id,
+            message,
+            details: details.map(|x| x.to_string()),
+        });
+    }
+
+    // Print and clear the logs so far, for connections that outlive them.
+    pub fn flush(&mut self) {
+        self.print();
+        self.logs.clear();
+    }
+
     fn end(&mut self, status_code: StatusCode) {
-        let total_duration = self.start.elapsed();
         self.logs.push(Log::Completed { status_code });
+        self.print();
+    }
+
+    fn print(&self) {
+        let total_duration = self.start.elapsed();
 
         for log in &self.logs {
             match log {
@@ -195,6 +225,10 @@ impl<'a> Logger<'a> {
                         println!("            {details}");
                     }
                 }
+                Log::Output { source, line } => {
+                    #[cfg(feature = "log.console")]
+                    println!("  OUTPUT [{source}] {line}");
+                }
                 Log::Completed { status_code } => {
                     #[cfg(feature = "log.console")]
                     println!("Completed {status_code} in {total_duration:?}");
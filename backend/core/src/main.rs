@@ -4,6 +4,7 @@
 mod api;
 mod constants;
 mod db;
+mod geometry;
 mod io;
 mod log;
 mod middleware;
@@ -11,7 +12,7 @@ mod types;
 
 use crate::{
     constants::{LOCAL_DATABASES_PATH, LOCAL_STORES_PATH, REGISTRY_PATH},
-    types::{database::DatabaseManager, socket::ClientSocketManager},
+    types::{database::DatabaseManager, generator::GeneratorRegistry, socket::ClientSocketManager},
 };
 use axum::{
     Extension, Router,
@@ -90,17 +91,73 @@ async fn main() {
             "/{image_id}/thumbnail",
             get(api::image::thumbnail::thumbnail),
         )
+        .route("/{image_id}/region", get(api::image::region::region))
+        .route(
+            "/{image_id}/annotations",
+            get(api::image::annotations::query::query)
+                .post(api::image::annotations::attach::attach)
+                .put(api::image::annotations::attach::replace),
+        )
+        .route(
+            "/{image_id}/annotations/export",
+            get(api::image::annotations::export::export),
+        )
+        .route(
+            "/{image_id}/annotations/generate",
+            post(api::image::annotations::generate::generate),
+        )
         .route(
             "/{image_id}/annotations/{annotation_layer_id}",
-            get(api::image::annotations::annotations),
+            get(api::image::annotations::layer::layer)
+                .post(api::image::annotations::create::create),
+        )
+        .route(
+            "/{image_id}/annotations/{annotation_layer_id}/region",
+            get(api::image::annotations::region::region),
+        )
+        .route(
+            "/{image_id}/annotations/{annotation_layer_id}/{annotation_id}",
+            patch(api::image::annotations::update::update)
+                .delete(api::image::annotations::delete::delete),
+        )
+        .route(
+            "/{image_id}/layers",
+            post(api::image::layers::create::create),
+        )
+        .route(
+            "/{image_id}/layers/{annotation_layer_id}",
+            patch(api::image::layers::update::update).delete(api::image::layers::delete::delete),
+        )
+        .route(
+            "/{image_id}/tiles/{level}/{x}/{file}",
+            get(api::image::tiles::tile),
         );
 
-    let store_routes = Router::new().route("/{store_id}", get(api::store::get::get));
+    let iiif_routes = Router::new()
+        .route("/{image_id}", get(api::iiif::info::redirect))
+        .route("/{image_id}/info.json", get(api::iiif::info::info))
+        .route(
+            "/{image_id}/{region}/{size}/{rotation}/{file}",
+            get(api::iiif::image::image),
+        );
+
+    let deepzoom_routes = Router::new()
+        .route("/{file}", get(api::deepzoom::descriptor::descriptor))
+        .route("/{files}/{level}/{file}", get(api::deepzoom::tiles::tiles));
+
+    let store_routes = Router::new()
+        .route("/{store_id}", get(api::store::get::get))
+        .route(
+            "/{store_id}/palette",
+            get(api::store::palette::palette).put(api::store::palette::update),
+        );
 
     let api_routes = Router::new()
         .nest("/directory/{store_id}", directory_routes)
         .nest("/image/{store_id}", image_routes)
         .nest("/store", store_routes)
+        .nest("/iiif/{store_id}", iiif_routes)
+        .nest("/deepzoom/{store_id}", deepzoom_routes)
         .route("/registry", get(api::registry::registry))
         .route("/generators", get(api::generators::generators))
         .route("/websocket", get(api::websocket::websocket));
@@ -116,7 +173,10 @@ async fn main() {
         .layer(Extension(Arc::new(
             DatabaseManager::connect().expect("Could not connect to the databases."),
         )))
-        .layer(Extension(Arc::new(ClientSocketManager::default())));
+        .layer(Extension(Arc::new(ClientSocketManager::default())))
+        .layer(Extension(Arc::new(
+            GeneratorRegistry::load().expect("Could not load the external generators."),
+        )));
 
     // Allow CORS from dev frontend server.
     #[cfg(debug_assertions)]
@@ -135,7 +195,13 @@ async fn main() {
                     .parse::<HeaderValue>()
                     .expect("Could not parse frontend url."),
             )
-            .allow_methods([Method::GET, Method::POST, Method::DELETE, Method::PATCH])
+            .allow_methods([
+                Method::GET,
+                Method::POST,
+                Method::PUT,
+                Method::DELETE,
+                Method::PATCH,
+            ])
             .allow_headers([CONTENT_TYPE]);
 
         app = app.layer(cors);
@@ -0,0 +1,182 @@
+use anyhow::{Result, anyhow};
+use rusqlite::types::Value;
+use serde::{Deserialize, Serialize};
+use shared::types::{Bounds, Geometry, Properties};
+use std::str::FromStr;
+
+/// Annotation as stored in the store database.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnnotationRecord {
+    pub id: u32,
+    pub layer_id: u32,
+    pub geometry: Geometry,
+    pub properties: Properties,
+    pub bounds: Bounds,
+}
+
+impl AnnotationRecord {
+    /// Geometry and properties as JSON text, for messages to clients.
+    pub fn to_json(&self) -> serde_json::Result<(String, String)> {
+        Ok((
+            serde_json::to_string(&self.geometry)?,
+            serde_json::to_string(&self.properties)?,
+        ))
+    }
+}
+
+/// Changes to the tag or style of a layer. Fields left out are kept as they are.
+#[derive(Deserialize)]
+pub struct LayerChanges {
+    pub tag: Option<String>,
+    pub visible: Option<bool>,
+    pub opacity: Option<f32>,
+    pub fill: Option<String>,
+    pub stroke: Option<String>,
+}
+
+/// Whether the value is a `#RRGGBB` colour, the form layer colours are kept in.
+pub fn is_colour(value: &str) -> bool {
+    value
+        .strip_prefix('#')
+        .is_some_and(|hex| hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// Annotation layer with its stored annotations, for exports.
+#[derive(Debug)]
+pub struct LayerRecord {
+    pub id: u32,
+    pub tag: String,
+    pub fill: String,
+    pub annotations: Vec<AnnotationRecord>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum ExportFormat {
+    GeoJson,
+    // GeoJSON with QuPath classifications and object types.
+    QuPath,
+    // One row per annotation with its centroid, area and properties.
+    Csv,
+}
+
+impl ExportFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::GeoJson | ExportFormat::QuPath => "application/geo+json",
+            ExportFormat::Csv => "text/csv",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::GeoJson | ExportFormat::QuPath => "geojson",
+            ExportFormat::Csv => "csv",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+impl Comparison {
+    const OPERATORS: [(&'static str, Comparison); 6] = [
+        ("!=", Comparison::NotEqual),
+        ("<=", Comparison::LessEqual),
+        (">=", Comparison::GreaterEqual),
+        ("=", Comparison::Equal),
+        ("<", Comparison::Less),
+        (">", Comparison::Greater),
+    ];
+
+    pub fn sql(self) -> &'static str {
+        match self {
+            Comparison::Equal => "=",
+            Comparison::NotEqual => "!=",
+            Comparison::Less => "<",
+            Comparison::LessEqual => "<=",
+            Comparison::Greater => ">",
+            Comparison::GreaterEqual => ">=",
+        }
+    }
+}
+
+/// Condition on an annotation property, e.g. `prob>0.9` or `classification.name=Tumor`.
+#[derive(Debug)]
+pub struct PropertyFilter {
+    // Keys into nested property objects.
+    pub keys: Vec<String>,
+    pub comparison: Comparison,
+    pub value: Value,
+}
+
+impl PropertyFilter {
+    /// Comma separated conditions, all of which must hold.
+    pub fn parse_all(filters: &str) -> Result<Vec<Self>> {
+        filters
+            .split(',')
+            .filter(|filter| !filter.trim().is_empty())
+            .map(str::parse)
+            .collect()
+    }
+
+    /// Path of the property for `json_extract`, e.g. `$."classification"."name"`.
+    pub fn json_path(&self) -> String {
+        self.keys
+            .iter()
+            .fold(String::from("$"), |path, key| format!("{path}.\"{key}\""))
+    }
+}
+
+impl FromStr for PropertyFilter {
+    type Err = anyhow::Error;
+
+    fn from_str(filter: &str) -> Result<Self> {
+        let (index, operator, comparison) = Comparison::OPERATORS
+            .iter()
+            .filter_map(|(operator, comparison)| {
+                filter
+                    .find(operator)
+                    .map(|index| (index, *operator, *comparison))
+            })
+            // The leftmost operator splits key and value; ties go to the longer operator.
+            .min_by_key(|(index, operator, _)| (*index, usize::MAX - operator.len()))
+            .ok_or_else(|| anyhow!("Filter `{filter}` has no comparison operator."))?;
+
+        let key = filter[..index].trim();
+        let value = filter[index + operator.len()..].trim();
+
+        let keys: Vec<String> = key.split('.').map(str::to_string).collect();
+        if keys.iter().any(|key| key.is_empty() || key.contains('"')) {
+            return Err(anyhow!("Filter `{filter}` has an invalid property name."));
+        }
+
+        // JSON booleans are compared as SQLite integers.
+        let value = match value {
+            "true" => Value::Integer(1),
+            "false" => Value::Integer(0),
+            _ => {
+                if let Ok(value) = value.parse::<i64>() {
+                    Value::Integer(value)
+                } else if let Ok(value) = value.parse::<f64>() {
+                    Value::Real(value)
+                } else {
+                    Value::Text(value.to_string())
+                }
+            }
+        };
+
+        Ok(Self {
+            keys,
+            comparison,
+            value,
+        })
+    }
+}
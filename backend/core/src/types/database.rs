@@ -36,7 +36,7 @@ impl DatabaseManager {
 
         crate::db::stores::create(&conn, &Interface::Local, "Local")?;
 
-        let stores = crate::db::registry::get_(&conn)?
+        let stores: HashMap<u32, Store> = crate::db::registry::get_(&conn)?
             .into_iter()
             .map(|properties| {
                 (
@@ -52,6 +52,14 @@ impl DatabaseManager {
             })
             .collect();
 
+        // Stores created before annotations were kept in the database lack their tables.
+        for store in stores.values() {
+            let conn = store.connection.lock().unwrap();
+            crate::db::image::create_columns(&conn)?;
+            crate::db::annotation::create_tables(&conn)?;
+            crate::db::palette::create_table(&conn)?;
+        }
+
         Ok(Self {
             registry: Arc::new(Mutex::new(conn)),
             stores,
@@ -77,6 +85,60 @@ impl DatabaseManager {
     }
 }
 
+#[cfg(test)]
+impl DatabaseManager {
+    /// In-memory store holding only the levels of one image, for handlers that read no more.
+    pub fn with_metadata_layers(
+        store_id: u32,
+        image_id: u32,
+        layers: &[shared::types::MetadataLayer],
+    ) -> Result<Self> {
+        let conn = Connection::open_in_memory()?;
+        conn.execute(
+            "
+                CREATE TABLE metadata_layer (
+                    image_id INTEGER NOT NULL,
+                    level INTEGER NOT NULL,
+                    cols INTEGER NOT NULL,
+                    rows INTEGER NOT NULL,
+                    width INTEGER NOT NULL,
+                    height INTEGER NOT NULL
+                );
+            ",
+            (),
+        )?;
+        for layer in layers {
+            conn.execute(
+                "INSERT INTO metadata_layer VALUES (?1, ?2, ?3, ?4, ?5, ?6);",
+                (
+                    image_id,
+                    layer.level,
+                    layer.cols,
+                    layer.rows,
+                    layer.width,
+                    layer.height,
+                ),
+            )?;
+        }
+
+        let store = Store {
+            connection: Arc::new(Mutex::new(conn)),
+            properties: StoreProperties {
+                id: store_id,
+                r#type: Interface::Local,
+                name: "Test".into(),
+                path: PathBuf::new(),
+                url: ":memory:".into(),
+            },
+        };
+
+        Ok(Self {
+            registry: Arc::new(Mutex::new(Connection::open_in_memory()?)),
+            stores: HashMap::from([(store_id, store)]),
+        })
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Store {
     pub connection: Arc<Mutex<Connection>>,
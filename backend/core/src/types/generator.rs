@@ -0,0 +1,402 @@
+use crate::constants::{
+    EXTERNAL_GENERATOR_ERROR_LINES, EXTERNAL_GENERATOR_POLL_INTERVAL,
+    EXTERNAL_GENERATOR_PROGRESS_PREFIX, EXTERNAL_GENERATORS_VAR,
+};
+use anyhow::{Context, Result, anyhow, bail};
+use serde::Deserialize;
+use shared::{
+    traits::{Decoder, Generator},
+    types::AnnotationLayer,
+};
+use std::{
+    collections::{HashSet, VecDeque},
+    env, fs,
+    io::{BufRead, BufReader},
+    path::Path,
+    process::{Command, Stdio},
+    sync::mpsc::{self, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Generators built into the server along with the external ones it was configured with.
+#[derive(Default)]
+pub struct GeneratorRegistry {
+    external: Vec<ExternalGenerator>,
+}
+
+impl GeneratorRegistry {
+    /// Reads the external generators from the file named by `EXTERNAL_GENERATORS_PATH`,
+    /// if it is set.
+    pub fn load() -> Result<Self> {
+        let Ok(path) = env::var(EXTERNAL_GENERATORS_VAR) else {
+            return Ok(Self::default());
+        };
+
+        let file = fs::read(&path).with_context(|| format!("Could not read {path}."))?;
+        let external: Vec<ExternalGenerator> = serde_json::from_slice::<Vec<Config>>(&file)?
+            .into_iter()
+            .map(ExternalGenerator::from)
+            .collect();
+
+        let mut names = HashSet::new();
+        for generator in &external {
+            if generators::export::get(generator.name).is_some() || !names.insert(generator.name) {
+                bail!("Generator name {} is used more than once.", generator.name);
+            }
+            if generators::export::get(&generator.translator).is_none() {
+                bail!(
+                    "Translator {} of {} could not be found.",
+                    generator.translator,
+                    generator.name
+                );
+            }
+            if generator.command.is_empty() {
+                bail!("{} has no command to run.", generator.name);
+            }
+        }
+
+        Ok(Self { external })
+    }
+
+    pub fn get(&self, name: &str) -> Option<Box<dyn Generator>> {
+        generators::export::get(name).or_else(|| {
+            self.external
+                .iter()
+                .find(|generator| generator.name == name)
+                .map(|generator| Box::new(generator.clone()) as Box<dyn Generator>)
+        })
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        let mut names = generators::export::names();
+        names.extend(self.external.iter().map(|generator| generator.name));
+        names
+    }
+}
+
+/// Entry of the external generators file.
+#[derive(Deserialize)]
+struct Config {
+    name: String,
+    // Program followed by its arguments, in which `{image}` and `{output}` are replaced
+    // by the path of the image and the path the annotations should be written to.
+    command: Vec<String>,
+    // Built-in generator that reads the output file.
+    translator: String,
+    // Extension given to the output path, for tools that pick a format from it.
+    extension: Option<String>,
+    // Seconds the process may run for before it is killed.
+    timeout: u64,
+}
+
+/// Analysis tool run as a separate process, whose output file is read by one of the
+/// built-in translators.
+///
+/// The tool reports progress by printing `PROGRESS <fraction>` lines to stdout. Its
+/// stderr is logged and the tail of it is kept if it fails.
+#[derive(Clone)]
+pub struct ExternalGenerator {
+    name: &'static str,
+    command: Vec<String>,
+    translator: String,
+    extension: Option<String>,
+    timeout: Duration,
+}
+
+impl From<Config> for ExternalGenerator {
+    fn from(config: Config) -> Self {
+        Self {
+            // Generators are loaded once at startup and live as long as the server.
+            name: config.name.leak(),
+            command: config.command,
+            translator: config.translator,
+            extension: config.extension,
+            timeout: Duration::from_secs(config.timeout),
+        }
+    }
+}
+
+/// Line read from one of the pipes of an external generator.
+enum Output {
+    Progress(f64),
+    Stderr(String),
+}
+
+impl Generator for ExternalGenerator {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn generate(
+        &self,
+        image_path: &Path,
+        _decoder: &dyn Decoder,
+        progress: &dyn Fn(f64),
+        log: &dyn Fn(&str),
+    ) -> Result<Vec<AnnotationLayer>> {
+        let translator = generators::export::get(&self.translator)
+            .ok_or_else(|| anyhow!("Translator {} could not be found.", self.translator))?;
+
+        let directory = tempfile::tempdir()?;
+        let output_path = directory.path().join(match &self.extension {
+            Some(extension) => format!("output.{extension}"),
+            None => "output".into(),
+        });
+
+        let (program, args) = self
+            .command
+            .split_first()
+            .ok_or_else(|| anyhow!("{} has no command to run.", self.name))?;
+        let args = args.iter().map(|arg| {
+            arg.replace("{image}", &image_path.to_string_lossy())
+                .replace("{output}", &output_path.to_string_lossy())
+        });
+
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Could not start {program}."))?;
+
+        // Both pipes are drained on their own threads so the process never blocks on a
+        // full pipe. Lines are passed back to this thread, which owns the callbacks.
+        let stdout = child.stdout.take().context("Missing stdout.")?;
+        let (sender, receiver) = mpsc::channel();
+        let stdout_sender = sender.clone();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                let fraction = line
+                    .strip_prefix(EXTERNAL_GENERATOR_PROGRESS_PREFIX)
+                    .and_then(|fraction| fraction.trim().parse::<f64>().ok());
+                if let Some(fraction) = fraction
+                    && stdout_sender.send(Output::Progress(fraction)).is_err()
+                {
+                    break;
+                }
+            }
+        });
+
+        let stderr = child.stderr.take().context("Missing stderr.")?;
+        thread::spawn(move || {
+            for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                if sender.send(Output::Stderr(line)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut tail = VecDeque::with_capacity(EXTERNAL_GENERATOR_ERROR_LINES);
+        let mut handle = |output: Output| match output {
+            Output::Progress(fraction) => progress(fraction.clamp(0.0, 1.0)),
+            Output::Stderr(line) => {
+                log(&line);
+                if tail.len() == EXTERNAL_GENERATOR_ERROR_LINES {
+                    tail.pop_front();
+                }
+                tail.push_back(line);
+            }
+        };
+
+        let deadline = Instant::now() + self.timeout;
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if Instant::now() >= deadline {
+                child.kill()?;
+                child.wait()?;
+                // Processes the tool started may keep its pipes open, so only what has
+                // already been read is logged.
+                receiver.try_iter().for_each(&mut handle);
+                bail!("{} timed out after {:?}.", self.name, self.timeout);
+            }
+
+            match receiver.recv_timeout(EXTERNAL_GENERATOR_POLL_INTERVAL) {
+                Ok(output) => handle(output),
+                Err(RecvTimeoutError::Timeout) => {}
+                // Both pipes were closed but the process has not exited yet.
+                Err(RecvTimeoutError::Disconnected) => {
+                    thread::sleep(EXTERNAL_GENERATOR_POLL_INTERVAL);
+                }
+            }
+        };
+
+        // The pipes may still hold lines. Processes the tool started can keep them open,
+        // so they are read until the deadline at most and the reader threads are then
+        // left to finish on their own.
+        while let Ok(output) =
+            receiver.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+        {
+            handle(output);
+        }
+        if !status.success() {
+            let tail = Vec::from(tail).join("\n");
+            bail!("{} failed ({status}):\n{tail}", self.name);
+        }
+
+        if !output_path.exists() {
+            bail!("{} did not write its output file.", self.name);
+        }
+
+        translator.translate(&output_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Rgb};
+    use shared::types::{Region, Size};
+    use std::cell::RefCell;
+
+    /// External generators do not read the image through a decoder.
+    struct NoDecoder;
+
+    impl Decoder for NoDecoder {
+        fn name(&self) -> &'static str {
+            unreachable!()
+        }
+        fn extensions(&self) -> Vec<&'static str> {
+            unreachable!()
+        }
+        fn open(_: &Path) -> Result<Self> {
+            unreachable!()
+        }
+        fn get_level_count(&self) -> Result<u32> {
+            unreachable!()
+        }
+        fn get_level_dimensions(&self, _: u32) -> Result<(u32, u32)> {
+            unreachable!()
+        }
+        fn read_region(&self, _: &Region) -> Result<Vec<u8>> {
+            unreachable!()
+        }
+        fn thumbnail(&self, _: &Size) -> Result<ImageBuffer<Rgb<u8>, Vec<u8>>> {
+            unreachable!()
+        }
+    }
+
+    /// Runs a shell script as a generator whose output is read as `GeoJSON`, returning
+    /// the progress and stderr lines it reported. The script gets the output path as `$1`.
+    fn run(
+        script: &str,
+        timeout: Duration,
+    ) -> (Result<Vec<AnnotationLayer>>, Vec<f64>, Vec<String>) {
+        let generator = ExternalGenerator {
+            name: "Stub",
+            command: ["sh", "-c", script, "sh", "{output}"]
+                .map(String::from)
+                .to_vec(),
+            translator: "GeoJSON".into(),
+            extension: Some("geojson".into()),
+            timeout,
+        };
+
+        let progress = RefCell::new(Vec::new());
+        let lines = RefCell::new(Vec::new());
+        let result = generator.generate(
+            Path::new("image.svs"),
+            &NoDecoder,
+            &|fraction| progress.borrow_mut().push(fraction),
+            &|line| lines.borrow_mut().push(line.to_string()),
+        );
+
+        (result, progress.into_inner(), lines.into_inner())
+    }
+
+    #[test]
+    fn reports_progress_and_translates_output() {
+        let (result, progress, lines) = run(
+            r#"
+                echo "PROGRESS 0.25"
+                echo "PROGRESS 2"
+                echo "Loading model"
+                echo "Warning" >&2
+                echo '{"type": "FeatureCollection", "features": []}' > "$1"
+            "#,
+            Duration::from_secs(10),
+        );
+
+        assert!(result.unwrap().is_empty());
+        assert_eq!(progress, [0.25, 1.0]);
+        assert_eq!(lines, ["Warning"]);
+    }
+
+    #[test]
+    fn fails_on_non_zero_exit() {
+        let (result, _, lines) = run(
+            r#"
+                echo "Out of memory" >&2
+                exit 3
+            "#,
+            Duration::from_secs(10),
+        );
+
+        let error = result.unwrap_err().to_string();
+        assert!(error.starts_with("Stub failed"), "{error}");
+        assert!(error.ends_with("Out of memory"), "{error}");
+        assert_eq!(lines, ["Out of memory"]);
+    }
+
+    #[test]
+    fn keeps_tail_of_stderr() {
+        let (result, _, lines) = run(
+            r#"
+                for i in $(seq 1 30); do echo "Line $i" >&2; done
+                exit 1
+            "#,
+            Duration::from_secs(10),
+        );
+
+        let error = result.unwrap_err().to_string();
+        assert!(!error.contains("Line 10\n"), "{error}");
+        assert!(error.contains("Line 11\n"), "{error}");
+        assert!(error.ends_with("Line 30"), "{error}");
+        assert_eq!(lines.len(), 30);
+    }
+
+    #[test]
+    fn fails_without_output() {
+        let (result, _, _) = run("exit 0", Duration::from_secs(10));
+
+        let error = result.unwrap_err().to_string();
+        assert_eq!(error, "Stub did not write its output file.");
+    }
+
+    #[test]
+    fn kills_generator_after_timeout() {
+        let started = Instant::now();
+        let (result, progress, _) = run(
+            r#"
+                echo "PROGRESS 0.5"
+                exec sleep 10
+            "#,
+            Duration::from_millis(500),
+        );
+
+        let error = result.unwrap_err().to_string();
+        assert!(error.starts_with("Stub timed out"), "{error}");
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(progress, [0.5]);
+    }
+
+    #[test]
+    fn stops_reading_pipes_held_by_background_processes() {
+        let started = Instant::now();
+        let (result, _, lines) = run(
+            r#"
+                sleep 30 &
+                echo "Started worker" >&2
+                echo '{"type": "FeatureCollection", "features": []}' > "$1"
+            "#,
+            Duration::from_secs(1),
+        );
+
+        assert!(result.unwrap().is_empty());
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(lines, ["Started worker"]);
+    }
+}
@@ -1,5 +1,17 @@
+pub mod annotation;
 pub mod database;
 pub mod fs;
+pub mod generator;
 pub mod messages;
+pub mod protocol;
+// Included to check the frontend's copy of the protocol against the schema.
+pub mod scheduler;
+#[cfg(test)]
+#[allow(dead_code)] // The rest of the schema is read by build.rs.
+#[path = "../../codegen/schema.rs"]
+mod schema;
 pub mod socket;
+#[cfg(test)]
+#[path = "../../codegen/typescript.rs"]
+mod typescript;
 pub mod user;
@@ -0,0 +1,509 @@
+/// Auto-generated file from protocol.json. Any changes will be overwritten.
+use crate::types::messages::{decode, encode};
+use axum::{body::Bytes, extract::ws::Message};
+use bincode::error::{DecodeError, EncodeError};
+/// Protocol version spoken by this server.
+pub const PROTOCOL_VERSION: u32 = 1;
+/// Oldest protocol version this server still accepts.
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+pub const C_TILE_TAG: u8 = 0;
+pub const C_VIEWPORT_TAG: u8 = 1;
+pub const C_CANCEL_TAG: u8 = 2;
+pub const C_FORMAT_TAG: u8 = 3;
+pub const C_HELLO_TAG: u8 = 4;
+pub const C_SUBSCRIBE_TAG: u8 = 5;
+pub const C_UNSUBSCRIBE_TAG: u8 = 6;
+pub const S_ERROR_TAG: u8 = 0;
+pub const S_TILE_TAG: u8 = 1;
+pub const S_DIRECTORY_TAG: u8 = 2;
+pub const S_DIRECTORY_CREATE_TAG: u8 = 0;
+pub const S_DIRECTORY_DELETE_TAG: u8 = 1;
+pub const S_DIRECTORY_MOVE_TAG: u8 = 2;
+pub const S_DIRECTORY_RENAME_TAG: u8 = 3;
+pub const S_CAPABILITIES_TAG: u8 = 3;
+pub const S_RESYNC_TAG: u8 = 4;
+pub const S_HELLO_TAG: u8 = 5;
+pub const S_ANNOTATION_TAG: u8 = 6;
+pub const S_ANNOTATION_CREATE_TAG: u8 = 0;
+pub const S_ANNOTATION_UPDATE_TAG: u8 = 1;
+pub const S_ANNOTATION_DELETE_TAG: u8 = 2;
+pub const S_ANNOTATION_LAYER_TAG: u8 = 7;
+pub const S_ANNOTATION_LAYER_CREATE_TAG: u8 = 0;
+pub const S_ANNOTATION_LAYER_UPDATE_TAG: u8 = 1;
+pub const S_ANNOTATION_LAYER_DELETE_TAG: u8 = 2;
+pub const S_GENERATION_TAG: u8 = 8;
+#[derive(Debug)]
+#[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+pub enum ClientMsg {
+    Tile(TileClientMsg),
+    Viewport(ViewportClientMsg),
+    Cancel(CancelClientMsg),
+    Format(FormatClientMsg),
+    /// Opens the handshake. Its tag must never change between versions.
+    Hello(HelloClientMsg),
+    Subscribe(SubscribeClientMsg),
+    Unsubscribe(UnsubscribeClientMsg),
+}
+#[derive(Debug)]
+#[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+pub enum ServerMsg {
+    Error(ErrorServerMsg),
+    Tile(TileServerMsg),
+    Directory(DirectoryServerMsg),
+    Capabilities(CapabilitiesServerMsg),
+    /// Sent when broadcasts were dropped; the client should refetch its store trees.
+    Resync,
+    /// Completes the handshake. Its tag must never change between versions.
+    Hello(HelloServerMsg),
+    Annotation(AnnotationServerMsg),
+    AnnotationLayer(AnnotationLayerServerMsg),
+    /// Progress of an annotation generator, with 1 once it has finished or failed.
+    Generation(GenerationServerMsg),
+}
+#[derive(bincode::Encode, bincode::Decode, Clone, Debug, PartialEq, Copy)]
+#[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+pub struct TileClientMsg {
+    pub request_id: u32,
+    pub store_id: u32,
+    pub image_id: u32,
+    pub level: u32,
+    pub x: u32,
+    pub y: u32,
+    /// Overrides the connection's tile format for this request only.
+    pub format: Option<TileFormat>,
+}
+#[derive(bincode::Encode, bincode::Decode, Clone, Debug, PartialEq)]
+#[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+pub struct ViewportClientMsg {
+    pub request_id: u32,
+    pub store_id: u32,
+    pub image_id: u32,
+    pub level: u32,
+    pub bounds: ViewportBounds,
+    /// Overrides the connection's tile format for this request only.
+    pub format: Option<TileFormat>,
+}
+#[derive(bincode::Encode, bincode::Decode, Clone, Debug, PartialEq)]
+#[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+pub enum ViewportBounds {
+    /// Inclusive range of tile indices at the requested level.
+    Tiles { x_start: u32, y_start: u32, x_end: u32, y_end: u32 },
+    /// Bounding box in level 0 pixel coordinates.
+    Region { x: u32, y: u32, width: u32, height: u32 },
+}
+/// Drops queued tile requests for an image, or only one of its levels.
+#[derive(bincode::Encode, bincode::Decode, Clone, Debug, PartialEq)]
+#[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+pub struct CancelClientMsg {
+    pub store_id: u32,
+    pub image_id: u32,
+    pub level: Option<u32>,
+}
+/// Sets the tile format used for the rest of the connection.
+#[derive(bincode::Encode, bincode::Decode, Clone, Debug, PartialEq)]
+#[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+pub struct FormatClientMsg {
+    pub format: TileFormat,
+}
+/// Starts receiving directory updates for a store.
+#[derive(bincode::Encode, bincode::Decode, Clone, Debug, PartialEq)]
+#[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+pub struct SubscribeClientMsg {
+    pub store_id: u32,
+}
+/// Stops receiving directory updates for a store.
+#[derive(bincode::Encode, bincode::Decode, Clone, Debug, PartialEq)]
+#[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+pub struct UnsubscribeClientMsg {
+    pub store_id: u32,
+}
+/// Must be the first message on a connection.
+#[derive(bincode::Encode, bincode::Decode, Clone, Debug, PartialEq)]
+#[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+pub struct HelloClientMsg {
+    /// Newest protocol version the client speaks.
+    pub version: u32,
+}
+#[derive(bincode::Encode, bincode::Decode, Clone, Debug, PartialEq, Copy, Eq, Hash)]
+#[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+pub enum TileFormat {
+    Jpeg { quality: u8, subsampling: Subsampling },
+    /// Lossless.
+    Png,
+    /// Lossless.
+    WebP,
+    /// Uncompressed interleaved RGB bytes.
+    Raw,
+    /// Lossless.
+    Tiff,
+}
+impl TileFormat {
+    pub fn names() -> Vec<String> {
+        vec!["jpeg".into(), "png".into(), "webp".into(), "raw".into(), "tiff".into()]
+    }
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Jpeg { .. } => "image/jpeg",
+            Self::Png => "image/png",
+            Self::WebP => "image/webp",
+            Self::Raw => "application/octet-stream",
+            Self::Tiff => "image/tiff",
+        }
+    }
+}
+#[derive(bincode::Encode, bincode::Decode, Clone, Debug, PartialEq, Copy, Eq, Hash)]
+#[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+pub enum Subsampling {
+    /// 4:4:4, no chroma subsampling.
+    Sub1x1,
+    /// 4:2:2.
+    Sub2x1,
+    /// 4:2:0.
+    Sub2x2,
+}
+/// Failure of a client message, identified by its `log::Error` category and id.
+#[derive(bincode::Encode, bincode::Decode, Clone, Debug, PartialEq)]
+#[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+pub struct ErrorServerMsg {
+    /// Request that failed, if the error relates to one.
+    pub request_id: Option<u32>,
+    pub error: String,
+    pub id: String,
+    pub message: String,
+}
+#[derive(bincode::Encode, bincode::Decode, Clone, Debug, PartialEq)]
+#[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+pub struct TileServerMsg {
+    pub request_id: u32,
+    pub store_id: u32,
+    pub image_id: u32,
+    pub level: u32,
+    pub x: u32,
+    pub y: u32,
+    pub format: TileFormat,
+    pub buffer: Vec<u8>,
+}
+/// Protocol version both sides will use for the rest of the connection.
+#[derive(bincode::Encode, bincode::Decode, Clone, Debug, PartialEq)]
+#[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+pub struct HelloServerMsg {
+    pub version: u32,
+}
+/// Sent once the handshake completes.
+#[derive(bincode::Encode, bincode::Decode, Clone, Debug, PartialEq)]
+#[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+pub struct CapabilitiesServerMsg {
+    pub formats: Vec<String>,
+    pub default_format: TileFormat,
+}
+#[derive(bincode::Encode, bincode::Decode, Clone, Debug, PartialEq)]
+#[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+pub enum DirectoryServerMsg {
+    Create { store_id: u32, parent_id: u32, id: u32, name: String },
+    Delete { store_id: u32, id: u32 },
+    Move { store_id: u32, id: u32, destination_id: u32 },
+    Rename { store_id: u32, id: u32, name: String },
+}
+/// Edit to the annotations of an image.
+/// Geometry and properties are JSON, as returned by the annotations query.
+#[derive(bincode::Encode, bincode::Decode, Clone, Debug, PartialEq)]
+#[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+pub enum AnnotationServerMsg {
+    Create {
+        store_id: u32,
+        image_id: u32,
+        layer_id: u32,
+        id: u32,
+        geometry: String,
+        properties: String,
+    },
+    Update {
+        store_id: u32,
+        image_id: u32,
+        layer_id: u32,
+        id: u32,
+        geometry: String,
+        properties: String,
+    },
+    Delete { store_id: u32, image_id: u32, layer_id: u32, id: u32 },
+}
+#[derive(bincode::Encode, bincode::Decode, Clone, Debug, PartialEq)]
+#[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+pub enum AnnotationLayerServerMsg {
+    Create {
+        store_id: u32,
+        image_id: u32,
+        id: u32,
+        tag: String,
+        fill: String,
+        visible: bool,
+        opacity: f32,
+        stroke: String,
+    },
+    Update {
+        store_id: u32,
+        image_id: u32,
+        id: u32,
+        tag: String,
+        fill: String,
+        visible: bool,
+        opacity: f32,
+        stroke: String,
+    },
+    Delete { store_id: u32, image_id: u32, id: u32 },
+}
+#[derive(bincode::Encode, bincode::Decode, Clone, Debug, PartialEq)]
+#[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+pub struct GenerationServerMsg {
+    pub store_id: u32,
+    pub image_id: u32,
+    pub progress: f64,
+    pub generator: String,
+}
+impl DirectoryServerMsg {
+    pub fn subtag(&self) -> u8 {
+        match self {
+            Self::Create { .. } => S_DIRECTORY_CREATE_TAG,
+            Self::Delete { .. } => S_DIRECTORY_DELETE_TAG,
+            Self::Move { .. } => S_DIRECTORY_MOVE_TAG,
+            Self::Rename { .. } => S_DIRECTORY_RENAME_TAG,
+        }
+    }
+}
+impl AnnotationServerMsg {
+    pub fn subtag(&self) -> u8 {
+        match self {
+            Self::Create { .. } => S_ANNOTATION_CREATE_TAG,
+            Self::Update { .. } => S_ANNOTATION_UPDATE_TAG,
+            Self::Delete { .. } => S_ANNOTATION_DELETE_TAG,
+        }
+    }
+}
+impl AnnotationLayerServerMsg {
+    pub fn subtag(&self) -> u8 {
+        match self {
+            Self::Create { .. } => S_ANNOTATION_LAYER_CREATE_TAG,
+            Self::Update { .. } => S_ANNOTATION_LAYER_UPDATE_TAG,
+            Self::Delete { .. } => S_ANNOTATION_LAYER_DELETE_TAG,
+        }
+    }
+}
+impl TryFrom<Bytes> for ClientMsg {
+    type Error = DecodeError;
+    fn try_from(msg: Bytes) -> Result<Self, <Self as TryFrom<Bytes>>::Error> {
+        let msg = msg.as_ref();
+        let (tag, payload) = msg
+            .split_first()
+            .ok_or(DecodeError::Other("Missing message tag"))?;
+        let result = match *tag {
+            C_TILE_TAG => ClientMsg::Tile(decode::<TileClientMsg>(payload)?),
+            C_VIEWPORT_TAG => ClientMsg::Viewport(decode::<ViewportClientMsg>(payload)?),
+            C_CANCEL_TAG => ClientMsg::Cancel(decode::<CancelClientMsg>(payload)?),
+            C_FORMAT_TAG => ClientMsg::Format(decode::<FormatClientMsg>(payload)?),
+            C_HELLO_TAG => ClientMsg::Hello(decode::<HelloClientMsg>(payload)?),
+            C_SUBSCRIBE_TAG => {
+                ClientMsg::Subscribe(decode::<SubscribeClientMsg>(payload)?)
+            }
+            C_UNSUBSCRIBE_TAG => {
+                ClientMsg::Unsubscribe(decode::<UnsubscribeClientMsg>(payload)?)
+            }
+            _ => return Err(DecodeError::Other("Invalid message.")),
+        };
+        Ok(result)
+    }
+}
+impl TryInto<Message> for ServerMsg {
+    type Error = EncodeError;
+    fn try_into(self) -> Result<Message, <Self as TryInto<Message>>::Error> {
+        let payload = match &self {
+            ServerMsg::Error(msg) => encode((S_ERROR_TAG, msg))?,
+            ServerMsg::Tile(msg) => encode((S_TILE_TAG, msg))?,
+            ServerMsg::Directory(msg) => encode((S_DIRECTORY_TAG, msg.subtag(), msg))?,
+            ServerMsg::Capabilities(msg) => encode((S_CAPABILITIES_TAG, msg))?,
+            ServerMsg::Resync => encode(S_RESYNC_TAG)?,
+            ServerMsg::Hello(msg) => encode((S_HELLO_TAG, msg))?,
+            ServerMsg::Annotation(msg) => encode((S_ANNOTATION_TAG, msg.subtag(), msg))?,
+            ServerMsg::AnnotationLayer(msg) => {
+                encode((S_ANNOTATION_LAYER_TAG, msg.subtag(), msg))?
+            }
+            ServerMsg::Generation(msg) => encode((S_GENERATION_TAG, msg))?,
+        };
+        Ok(Message::Binary(payload.into()))
+    }
+}
+#[cfg(test)]
+impl ClientMsg {
+    fn encode(&self) -> Result<Vec<u8>, EncodeError> {
+        Ok(
+            match self {
+                ClientMsg::Tile(msg) => encode((C_TILE_TAG, msg))?,
+                ClientMsg::Viewport(msg) => encode((C_VIEWPORT_TAG, msg))?,
+                ClientMsg::Cancel(msg) => encode((C_CANCEL_TAG, msg))?,
+                ClientMsg::Format(msg) => encode((C_FORMAT_TAG, msg))?,
+                ClientMsg::Hello(msg) => encode((C_HELLO_TAG, msg))?,
+                ClientMsg::Subscribe(msg) => encode((C_SUBSCRIBE_TAG, msg))?,
+                ClientMsg::Unsubscribe(msg) => encode((C_UNSUBSCRIBE_TAG, msg))?,
+            },
+        )
+    }
+}
+#[cfg(test)]
+impl ServerMsg {
+    fn decode(msg: &[u8]) -> Result<Self, DecodeError> {
+        let (tag, payload) = msg
+            .split_first()
+            .ok_or(DecodeError::Other("Missing message tag"))?;
+        Ok(
+            match *tag {
+                S_ERROR_TAG => ServerMsg::Error(decode::<ErrorServerMsg>(payload)?),
+                S_TILE_TAG => ServerMsg::Tile(decode::<TileServerMsg>(payload)?),
+                S_DIRECTORY_TAG => {
+                    let (subtag, payload) = payload
+                        .split_first()
+                        .ok_or(DecodeError::Other("Missing message subtag"))?;
+                    let msg = decode::<DirectoryServerMsg>(payload)?;
+                    if msg.subtag() != *subtag {
+                        return Err(DecodeError::Other("Invalid message subtag."));
+                    }
+                    ServerMsg::Directory(msg)
+                }
+                S_CAPABILITIES_TAG => {
+                    ServerMsg::Capabilities(decode::<CapabilitiesServerMsg>(payload)?)
+                }
+                S_RESYNC_TAG => ServerMsg::Resync,
+                S_HELLO_TAG => ServerMsg::Hello(decode::<HelloServerMsg>(payload)?),
+                S_ANNOTATION_TAG => {
+                    let (subtag, payload) = payload
+                        .split_first()
+                        .ok_or(DecodeError::Other("Missing message subtag"))?;
+                    let msg = decode::<AnnotationServerMsg>(payload)?;
+                    if msg.subtag() != *subtag {
+                        return Err(DecodeError::Other("Invalid message subtag."));
+                    }
+                    ServerMsg::Annotation(msg)
+                }
+                S_ANNOTATION_LAYER_TAG => {
+                    let (subtag, payload) = payload
+                        .split_first()
+                        .ok_or(DecodeError::Other("Missing message subtag"))?;
+                    let msg = decode::<AnnotationLayerServerMsg>(payload)?;
+                    if msg.subtag() != *subtag {
+                        return Err(DecodeError::Other("Invalid message subtag."));
+                    }
+                    ServerMsg::AnnotationLayer(msg)
+                }
+                S_GENERATION_TAG => {
+                    ServerMsg::Generation(decode::<GenerationServerMsg>(payload)?)
+                }
+                _ => return Err(DecodeError::Other("Invalid message.")),
+            },
+        )
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::{prelude::*, test_runner::TestRunner};
+    trait RoundTrip: Sized {
+        fn to_bytes(self) -> Vec<u8>;
+        fn from_bytes(bytes: Vec<u8>) -> Self;
+    }
+    impl RoundTrip for ClientMsg {
+        fn to_bytes(self) -> Vec<u8> {
+            self.encode().unwrap()
+        }
+        fn from_bytes(bytes: Vec<u8>) -> Self {
+            Self::try_from(Bytes::from(bytes)).unwrap()
+        }
+    }
+    impl RoundTrip for ServerMsg {
+        fn to_bytes(self) -> Vec<u8> {
+            let msg: Message = self.try_into().unwrap();
+            msg.into_data().to_vec()
+        }
+        fn from_bytes(bytes: Vec<u8>) -> Self {
+            Self::decode(&bytes).unwrap()
+        }
+    }
+    fn assert_round_trip<M: RoundTrip>(msg: M) {
+        let bytes = msg.to_bytes();
+        assert_eq!(M::from_bytes(bytes.clone()).to_bytes(), bytes);
+    }
+    /// Round-trips the message with payloads generated by proptest.
+    fn assert_payloads_round_trip<P: Arbitrary, M: RoundTrip>(message: fn(P) -> M) {
+        TestRunner::default()
+            .run(
+                &any::<P>(),
+                |payload| {
+                    let bytes = message(payload).to_bytes();
+                    prop_assert_eq!(M::from_bytes(bytes.clone()).to_bytes(), bytes);
+                    Ok(())
+                },
+            )
+            .unwrap();
+    }
+    #[test]
+    fn client_tile_round_trips() {
+        assert_payloads_round_trip(ClientMsg::Tile);
+    }
+    #[test]
+    fn client_viewport_round_trips() {
+        assert_payloads_round_trip(ClientMsg::Viewport);
+    }
+    #[test]
+    fn client_cancel_round_trips() {
+        assert_payloads_round_trip(ClientMsg::Cancel);
+    }
+    #[test]
+    fn client_format_round_trips() {
+        assert_payloads_round_trip(ClientMsg::Format);
+    }
+    #[test]
+    fn client_hello_round_trips() {
+        assert_payloads_round_trip(ClientMsg::Hello);
+    }
+    #[test]
+    fn client_subscribe_round_trips() {
+        assert_payloads_round_trip(ClientMsg::Subscribe);
+    }
+    #[test]
+    fn client_unsubscribe_round_trips() {
+        assert_payloads_round_trip(ClientMsg::Unsubscribe);
+    }
+    #[test]
+    fn server_error_round_trips() {
+        assert_payloads_round_trip(ServerMsg::Error);
+    }
+    #[test]
+    fn server_tile_round_trips() {
+        assert_payloads_round_trip(ServerMsg::Tile);
+    }
+    #[test]
+    fn server_directory_round_trips() {
+        assert_payloads_round_trip(ServerMsg::Directory);
+    }
+    #[test]
+    fn server_capabilities_round_trips() {
+        assert_payloads_round_trip(ServerMsg::Capabilities);
+    }
+    #[test]
+    fn server_resync_round_trips() {
+        assert_round_trip(ServerMsg::Resync);
+    }
+    #[test]
+    fn server_hello_round_trips() {
+        assert_payloads_round_trip(ServerMsg::Hello);
+    }
+    #[test]
+    fn server_annotation_round_trips() {
+        assert_payloads_round_trip(ServerMsg::Annotation);
+    }
+    #[test]
+    fn server_annotation_layer_round_trips() {
+        assert_payloads_round_trip(ServerMsg::AnnotationLayer);
+    }
+    #[test]
+    fn server_generation_round_trips() {
+        assert_payloads_round_trip(ServerMsg::Generation);
+    }
+}
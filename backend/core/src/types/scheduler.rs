@@ -0,0 +1,376 @@
+use crate::types::messages::{TileClientMsg, TileFormat};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::{Notify, OwnedSemaphorePermit, Semaphore};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TileKey {
+    pub store_id: u32,
+    pub image_id: u32,
+    pub level: u32,
+    pub x: u32,
+    pub y: u32,
+    pub format: Option<TileFormat>,
+}
+
+/// Part of the image the client is currently looking at.
+#[derive(Debug, Clone, Copy)]
+struct Focus {
+    store_id: u32,
+    image_id: u32,
+    level: u32,
+    centre_x: f64,
+    centre_y: f64,
+}
+
+#[derive(Default)]
+struct Queue {
+    queued: Vec<TileClientMsg>,
+    // Tiles that are either queued or currently being retrieved, along with every
+    // request waiting for them.
+    pending: HashMap<TileKey, Vec<u32>>,
+    focus: Option<Focus>,
+    closed: bool,
+}
+
+/// Per-connection tile queue with bounded concurrency.
+///
+/// Requests are dispatched in priority order: tiles on the level the client is
+/// currently viewing come first, closest to the centre of its viewport.
+pub struct TileScheduler {
+    queue: Mutex<Queue>,
+    notify: Notify,
+    permits: Arc<Semaphore>,
+}
+
+impl TileScheduler {
+    pub fn new(concurrency: usize) -> Self {
+        Self {
+            queue: Mutex::new(Queue::default()),
+            notify: Notify::new(),
+            permits: Arc::new(Semaphore::new(concurrency)),
+        }
+    }
+
+    /// Queues a single tile and centres the focus on it. Returns `false` if the tile
+    /// is already pending, in which case the request is answered along with it.
+    pub fn push(&self, tile: TileClientMsg) -> bool {
+        let mut queue = self.queue.lock().unwrap();
+
+        queue.focus = Some(Focus {
+            store_id: tile.store_id,
+            image_id: tile.image_id,
+            level: tile.level,
+            centre_x: f64::from(tile.x),
+            centre_y: f64::from(tile.y),
+        });
+
+        let queued = queue.enqueue(tile);
+        drop(queue);
+
+        if queued {
+            self.notify.notify_one();
+        }
+
+        queued
+    }
+
+    /// Queues every tile of a viewport and moves the focus to its centre.
+    /// Returns the number of tiles that were not already pending.
+    pub fn push_viewport(&self, tiles: Vec<TileClientMsg>) -> usize {
+        let Some(first) = tiles.first() else {
+            return 0;
+        };
+
+        let (mut x_min, mut y_min, mut x_max, mut y_max) = (first.x, first.y, first.x, first.y);
+        for tile in &tiles {
+            x_min = x_min.min(tile.x);
+            y_min = y_min.min(tile.y);
+            x_max = x_max.max(tile.x);
+            y_max = y_max.max(tile.y);
+        }
+
+        let mut queue = self.queue.lock().unwrap();
+
+        queue.focus = Some(Focus {
+            store_id: first.store_id,
+            image_id: first.image_id,
+            level: first.level,
+            centre_x: f64::midpoint(f64::from(x_min), f64::from(x_max)),
+            centre_y: f64::midpoint(f64::from(y_min), f64::from(y_max)),
+        });
+
+        let queued = tiles
+            .into_iter()
+            .filter(|tile| queue.enqueue(*tile))
+            .count();
+        drop(queue);
+
+        for _ in 0..queued {
+            self.notify.notify_one();
+        }
+
+        queued
+    }
+
+    /// Drops queued requests for an image, optionally restricted to one level.
+    /// Tiles that are already being retrieved are left to finish.
+    /// Returns the number of requests dropped.
+    pub fn cancel(&self, store_id: u32, image_id: u32, level: Option<u32>) -> usize {
+        let mut queue = self.queue.lock().unwrap();
+        let Queue {
+            queued, pending, ..
+        } = &mut *queue;
+
+        let before = queued.len();
+        queued.retain(|tile| {
+            let cancelled = tile.store_id == store_id
+                && tile.image_id == image_id
+                && level.is_none_or(|level| tile.level == level);
+            if cancelled {
+                pending.remove(&TileKey::from(tile));
+            }
+            !cancelled
+        });
+
+        before - queued.len()
+    }
+
+    /// Waits for a free slot and the highest priority queued tile.
+    /// Returns `None` once the scheduler has been closed.
+    pub async fn next(&self) -> Option<(TileClientMsg, OwnedSemaphorePermit)> {
+        let permit = Arc::clone(&self.permits).acquire_owned().await.ok()?;
+
+        loop {
+            {
+                let mut queue = self.queue.lock().unwrap();
+                if queue.closed {
+                    return None;
+                }
+                if let Some(tile) = queue.pop() {
+                    return Some((tile, permit));
+                }
+            }
+
+            self.notify.notified().await;
+        }
+    }
+
+    /// Marks a dispatched tile as finished so it can be requested again.
+    /// Returns the requests waiting for it.
+    pub fn complete(&self, key: &TileKey) -> Vec<u32> {
+        self.queue
+            .lock()
+            .unwrap()
+            .pending
+            .remove(key)
+            .unwrap_or_default()
+    }
+
+    /// Drops everything that is queued and stops `next` from handing out more work.
+    pub fn close(&self) {
+        let mut queue = self.queue.lock().unwrap();
+        queue.closed = true;
+        queue.queued.clear();
+        queue.pending.clear();
+        drop(queue);
+
+        self.permits.close();
+        self.notify.notify_one();
+    }
+}
+
+impl Queue {
+    fn enqueue(&mut self, tile: TileClientMsg) -> bool {
+        if self.closed {
+            return false;
+        }
+
+        if let Some(request_ids) = self.pending.get_mut(&TileKey::from(&tile)) {
+            request_ids.push(tile.request_id);
+            return false;
+        }
+
+        self.pending
+            .insert(TileKey::from(&tile), vec![tile.request_id]);
+        self.queued.push(tile);
+        true
+    }
+
+    fn pop(&mut self) -> Option<TileClientMsg> {
+        let focus = self.focus;
+        let (index, _) = self
+            .queued
+            .iter()
+            .enumerate()
+            // Ties go to the oldest request.
+            .min_by(|(_, a), (_, b)| priority(focus, a).total_cmp(&priority(focus, b)))?;
+
+        Some(self.queued.remove(index))
+    }
+}
+
+/// Lower is more urgent. Tiles on the focused level are ranked by their distance
+/// to the viewport centre, all other tiles by how far their level is from it.
+fn priority(focus: Option<Focus>, tile: &TileClientMsg) -> f64 {
+    let Some(focus) = focus else {
+        return 0.0;
+    };
+
+    if tile.store_id != focus.store_id || tile.image_id != focus.image_id {
+        return f64::MAX;
+    }
+
+    if tile.level == focus.level {
+        (f64::from(tile.x) - focus.centre_x).hypot(f64::from(tile.y) - focus.centre_y)
+    } else {
+        // Rank behind every tile on the focused level.
+        f64::from(u32::MAX) + f64::from(tile.level.abs_diff(focus.level))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::MAX_CONCURRENT_TILES;
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    fn tile(request_id: u32, image_id: u32, level: u32, x: u32, y: u32) -> TileClientMsg {
+        TileClientMsg {
+            request_id,
+            store_id: 0,
+            image_id,
+            level,
+            x,
+            y,
+            format: None,
+        }
+    }
+
+    async fn pop(scheduler: &TileScheduler) -> TileClientMsg {
+        let (tile, _permit) = scheduler.next().await.unwrap();
+        scheduler.complete(&TileKey::from(&tile));
+        tile
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn concurrency_stays_bounded_under_load() {
+        let scheduler = Arc::new(TileScheduler::new(MAX_CONCURRENT_TILES));
+        let tiles = 200;
+        for i in 0..tiles {
+            assert!(scheduler.push(tile(i, 0, i % 4, i, i)));
+        }
+
+        let active = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let completed = Arc::new(AtomicUsize::new(0));
+        let mut handles = Vec::new();
+        for _ in 0..tiles {
+            let (tile, permit) = scheduler.next().await.unwrap();
+            let scheduler = Arc::clone(&scheduler);
+            let active = Arc::clone(&active);
+            let peak = Arc::clone(&peak);
+            let completed = Arc::clone(&completed);
+
+            handles.push(tokio::spawn(async move {
+                let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(2)).await;
+                active.fetch_sub(1, Ordering::SeqCst);
+                scheduler.complete(&TileKey::from(&tile));
+                completed.fetch_add(1, Ordering::SeqCst);
+                drop(permit);
+            }));
+        }
+        for handle in handles {
+            handle.await.unwrap();
+        }
+
+        // How many tiles overlap depends on timing, so only the bound is certain.
+        assert!(peak.load(Ordering::SeqCst) <= MAX_CONCURRENT_TILES);
+        assert_eq!(completed.load(Ordering::SeqCst), tiles as usize);
+        assert!(scheduler.queue.lock().unwrap().pending.is_empty());
+    }
+
+    #[tokio::test]
+    async fn cancel_drops_queued_tiles() {
+        let scheduler = TileScheduler::new(MAX_CONCURRENT_TILES);
+        for (i, (image_id, level)) in [(1, 0), (1, 0), (1, 1), (2, 0)].into_iter().enumerate() {
+            #[allow(clippy::cast_possible_truncation)]
+            let i = i as u32;
+            scheduler.push(tile(i, image_id, level, i, 0));
+        }
+
+        assert_eq!(scheduler.cancel(0, 1, Some(0)), 2);
+        assert_eq!(scheduler.cancel(0, 1, Some(0)), 0);
+        assert_eq!(scheduler.cancel(0, 1, None), 1);
+
+        // Cancelled tiles can be requested again.
+        assert!(scheduler.push(tile(4, 1, 0, 0, 0)));
+
+        let mut remaining = vec![
+            pop(&scheduler).await.image_id,
+            pop(&scheduler).await.image_id,
+        ];
+        remaining.sort_unstable();
+        assert_eq!(remaining, [1, 2]);
+    }
+
+    #[tokio::test]
+    async fn dispatches_closest_to_centre_first() {
+        let scheduler = TileScheduler::new(MAX_CONCURRENT_TILES);
+        let tiles = (0..5)
+            .flat_map(|y| (0..5).map(move |x| tile(y * 5 + x, 0, 2, x, y)))
+            // Another level is only dispatched once the focused one is done.
+            .chain([tile(25, 0, 3, 2, 2)])
+            .collect();
+        assert_eq!(scheduler.push_viewport(tiles), 26);
+
+        let mut previous = 0.0;
+        for _ in 0..25 {
+            let tile = pop(&scheduler).await;
+            assert_eq!(tile.level, 2);
+            let distance = (f64::from(tile.x) - 2.0).hypot(f64::from(tile.y) - 2.0);
+            assert!(distance >= previous);
+            previous = distance;
+        }
+        assert_eq!(pop(&scheduler).await.level, 3);
+    }
+
+    #[tokio::test]
+    async fn push_on_same_level_moves_centre() {
+        let scheduler = TileScheduler::new(MAX_CONCURRENT_TILES);
+        scheduler.push_viewport((0..10).map(|x| tile(x, 0, 2, x, 0)).collect());
+
+        // Already pending, but the client has panned towards it.
+        assert!(!scheduler.push(tile(10, 0, 2, 9, 0)));
+
+        assert_eq!(pop(&scheduler).await.x, 9);
+        assert_eq!(pop(&scheduler).await.x, 8);
+    }
+
+    #[test]
+    fn duplicate_requests_share_the_pending_tile() {
+        let scheduler = TileScheduler::new(MAX_CONCURRENT_TILES);
+        assert!(scheduler.push(tile(0, 0, 0, 0, 0)));
+        assert!(!scheduler.push(tile(1, 0, 0, 0, 0)));
+
+        // A different format is a different tile.
+        let png = TileClientMsg {
+            format: Some(TileFormat::Png),
+            ..tile(2, 0, 0, 0, 0)
+        };
+        assert!(scheduler.push(png));
+
+        assert_eq!(
+            scheduler.complete(&TileKey::from(&tile(0, 0, 0, 0, 0))),
+            [0, 1]
+        );
+        assert_eq!(scheduler.complete(&TileKey::from(&png)), [2]);
+    }
+}
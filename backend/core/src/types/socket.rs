@@ -1,16 +1,63 @@
-use crate::types::messages::ServerMsg;
+use crate::{constants::WEBSOCKET_SEND_TIMEOUT, log::Error, types::messages::ServerMsg};
 use anyhow::Result;
 use axum::extract::ws::Message;
 use dashmap::DashMap;
-use tokio::sync::{broadcast, mpsc};
+use std::{
+    collections::HashSet,
+    sync::atomic::{AtomicU64, Ordering},
+};
+use tokio::sync::{
+    broadcast,
+    mpsc::{self, error::SendTimeoutError},
+};
 
-type Broadcast = broadcast::Sender<Message>;
-type Connections = DashMap<u32, mpsc::Sender<Message>>;
+// Messages tagged with the id of the store they concern.
+type Broadcast = broadcast::Sender<(u32, Message)>;
+// Session id to its connection.
+type Connections = DashMap<u64, Connection>;
+// User id to the ids of their open sessions.
+type Sessions = DashMap<u32, HashSet<u64>>;
 
-#[derive(Debug, Clone)]
+/// Failure while handling a websocket message, reported back to the client.
+pub struct SocketError {
+    pub error: Error,
+    pub id: &'static str,
+    pub message: &'static str,
+    pub details: Option<anyhow::Error>,
+}
+
+impl SocketError {
+    pub fn new(
+        error: Error,
+        id: &'static str,
+        message: &'static str,
+        details: Option<anyhow::Error>,
+    ) -> Self {
+        Self {
+            error,
+            id,
+            message,
+            details,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Connection {
+    user_id: u32,
+    sender: mpsc::Sender<Message>,
+    // Stores the session receives broadcasts for.
+    stores: HashSet<u32>,
+}
+
+/// Open websocket connections, one session per socket.
+/// A user may have several sessions open at once, e.g. one per tab.
+#[derive(Debug)]
 pub struct ClientSocketManager {
     pub broadcast: Broadcast,
     connections: Connections,
+    sessions: Sessions,
+    next_session_id: AtomicU64,
 }
 
 impl Default for ClientSocketManager {
@@ -18,30 +65,124 @@ impl Default for ClientSocketManager {
         Self {
             broadcast: broadcast::channel(1024).0,
             connections: DashMap::new(),
+            sessions: DashMap::new(),
+            next_session_id: AtomicU64::new(0),
         }
     }
 }
 
 impl ClientSocketManager {
-    pub fn add_connection(&self, user_id: u32, sender: mpsc::Sender<Message>) {
-        self.connections.insert(user_id, sender);
+    /// Registers a new socket for the user and returns its session id.
+    pub fn add_connection(&self, user_id: u32, sender: mpsc::Sender<Message>) -> u64 {
+        let session_id = self.next_session_id.fetch_add(1, Ordering::Relaxed);
+
+        self.connections.insert(
+            session_id,
+            Connection {
+                user_id,
+                sender,
+                stores: HashSet::new(),
+            },
+        );
+        self.sessions.entry(user_id).or_default().insert(session_id);
+
+        session_id
     }
 
-    pub fn remove_connection(&self, user_id: u32) {
-        self.connections.remove(&user_id);
+    pub fn remove_connection(&self, session_id: u64) {
+        let Some((_, Connection { user_id, .. })) = self.connections.remove(&session_id) else {
+            return;
+        };
+
+        self.sessions.remove_if_mut(&user_id, |_, sessions| {
+            sessions.remove(&session_id);
+            sessions.is_empty()
+        });
     }
 
-    // Send to specific user.
-    pub async fn send(&self, user_id: u32, msg: ServerMsg) -> Result<()> {
-        if let Some(sender) = self.connections.get(&user_id) {
-            sender.send(msg.try_into()?).await?;
+    pub fn subscribe(&self, session_id: u64, store_id: u32) {
+        if let Some(mut connection) = self.connections.get_mut(&session_id) {
+            connection.stores.insert(store_id);
+        }
+    }
+
+    pub fn unsubscribe(&self, session_id: u64, store_id: u32) {
+        if let Some(mut connection) = self.connections.get_mut(&session_id) {
+            connection.stores.remove(&store_id);
+        }
+    }
+
+    pub fn is_subscribed(&self, session_id: u64, store_id: u32) -> bool {
+        self.connections
+            .get(&session_id)
+            .is_some_and(|connection| connection.stores.contains(&store_id))
+    }
+
+    // Send to specific session.
+    pub async fn send(&self, session_id: u64, msg: ServerMsg) -> Result<()> {
+        self.send_message(session_id, msg.try_into()?).await
+    }
+
+    // Send to every session of a user. A failed session does not stop the rest.
+    pub async fn send_user(&self, user_id: u32, msg: ServerMsg) -> Result<()> {
+        let session_ids: Vec<_> = self
+            .sessions
+            .get(&user_id)
+            .map(|sessions| sessions.iter().copied().collect())
+            .unwrap_or_default();
+
+        let msg: Message = msg.try_into()?;
+        let mut errors = Vec::new();
+        for &session_id in &session_ids {
+            if let Err(e) = self.send_message(session_id, msg.clone()).await {
+                errors.push(e.to_string());
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!(
+                "Failed to send to {} of {} sessions: {}",
+                errors.len(),
+                session_ids.len(),
+                errors.join(" ")
+            ))
+        }
+    }
+
+    /// Queues a message for a session, waiting for room in its outbound queue.
+    /// A session that stays full for `WEBSOCKET_SEND_TIMEOUT` is assumed stalled
+    /// and disconnected rather than buffering without bound.
+    async fn send_message(&self, session_id: u64, msg: Message) -> Result<()> {
+        // Clone the sender so the map is not locked while waiting for capacity.
+        let Some(sender) = self
+            .connections
+            .get(&session_id)
+            .map(|connection| connection.sender.clone())
+        else {
+            return Ok(());
+        };
+
+        match sender.send_timeout(msg, WEBSOCKET_SEND_TIMEOUT).await {
+            Ok(()) => Ok(()),
+            Err(SendTimeoutError::Timeout(_)) => {
+                self.remove_connection(session_id);
+                Err(anyhow::anyhow!(
+                    "Outbound queue of session {session_id} is full; disconnected."
+                ))
+            }
+            Err(SendTimeoutError::Closed(_)) => {
+                self.remove_connection(session_id);
+                Err(anyhow::anyhow!("Session {session_id} is closed."))
+            }
         }
-        Ok(())
     }
 
-    // Broadcast to all users.
-    pub async fn broadcast(&self, msg: ServerMsg) -> Result<()> {
-        self.broadcast.send(msg.try_into()?)?;
+    // Broadcast to every session subscribed to the store.
+    pub async fn broadcast(&self, store_id: u32, msg: ServerMsg) -> Result<()> {
+        // Sending only fails when nobody is connected, which is not an error.
+        let _ = self.broadcast.send((store_id, msg.try_into()?));
         Ok(())
     }
 }
@@ -72,4 +72,20 @@ impl Decoder for Module {
 
         Ok(thumbnail)
     }
+
+    fn get_microns_per_pixel(&self) -> Result<Option<f64>> {
+        let mpp = |name: &str| {
+            self.image
+                .get_property_value(name)
+                .ok()
+                .and_then(|value| value.parse::<f64>().ok())
+                .filter(|mpp| *mpp > 0.0)
+        };
+
+        // Pixels are treated as square, so the two axes are averaged.
+        Ok(match (mpp("openslide.mpp-x"), mpp("openslide.mpp-y")) {
+            (Some(x), Some(y)) => Some(f64::midpoint(x, y)),
+            (x, y) => x.or(y),
+        })
+    }
 }
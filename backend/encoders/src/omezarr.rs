@@ -168,4 +168,48 @@ impl Encoder for Module {
 
         Ok(())
     }
+
+    fn retrieve_region(&self, image_path: &Path, region: &Region) -> Result<Vec<u8>> {
+        let store = Arc::new(FilesystemStore::new(image_path)?);
+        let array = Array::open(store, &format!("{GROUP_PATH}/{}", region.level))?;
+
+        let tile = TILE_SIZE as usize;
+        let x = region.address.x as usize;
+        let y = region.address.y as usize;
+        let width = region.size.width as usize;
+        let height = region.size.height as usize;
+
+        if width == 0 || height == 0 {
+            return Err(anyhow::anyhow!("Region is empty."));
+        }
+
+        // Chunks covering the region.
+        let (x_start, y_start) = (x / tile, y / tile);
+        let (x_end, y_end) = ((x + width - 1) / tile, (y + height - 1) / tile);
+
+        // Retrieve covering chunks for each RGB channel.
+        let channels =
+            array.retrieve_chunks_elements::<u8>(&ArraySubset::new_with_start_end_inc(
+                vec![0, 0, 0, y_start as u64, x_start as u64],
+                vec![0, 2, 0, y_end as u64, x_end as u64],
+            )?)?;
+
+        let stride = (x_end - x_start + 1) * tile;
+        let plane = stride * (y_end - y_start + 1) * tile;
+
+        // Crop to the region and interleave RGB channels.
+        let mut output = Vec::with_capacity(width * height * RGB_CHANNELS as usize);
+        for row in 0..height {
+            let offset = (y - y_start * tile + row) * stride + (x - x_start * tile);
+            for i in offset..offset + width {
+                output.extend_from_slice(&[
+                    channels[i],
+                    channels[plane + i],
+                    channels[plane * 2 + i],
+                ]);
+            }
+        }
+
+        Ok(output)
+    }
 }
@@ -23,7 +23,11 @@ fn generate_common() -> proc_macro2::TokenStream {
     quote! {
         /// Auto-generated file. Any changes will be overwritten.
         pub use anyhow::Result;
-        pub use shared::{timer::Timer, traits::Generator, types::AnnotationLayer};
+        pub use shared::{
+            timer::Timer,
+            traits::{Decoder, Generator},
+            types::AnnotationLayer,
+        };
         pub use std::path::Path;
     }
 }
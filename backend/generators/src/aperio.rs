@@ -0,0 +1,302 @@
+use crate::common::*;
+use roxmltree::{Document, Node};
+use shared::types::{Annotation, AnnotationLayers, Geometry, Polygon, Properties};
+use std::{f64::consts::TAU, fs};
+
+// Region types, as written by ImageScope.
+const REGION_FREEHAND: &str = "0";
+const REGION_RECTANGLE: &str = "1";
+const REGION_ELLIPSE: &str = "2";
+const REGION_ARROW: &str = "3";
+const REGION_RULER: &str = "4";
+// Number of vertices used to approximate an ellipse.
+const ELLIPSE_VERTICES: u32 = 64;
+
+pub struct Module;
+
+impl Generator for Module {
+    fn name(&self) -> &'static str {
+        "Aperio ImageScope"
+    }
+
+    fn translate(&self, annotations_path: &Path) -> Result<Vec<AnnotationLayer>> {
+        let mut timer = Timer::new("generators/aperio/translate");
+
+        let contents = fs::read_to_string(annotations_path)?;
+        let document = Document::parse(&contents)?;
+
+        timer.lap("Parsed annotations file.");
+
+        let mut layers = AnnotationLayers::default();
+
+        // Each annotation is a layer of regions.
+        for annotation in document
+            .descendants()
+            .filter(|node| node.has_tag_name("Annotation"))
+        {
+            let tag = tag(annotation);
+            let fill = annotation.attribute("LineColor").and_then(colour);
+
+            let mut polygons = Vec::new();
+            // Negative regions cut holes into the regions enclosing them.
+            let mut holes = Vec::new();
+
+            for region in annotation
+                .descendants()
+                .filter(|node| node.has_tag_name("Region"))
+            {
+                let vertices = vertices(region);
+                let properties = properties(region);
+
+                let geometry = match region.attribute("Type").unwrap_or(REGION_FREEHAND) {
+                    REGION_FREEHAND | REGION_RECTANGLE if vertices.len() >= 3 => vertices,
+                    REGION_ELLIPSE if vertices.len() >= 2 => ellipse(vertices[0], vertices[1]),
+                    REGION_ARROW | REGION_RULER if vertices.len() >= 2 => {
+                        layers.insert_with_fill(
+                            tag.clone(),
+                            fill.clone(),
+                            Annotation {
+                                geometry: Geometry::LineString(vertices),
+                                properties,
+                            },
+                        );
+                        continue;
+                    }
+                    _ => continue,
+                };
+
+                if region.attribute("NegativeROA") == Some("1") {
+                    holes.push(geometry);
+                } else {
+                    polygons.push((Polygon::new(geometry), properties));
+                }
+            }
+
+            for hole in holes {
+                if let Some((polygon, _)) = polygons
+                    .iter_mut()
+                    .find(|(polygon, _)| contains(&polygon.exterior, hole[0]))
+                {
+                    polygon.interiors.push(hole);
+                }
+            }
+
+            for (polygon, properties) in polygons {
+                layers.insert_with_fill(
+                    tag.clone(),
+                    fill.clone(),
+                    Annotation {
+                        geometry: Geometry::Polygon(polygon),
+                        properties,
+                    },
+                );
+            }
+        }
+
+        timer.end("Grouped annotations.");
+
+        Ok(layers.to_vec())
+    }
+}
+
+/// Name of an annotation, falling back to its description and then its id.
+fn tag(annotation: Node) -> String {
+    let description = annotation
+        .descendants()
+        .filter(|node| node.has_tag_name("Attribute"))
+        .find(|attribute| attribute.attribute("Name") == Some("Description"))
+        .and_then(|attribute| attribute.attribute("Value"));
+
+    [annotation.attribute("Name"), description]
+        .into_iter()
+        .flatten()
+        .find(|name| !name.trim().is_empty())
+        .map_or_else(
+            || format!("Layer {}", annotation.attribute("Id").unwrap_or_default()),
+            |name| name.trim().to_string(),
+        )
+}
+
+fn vertices(region: Node) -> Vec<[f64; 2]> {
+    region
+        .descendants()
+        .filter(|node| node.has_tag_name("Vertex"))
+        .filter_map(|vertex| {
+            let x = vertex.attribute("X")?.parse().ok()?;
+            let y = vertex.attribute("Y")?.parse().ok()?;
+            Some([x, y])
+        })
+        .collect()
+}
+
+/// Text and measurements ImageScope records for a region.
+fn properties(region: Node) -> Properties {
+    let mut properties = Properties::new();
+
+    if let Some(text) = region.attribute("Text").filter(|text| !text.is_empty()) {
+        properties.insert("text".into(), text.into());
+    }
+    for (attribute, key) in [("Area", "area"), ("Length", "length")] {
+        if let Some(value) = region
+            .attribute(attribute)
+            .and_then(|value| value.parse::<f64>().ok())
+        {
+            properties.insert(key.into(), value.into());
+        }
+    }
+
+    properties
+}
+
+/// Ellipse inscribed in the box spanned by two opposite corners.
+fn ellipse([x0, y0]: [f64; 2], [x1, y1]: [f64; 2]) -> Vec<[f64; 2]> {
+    let (cx, cy) = ((x0 + x1) / 2.0, (y0 + y1) / 2.0);
+    let (rx, ry) = ((x1 - x0).abs() / 2.0, (y1 - y0).abs() / 2.0);
+
+    (0..ELLIPSE_VERTICES)
+        .map(|i| {
+            let angle = TAU * f64::from(i) / f64::from(ELLIPSE_VERTICES);
+            [cx + rx * angle.cos(), cy + ry * angle.sin()]
+        })
+        .collect()
+}
+
+/// Whether a point lies inside a ring, by ray casting.
+fn contains(ring: &[[f64; 2]], [x, y]: [f64; 2]) -> bool {
+    let mut inside = false;
+    let mut j = ring.len() - 1;
+    for (i, &[xi, yi]) in ring.iter().enumerate() {
+        let [xj, yj] = ring[j];
+        if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+/// ImageScope stores colours as a Windows `COLORREF`, i.e. `0x00BBGGRR`.
+fn colour(value: &str) -> Option<String> {
+    let colour: u32 = value.parse().ok()?;
+    let [r, g, b, _] = colour.to_le_bytes();
+
+    Some(format!("#{r:02X}{g:02X}{b:02X}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn translate(document: &str) -> Vec<AnnotationLayer> {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(document.as_bytes()).unwrap();
+        Module.translate(file.path()).unwrap()
+    }
+
+    fn polygons(layer: &AnnotationLayer) -> Vec<&Polygon> {
+        layer
+            .annotations
+            .iter()
+            .filter_map(|annotation| match &annotation.geometry {
+                Geometry::Polygon(polygon) => Some(polygon),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reads_colourref_as_bgr() {
+        assert_eq!(colour("255").as_deref(), Some("#FF0000"));
+        assert_eq!(colour("65280").as_deref(), Some("#00FF00"));
+        assert_eq!(colour("16711680").as_deref(), Some("#0000FF"));
+        assert_eq!(colour("4227327").as_deref(), Some("#FF8040"));
+        assert_eq!(colour("red"), None);
+    }
+
+    #[test]
+    fn assigns_negative_regions_as_holes() {
+        let layers = translate(
+            r#"<Annotations>
+                <Annotation Id="1" Name="Tumour" LineColor="255">
+                    <Regions>
+                        <Region Id="1" Type="1" Text="Outer">
+                            <Vertices>
+                                <Vertex X="0" Y="0" /><Vertex X="100" Y="0" />
+                                <Vertex X="100" Y="100" /><Vertex X="0" Y="100" />
+                            </Vertices>
+                        </Region>
+                        <Region Id="2" Type="0">
+                            <Vertices>
+                                <Vertex X="200" Y="0" /><Vertex X="300" Y="0" /><Vertex X="300" Y="100" />
+                            </Vertices>
+                        </Region>
+                        <Region Id="3" Type="0" NegativeROA="1">
+                            <Vertices>
+                                <Vertex X="10" Y="10" /><Vertex X="20" Y="10" /><Vertex X="20" Y="20" />
+                            </Vertices>
+                        </Region>
+                    </Regions>
+                </Annotation>
+            </Annotations>"#,
+        );
+
+        assert_eq!(layers.len(), 1);
+        assert_eq!(layers[0].tag, "Tumour");
+        assert_eq!(layers[0].fill.as_deref(), Some("#FF0000"));
+
+        let polygons = polygons(&layers[0]);
+        assert_eq!(polygons.len(), 2);
+        assert_eq!(
+            polygons[0].interiors,
+            [vec![[10.0, 10.0], [20.0, 10.0], [20.0, 20.0]]]
+        );
+        assert!(polygons[1].interiors.is_empty());
+        assert_eq!(layers[0].annotations[0].properties["text"], "Outer");
+    }
+
+    #[test]
+    fn approximates_ellipses() {
+        let layers = translate(
+            r#"<Annotations>
+                <Annotation Id="7">
+                    <Regions>
+                        <Region Id="1" Type="2">
+                            <Vertices><Vertex X="10" Y="20" /><Vertex X="50" Y="40" /></Vertices>
+                        </Region>
+                    </Regions>
+                </Annotation>
+            </Annotations>"#,
+        );
+
+        assert_eq!(layers[0].tag, "Layer 7");
+        let exterior = &polygons(&layers[0])[0].exterior;
+        assert_eq!(exterior.len(), ELLIPSE_VERTICES as usize);
+        assert_eq!(exterior[0], [50.0, 30.0]);
+        for &[x, y] in exterior {
+            let (dx, dy) = ((x - 30.0) / 20.0, (y - 30.0) / 10.0);
+            assert!((dx * dx + dy * dy - 1.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn reads_rulers_as_lines() {
+        let layers = translate(
+            r#"<Annotations>
+                <Annotation Id="1">
+                    <Attributes><Attribute Name="Description" Value=" Scale " /></Attributes>
+                    <Regions>
+                        <Region Id="1" Type="4" Length="10.5">
+                            <Vertices><Vertex X="0" Y="0" /><Vertex X="10" Y="0" /></Vertices>
+                        </Region>
+                    </Regions>
+                </Annotation>
+            </Annotations>"#,
+        );
+
+        assert_eq!(layers[0].tag, "Scale");
+        let annotation = &layers[0].annotations[0];
+        assert!(matches!(&annotation.geometry, Geometry::LineString(line) if line.len() == 2));
+        assert_eq!(annotation.properties["length"], 10.5);
+    }
+}
@@ -0,0 +1,206 @@
+use crate::common::*;
+use roxmltree::{Document, Node};
+use shared::types::{Annotation, AnnotationLayers, Geometry, Polygon, Properties};
+use std::{collections::HashMap, fs};
+
+// Group of annotations outside any group.
+const UNGROUPED: &str = "None";
+
+pub struct Module;
+
+impl Generator for Module {
+    fn name(&self) -> &'static str {
+        "ASAP"
+    }
+
+    fn translate(&self, annotations_path: &Path) -> Result<Vec<AnnotationLayer>> {
+        let mut timer = Timer::new("generators/asap/translate");
+
+        let contents = fs::read_to_string(annotations_path)?;
+        let document = Document::parse(&contents)?;
+
+        timer.lap("Parsed annotations file.");
+
+        // Colours of the annotation groups, which take precedence over those of annotations.
+        let group_colours: HashMap<&str, &str> = document
+            .descendants()
+            .filter(|node| node.has_tag_name("Group"))
+            .filter_map(|group| Some((group.attribute("Name")?, group.attribute("Color")?)))
+            .collect();
+
+        let mut layers = AnnotationLayers::default();
+
+        for annotation in document
+            .descendants()
+            .filter(|node| node.has_tag_name("Annotation"))
+        {
+            let group = annotation.attribute("PartOfGroup").unwrap_or(UNGROUPED);
+            let fill = group_colours
+                .get(group)
+                .copied()
+                .or_else(|| annotation.attribute("Color"))
+                .map(str::to_uppercase);
+
+            let coordinates = coordinates(annotation);
+
+            let geometries = match annotation.attribute("Type") {
+                Some("Dot" | "PointSet") => coordinates.into_iter().map(Geometry::Point).collect(),
+                Some("Measurement") if coordinates.len() >= 2 => {
+                    vec![Geometry::LineString(coordinates)]
+                }
+                // Polygons, rectangles and splines, the latter by their control points.
+                _ if coordinates.len() >= 3 => vec![Geometry::Polygon(Polygon::new(coordinates))],
+                _ => continue,
+            };
+
+            let mut properties = Properties::new();
+            if let Some(name) = annotation.attribute("Name") {
+                properties.insert("name".into(), name.into());
+            }
+
+            for geometry in geometries {
+                layers.insert_with_fill(
+                    group.into(),
+                    fill.clone(),
+                    Annotation {
+                        geometry,
+                        properties: properties.clone(),
+                    },
+                );
+            }
+        }
+
+        timer.end("Grouped annotations.");
+
+        Ok(layers.to_vec())
+    }
+}
+
+/// Coordinates of an annotation, in the order given by their `Order` attribute.
+fn coordinates(annotation: Node) -> Vec<[f64; 2]> {
+    let mut coordinates: Vec<(u32, [f64; 2])> = annotation
+        .descendants()
+        .filter(|node| node.has_tag_name("Coordinate"))
+        .filter_map(|coordinate| {
+            let order = coordinate.attribute("Order")?.parse().ok()?;
+            let x = number(coordinate.attribute("X")?)?;
+            let y = number(coordinate.attribute("Y")?)?;
+            Some((order, [x, y]))
+        })
+        .collect();
+
+    coordinates.sort_by_key(|(order, _)| *order);
+    coordinates.into_iter().map(|(_, point)| point).collect()
+}
+
+/// ASAP writes numbers in the locale of the annotator, so the decimal separator may be a comma.
+fn number(value: &str) -> Option<f64> {
+    value.replace(',', ".").parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn translate(document: &str) -> Vec<AnnotationLayer> {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(document.as_bytes()).unwrap();
+        Module.translate(file.path()).unwrap()
+    }
+
+    fn layer<'a>(layers: &'a [AnnotationLayer], tag: &str) -> &'a AnnotationLayer {
+        layers.iter().find(|layer| layer.tag == tag).unwrap()
+    }
+
+    const DOCUMENT: &str = r##"<?xml version="1.0"?>
+        <ASAP_Annotations>
+            <Annotations>
+                <Annotation Name="Nuclei" Type="PointSet" PartOfGroup="Cells" Color="#f4fa58">
+                    <Coordinates>
+                        <Coordinate Order="0" X="1" Y="2" />
+                        <Coordinate Order="1" X="3" Y="4" />
+                        <Coordinate Order="2" X="5" Y="6" />
+                    </Coordinates>
+                </Annotation>
+                <Annotation Name="Mitosis" Type="Dot" PartOfGroup="Cells" Color="#000000">
+                    <Coordinates>
+                        <Coordinate Order="0" X="7,5" Y="8,25" />
+                    </Coordinates>
+                </Annotation>
+                <Annotation Name="Scale" Type="Measurement" Color="#00ff00">
+                    <Coordinates>
+                        <Coordinate Order="1" X="10" Y="0" />
+                        <Coordinate Order="0" X="0" Y="0" />
+                    </Coordinates>
+                </Annotation>
+                <Annotation Name="Tumour" Type="Polygon" PartOfGroup="Regions">
+                    <Coordinates>
+                        <Coordinate Order="2" X="10" Y="10" />
+                        <Coordinate Order="0" X="0" Y="0" />
+                        <Coordinate Order="1" X="10" Y="0" />
+                    </Coordinates>
+                </Annotation>
+                <Annotation Name="Stray" Type="Polygon" PartOfGroup="Regions">
+                    <Coordinates>
+                        <Coordinate Order="0" X="0" Y="0" />
+                    </Coordinates>
+                </Annotation>
+            </Annotations>
+            <AnnotationGroups>
+                <Group Name="Cells" PartOfGroup="None" Color="#64fe2e" />
+                <Group Name="Regions" PartOfGroup="None" Color="#ff0000" />
+            </AnnotationGroups>
+        </ASAP_Annotations>
+    "##;
+
+    #[test]
+    fn splits_dots_and_point_sets_into_points() {
+        let layers = translate(DOCUMENT);
+        let cells = layer(&layers, "Cells");
+
+        let points: Vec<_> = cells
+            .annotations
+            .iter()
+            .map(|annotation| match annotation.geometry {
+                Geometry::Point(point) => point,
+                _ => panic!("Expected a point."),
+            })
+            .collect();
+        assert_eq!(points, [[1.0, 2.0], [3.0, 4.0], [5.0, 6.0], [7.5, 8.25]]);
+        assert_eq!(cells.annotations[3].properties["name"], "Mitosis");
+    }
+
+    #[test]
+    fn reads_measurements_as_lines() {
+        let layers = translate(DOCUMENT);
+        let ungrouped = layer(&layers, UNGROUPED);
+
+        assert_eq!(ungrouped.fill.as_deref(), Some("#00FF00"));
+        assert!(matches!(
+            &ungrouped.annotations[..],
+            [Annotation { geometry: Geometry::LineString(line), .. }]
+                if line == &[[0.0, 0.0], [10.0, 0.0]]
+        ));
+    }
+
+    #[test]
+    fn orders_polygon_coordinates_and_skips_degenerate_ones() {
+        let layers = translate(DOCUMENT);
+        let regions = layer(&layers, "Regions");
+
+        assert!(matches!(
+            &regions.annotations[..],
+            [Annotation { geometry: Geometry::Polygon(polygon), .. }]
+                if polygon.exterior[..3] == [[0.0, 0.0], [10.0, 0.0], [10.0, 10.0]]
+        ));
+    }
+
+    #[test]
+    fn group_colours_take_precedence() {
+        let layers = translate(DOCUMENT);
+
+        assert_eq!(layer(&layers, "Cells").fill.as_deref(), Some("#64FE2E"));
+        assert_eq!(layer(&layers, "Regions").fill.as_deref(), Some("#FF0000"));
+    }
+}
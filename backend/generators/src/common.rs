@@ -1,4 +1,7 @@
 /// Auto-generated file. Any changes will be overwritten.
 pub use anyhow::Result;
-pub use shared::{timer::Timer, traits::Generator, types::AnnotationLayer};
+pub use shared::{
+    timer::Timer, traits::{Decoder, Generator},
+    types::AnnotationLayer,
+};
 pub use std::path::Path;
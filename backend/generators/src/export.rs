@@ -2,10 +2,14 @@
 use crate::common::*;
 pub fn get(name: &str) -> Option<Box<dyn Generator>> {
     match name {
+        "Aperio ImageScope" => Some(Box::new(crate::aperio::Module)),
+        "ASAP" => Some(Box::new(crate::asap::Module)),
+        "GeoJSON" => Some(Box::new(crate::geojson::Module)),
         "TIAToolbox" => Some(Box::new(crate::tiatoolbox::Module)),
+        "Tissue Detection" => Some(Box::new(crate::tissue::Module)),
         _ => None,
     }
 }
 pub fn names() -> Vec<&'static str> {
-    vec!["TIAToolbox"]
+    vec!["Aperio ImageScope", "ASAP", "GeoJSON", "TIAToolbox", "Tissue Detection"]
 }
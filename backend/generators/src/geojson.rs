@@ -0,0 +1,325 @@
+use crate::common::*;
+use serde::Deserialize;
+use serde_json::Value;
+use shared::types::{Annotation, AnnotationLayers, Geometry, Polygon, Properties};
+use std::{env, fs};
+
+// Property holding the tag of a feature, as a dot separated path into its properties.
+const TAG_PROPERTY_VAR: &str = "GEOJSON_TAG_PROPERTY";
+const DEFAULT_TAG_PROPERTY: &str = "classification.name";
+// Tag of features without the tag property.
+const UNCLASSIFIED: &str = "Unclassified";
+
+pub struct Module;
+
+impl Generator for Module {
+    fn name(&self) -> &'static str {
+        "GeoJSON"
+    }
+
+    fn translate(&self, annotations_path: &Path) -> Result<Vec<AnnotationLayer>> {
+        let mut timer = Timer::new("generators/geojson/translate");
+
+        let document: Document = serde_json::from_slice(&fs::read(annotations_path)?)?;
+        let features = match document {
+            Document::Collection { features } | Document::Features(features) => features,
+            Document::Feature(feature) => vec![feature],
+        };
+
+        timer.lap("Parsed features.");
+
+        let tag_property =
+            env::var(TAG_PROPERTY_VAR).unwrap_or_else(|_| DEFAULT_TAG_PROPERTY.into());
+        let tag_path: Vec<&str> = tag_property.split('.').collect();
+
+        let mut layers = AnnotationLayers::default();
+
+        for feature in features {
+            let Some(geometry) = feature.geometry else {
+                continue;
+            };
+            let (tag, fill) = classify(feature.properties.as_ref(), &tag_path);
+            let properties = match feature.properties {
+                Some(Value::Object(properties)) => properties,
+                _ => Properties::new(),
+            };
+
+            // Parts of a multi-geometry share the properties of their feature.
+            let mut geometries = Vec::new();
+            geometry.flatten(&mut geometries);
+            for geometry in geometries {
+                layers.insert_with_fill(
+                    tag.clone(),
+                    fill.clone(),
+                    Annotation {
+                        geometry,
+                        properties: properties.clone(),
+                    },
+                );
+            }
+        }
+
+        timer.end("Grouped annotations.");
+
+        Ok(layers.to_vec())
+    }
+}
+
+/// QuPath exports either a `FeatureCollection` or a bare array of features.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Document {
+    Collection { features: Vec<Feature> },
+    Features(Vec<Feature>),
+    Feature(Feature),
+}
+
+#[derive(Deserialize)]
+struct Feature {
+    geometry: Option<FeatureGeometry>,
+    properties: Option<Value>,
+}
+
+// Positions may carry a third, elevation, coordinate which is ignored.
+type Position = Vec<f64>;
+
+#[derive(Deserialize)]
+#[serde(tag = "type")]
+enum FeatureGeometry {
+    Point {
+        coordinates: Position,
+    },
+    MultiPoint {
+        coordinates: Vec<Position>,
+    },
+    LineString {
+        coordinates: Vec<Position>,
+    },
+    MultiLineString {
+        coordinates: Vec<Vec<Position>>,
+    },
+    Polygon {
+        coordinates: Vec<Vec<Position>>,
+    },
+    MultiPolygon {
+        coordinates: Vec<Vec<Vec<Position>>>,
+    },
+    GeometryCollection {
+        geometries: Vec<FeatureGeometry>,
+    },
+}
+
+impl FeatureGeometry {
+    /// Collects the geometries, splitting multi-points, multi-lines and collections into their parts.
+    fn flatten(self, geometries: &mut Vec<Geometry>) {
+        match self {
+            FeatureGeometry::Point { coordinates } => {
+                geometries.extend(point(&coordinates).map(Geometry::Point));
+            }
+            FeatureGeometry::MultiPoint { coordinates } => {
+                geometries.extend(coordinates.iter().filter_map(point).map(Geometry::Point));
+            }
+            FeatureGeometry::LineString { coordinates } => {
+                geometries.push(Geometry::LineString(line(&coordinates)));
+            }
+            FeatureGeometry::MultiLineString { coordinates } => {
+                geometries.extend(
+                    coordinates
+                        .iter()
+                        .map(|coordinates| Geometry::LineString(line(coordinates))),
+                );
+            }
+            FeatureGeometry::Polygon { coordinates } => {
+                geometries.extend(polygon(coordinates).map(Geometry::Polygon));
+            }
+            FeatureGeometry::MultiPolygon { coordinates } => {
+                geometries.push(Geometry::MultiPolygon(
+                    coordinates.into_iter().filter_map(polygon).collect(),
+                ));
+            }
+            FeatureGeometry::GeometryCollection {
+                geometries: children,
+            } => {
+                for child in children {
+                    child.flatten(geometries);
+                }
+            }
+        }
+    }
+}
+
+fn point(position: &Position) -> Option<[f64; 2]> {
+    match position[..] {
+        [x, y, ..] => Some([x, y]),
+        _ => None,
+    }
+}
+
+fn line(positions: &[Position]) -> Vec<[f64; 2]> {
+    positions.iter().filter_map(point).collect()
+}
+
+/// The first ring of a GeoJSON polygon is its exterior, the rest are holes.
+fn polygon(rings: Vec<Vec<Position>>) -> Option<Polygon> {
+    let mut rings = rings.iter().map(|ring| line(ring));
+
+    Some(Polygon {
+        exterior: rings.next()?,
+        interiors: rings.collect(),
+    })
+}
+
+/// Tag and colour of a feature. The colour is read from the object holding the tag,
+/// e.g. QuPath's `classification`.
+fn classify(properties: Option<&Value>, tag_path: &[&str]) -> (String, Option<String>) {
+    let Some((key, parent_path)) = tag_path.split_last() else {
+        return (UNCLASSIFIED.into(), None);
+    };
+
+    let parent = properties.and_then(|properties| {
+        parent_path
+            .iter()
+            .try_fold(properties, |value, key| value.get(key))
+    });
+
+    let Some(tag) = parent
+        .and_then(|parent| parent.get(key))
+        .and_then(Value::as_str)
+    else {
+        return (UNCLASSIFIED.into(), None);
+    };
+
+    (tag.into(), parent.and_then(colour))
+}
+
+/// QuPath stores colours as `color: [r, g, b]`, or as a packed `colorRGB` integer in older versions.
+fn colour(value: &Value) -> Option<String> {
+    let [r, g, b] = if let Some(rgb) = value.get("color").and_then(Value::as_array) {
+        let mut channels = rgb
+            .iter()
+            .map(|channel| channel.as_u64().map(|c| c.min(255)));
+        [channels.next()??, channels.next()??, channels.next()??]
+    } else {
+        let packed = value.get("colorRGB").and_then(Value::as_i64)?;
+        [(packed >> 16) & 0xFF, (packed >> 8) & 0xFF, packed & 0xFF].map(|channel| channel as u64)
+    };
+
+    Some(format!("#{r:02X}{g:02X}{b:02X}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::io::Write;
+
+    fn translate(document: &str) -> Vec<AnnotationLayer> {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(document.as_bytes()).unwrap();
+        Module.translate(file.path()).unwrap()
+    }
+
+    fn tag_path() -> Vec<&'static str> {
+        DEFAULT_TAG_PROPERTY.split('.').collect()
+    }
+
+    #[test]
+    fn reads_qupath_colour_arrays() {
+        let properties = json!({"classification": {"name": "Tumor", "color": [200, 0, 300]}});
+
+        let (tag, fill) = classify(Some(&properties), &tag_path());
+
+        assert_eq!(tag, "Tumor");
+        assert_eq!(fill.as_deref(), Some("#C800FF"));
+    }
+
+    #[test]
+    fn reads_packed_qupath_colours() {
+        // Older versions pack the colour with an opaque alpha, which makes it negative.
+        let properties = json!({"classification": {"name": "Stroma", "colorRGB": -16744448}});
+
+        let (tag, fill) = classify(Some(&properties), &tag_path());
+
+        assert_eq!(tag, "Stroma");
+        assert_eq!(fill.as_deref(), Some("#008000"));
+    }
+
+    #[test]
+    fn features_without_tag_are_unclassified() {
+        let properties = json!({"name": "Cell", "color": [255, 0, 0]});
+
+        assert_eq!(
+            classify(Some(&properties), &tag_path()),
+            (UNCLASSIFIED.to_string(), None)
+        );
+        assert_eq!(
+            classify(None, &tag_path()),
+            (UNCLASSIFIED.to_string(), None)
+        );
+    }
+
+    #[test]
+    fn flattens_multi_geometries() {
+        let layers = translate(
+            r#"{
+                "type": "FeatureCollection",
+                "features": [
+                    {
+                        "type": "Feature",
+                        "geometry": {"type": "MultiPoint", "coordinates": [[1, 2], [3, 4, 5]]},
+                        "properties": {"classification": {"name": "Cells"}, "score": 0.9}
+                    },
+                    {
+                        "type": "Feature",
+                        "geometry": {
+                            "type": "GeometryCollection",
+                            "geometries": [
+                                {"type": "MultiLineString", "coordinates": [[[0, 0], [1, 1]], [[2, 2], [3, 3]]]},
+                                {
+                                    "type": "Polygon",
+                                    "coordinates": [
+                                        [[0, 0], [10, 0], [10, 10], [0, 10], [0, 0]],
+                                        [[2, 2], [4, 2], [4, 4], [2, 2]]
+                                    ]
+                                }
+                            ]
+                        },
+                        "properties": {"classification": {"name": "Cells"}}
+                    },
+                    {"type": "Feature", "geometry": null, "properties": {}}
+                ]
+            }"#,
+        );
+
+        assert_eq!(layers.len(), 1);
+        let geometries: Vec<_> = layers[0]
+            .annotations
+            .iter()
+            .map(|annotation| &annotation.geometry)
+            .collect();
+        assert_eq!(geometries.len(), 5);
+        assert!(matches!(geometries[0], Geometry::Point([1.0, 2.0])));
+        assert!(matches!(geometries[1], Geometry::Point([3.0, 4.0])));
+        assert!(matches!(geometries[2], Geometry::LineString(line) if line.len() == 2));
+        assert!(matches!(geometries[3], Geometry::LineString(line) if line.len() == 2));
+        assert!(matches!(
+            geometries[4],
+            Geometry::Polygon(Polygon { exterior, interiors })
+                if exterior.len() == 5 && interiors.len() == 1
+        ));
+
+        // Parts keep the properties of their feature.
+        assert_eq!(layers[0].annotations[1].properties["score"], 0.9);
+    }
+
+    #[test]
+    fn reads_bare_feature_arrays() {
+        let layers = translate(
+            r#"[{"type": "Feature", "geometry": {"type": "Point", "coordinates": [1, 2]}, "properties": null}]"#,
+        );
+
+        assert_eq!(layers.len(), 1);
+        assert_eq!(layers[0].tag, UNCLASSIFIED);
+        assert_eq!(layers[0].annotations.len(), 1);
+    }
+}
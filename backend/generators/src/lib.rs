@@ -2,4 +2,8 @@
 mod common;
 pub mod export;
 
+mod aperio;
+mod asap;
+mod geojson;
 mod tiatoolbox;
+mod tissue;
@@ -1,10 +1,10 @@
 use crate::common::*;
 use flate2::read::ZlibDecoder;
 use geo_traits::to_geo::ToGeoGeometry;
-use geo_types::Geometry;
+use geo_types::{Geometry as WkbGeometry, LineString};
 use rusqlite::{Connection, Error};
-use serde::Deserialize;
-use shared::types::AnnotationLayers;
+use serde_json::Value;
+use shared::types::{Annotation, AnnotationLayers, Geometry, Polygon, Properties};
 use std::io::Read;
 use wkb::reader;
 
@@ -28,13 +28,13 @@ impl Generator for Module {
             ",
         )?;
 
-        let annotations = stmt.query_map([], |row| {
-            Ok(Annotation {
-                _cx: row.get(0)?,
-                _cy: row.get(1)?,
+        let records = stmt.query_map([], |row| {
+            Ok(Record {
+                cx: row.get(0)?,
+                cy: row.get(1)?,
                 geometry: parse_geometry(row.get(2)?)?,
                 properties: parse_properties(row.get(3)?)?,
-                _area: row.get(4)?,
+                area: row.get(4)?,
             })
         })?;
 
@@ -42,8 +42,32 @@ impl Generator for Module {
 
         let mut layers = AnnotationLayers::default();
 
-        annotations.filter_map(Result::ok).for_each(|annotation| {
-            layers.insert(annotation.properties.tag, annotation.geometry);
+        records.filter_map(Result::ok).for_each(|record| {
+            let Record {
+                cx,
+                cy,
+                geometry,
+                mut properties,
+                area,
+            } = record;
+
+            let Some(tag) = properties.get("type").and_then(Value::as_str) else {
+                return;
+            };
+            let tag = tag.to_string();
+
+            // Keep the columns TIAToolbox stores outside of the properties.
+            properties.entry("cx").or_insert(cx.into());
+            properties.entry("cy").or_insert(cy.into());
+            properties.entry("area").or_insert(area.into());
+
+            layers.insert(
+                tag,
+                Annotation {
+                    geometry,
+                    properties,
+                },
+            );
         });
 
         timer.end("Grouped annotations.");
@@ -52,18 +76,12 @@ impl Generator for Module {
     }
 }
 
-struct Annotation {
-    _cx: u32,
-    _cy: u32,
-    geometry: Vec<[f64; 2]>,
+struct Record {
+    cx: u32,
+    cy: u32,
+    geometry: Geometry,
     properties: Properties,
-    _area: f64,
-}
-
-#[derive(Deserialize)]
-struct Properties {
-    #[serde(rename = "type")]
-    tag: String,
+    area: f64,
 }
 
 fn parse_properties(properties: String) -> Result<Properties, rusqlite::Error> {
@@ -73,7 +91,7 @@ fn parse_properties(properties: String) -> Result<Properties, rusqlite::Error> {
     }
 }
 
-fn parse_geometry(geometry: Vec<u8>) -> Result<Vec<[f64; 2]>, rusqlite::Error> {
+fn parse_geometry(geometry: Vec<u8>) -> Result<Geometry, rusqlite::Error> {
     // Decompress zlib compressed geometry.
     let mut decoder = ZlibDecoder::new(&*geometry);
     let mut buf = Vec::new();
@@ -84,12 +102,32 @@ fn parse_geometry(geometry: Vec<u8>) -> Result<Vec<[f64; 2]>, rusqlite::Error> {
     };
 
     // Read geometry stored in well-known bytes format.
-    let Ok(Some(Geometry::Polygon(polygon))) = reader::read_wkb(&buf).map(|g| g.try_to_geometry())
-    else {
+    let Ok(Some(geometry)) = reader::read_wkb(&buf).map(|g| g.try_to_geometry()) else {
         return Err(Error::ToSqlConversionFailure("Failed to read wkb.".into()));
     };
 
-    let (exterior, _) = polygon.into_inner();
+    match geometry {
+        WkbGeometry::Point(point) => Ok(Geometry::Point([point.x(), point.y()])),
+        WkbGeometry::LineString(line) => Ok(Geometry::LineString(coordinates(&line))),
+        WkbGeometry::Polygon(polygon) => Ok(Geometry::Polygon(parse_polygon(polygon))),
+        WkbGeometry::MultiPolygon(polygons) => Ok(Geometry::MultiPolygon(
+            polygons.into_iter().map(parse_polygon).collect(),
+        )),
+        _ => Err(Error::ToSqlConversionFailure(
+            "Unsupported geometry type.".into(),
+        )),
+    }
+}
+
+fn parse_polygon(polygon: geo_types::Polygon) -> Polygon {
+    let (exterior, interiors) = polygon.into_inner();
+
+    Polygon {
+        exterior: coordinates(&exterior),
+        interiors: interiors.iter().map(coordinates).collect(),
+    }
+}
 
-    Ok(exterior.0.iter().map(|coord| [coord.x, coord.y]).collect())
+fn coordinates(line: &LineString) -> Vec<[f64; 2]> {
+    line.0.iter().map(|coord| [coord.x, coord.y]).collect()
 }
@@ -0,0 +1,377 @@
+use crate::common::*;
+use shared::types::{Annotation, AnnotationLayers, Geometry, Polygon, Size};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+// Longest side of the overview the tissue is detected on.
+const OVERVIEW_SIZE: u32 = 2048;
+// Regions and holes smaller than this many overview pixels are treated as noise.
+const MIN_AREA: usize = 64;
+const TAG: &str = "Tissue";
+
+pub struct Module;
+
+impl Generator for Module {
+    fn name(&self) -> &'static str {
+        "Tissue Detection"
+    }
+
+    fn generate(
+        &self,
+        _image_path: &Path,
+        decoder: &dyn Decoder,
+        progress: &dyn Fn(f64),
+        _log: &dyn Fn(&str),
+    ) -> Result<Vec<AnnotationLayer>> {
+        let mut timer = Timer::new("generators/tissue/generate");
+
+        let (width, height) = decoder.get_level_dimensions(0)?;
+        let overview = decoder.thumbnail(&Size {
+            width: OVERVIEW_SIZE,
+            height: OVERVIEW_SIZE,
+        })?;
+        let (columns, rows) = (overview.width() as usize, overview.height() as usize);
+
+        timer.lap("Read overview.");
+        progress(0.25);
+
+        let grey: Vec<u8> = overview.as_raw().chunks_exact(3).map(luminance).collect();
+
+        let mut layers = AnnotationLayers::default();
+
+        // A uniform overview has no tissue to separate from the background.
+        let Some(threshold) = otsu(&grey) else {
+            timer.end("Found no tissue.");
+            return Ok(layers.to_vec());
+        };
+
+        // Tissue is darker than the glass around it.
+        let mask: Vec<bool> = grey.iter().map(|&value| value <= threshold).collect();
+
+        timer.lap("Thresholded overview.");
+        progress(0.5);
+
+        // Overview pixels to level 0 pixels.
+        let scale_x = f64::from(width) / columns as f64;
+        let scale_y = f64::from(height) / rows as f64;
+        let scale = |ring: Vec<[f64; 2]>| -> Vec<[f64; 2]> {
+            ring.into_iter()
+                .map(|[x, y]| [x * scale_x, y * scale_y])
+                .collect()
+        };
+
+        for polygon in polygons(&mask, columns, rows) {
+            let polygon = Polygon {
+                exterior: scale(polygon.exterior),
+                interiors: polygon.interiors.into_iter().map(scale).collect(),
+            };
+            layers.insert(TAG.into(), Annotation::from(Geometry::Polygon(polygon)));
+        }
+
+        timer.end("Traced tissue regions.");
+
+        Ok(layers.to_vec())
+    }
+}
+
+fn luminance(rgb: &[u8]) -> u8 {
+    let [r, g, b] = [rgb[0], rgb[1], rgb[2]].map(u32::from);
+    ((299 * r + 587 * g + 114 * b) / 1000) as u8
+}
+
+/// Threshold maximising the variance between the values at or below it and those above,
+/// or `None` if every value is the same.
+fn otsu(values: &[u8]) -> Option<u8> {
+    let mut histogram = [0u64; 256];
+    for &value in values {
+        histogram[value as usize] += 1;
+    }
+
+    let total = values.len() as u64;
+    let sum: f64 = histogram
+        .iter()
+        .enumerate()
+        .map(|(value, &count)| value as f64 * count as f64)
+        .sum();
+
+    let (mut below, mut below_sum) = (0, 0.0);
+    let mut best: Option<(u8, f64)> = None;
+
+    for (value, &count) in histogram.iter().enumerate() {
+        below += count;
+        below_sum += value as f64 * count as f64;
+
+        let above = total - below;
+        if below == 0 {
+            continue;
+        }
+        if above == 0 {
+            break;
+        }
+
+        let mean_below = below_sum / below as f64;
+        let mean_above = (sum - below_sum) / above as f64;
+        let variance = below as f64 * above as f64 * (mean_below - mean_above).powi(2);
+
+        if best.is_none_or(|(_, best)| variance > best) {
+            best = Some((value as u8, variance));
+        }
+    }
+
+    best.map(|(threshold, _)| threshold)
+}
+
+/// Outlines of the 4-connected regions of the mask, traced along pixel edges, with their holes.
+fn polygons(mask: &[bool], columns: usize, rows: usize) -> Vec<Polygon> {
+    let (labels, sizes) = label(mask, columns, rows);
+    let filled = |x: usize, y: usize| x < columns && y < rows && mask[y * columns + x];
+
+    // Edges between region and background, directed clockwise around the region, so the
+    // region lies on their right. Vertices are pixel corners.
+    let mut edges: Vec<([usize; 2], [usize; 2], usize)> = Vec::new();
+    for y in 0..rows {
+        for x in 0..columns {
+            let Some(label) = labels[y * columns + x] else {
+                continue;
+            };
+            if sizes[label] < MIN_AREA {
+                continue;
+            }
+
+            if y == 0 || !filled(x, y - 1) {
+                edges.push(([x, y], [x + 1, y], label));
+            }
+            if !filled(x + 1, y) {
+                edges.push(([x + 1, y], [x + 1, y + 1], label));
+            }
+            if !filled(x, y + 1) {
+                edges.push(([x + 1, y + 1], [x, y + 1], label));
+            }
+            if x == 0 || !filled(x - 1, y) {
+                edges.push(([x, y + 1], [x, y], label));
+            }
+        }
+    }
+
+    let mut outgoing: HashMap<[usize; 2], Vec<usize>> = HashMap::new();
+    for (index, (start, _, _)) in edges.iter().enumerate() {
+        outgoing.entry(*start).or_default().push(index);
+    }
+
+    // Where two regions touch at a corner, the trace turns right so they stay apart.
+    let next = |index: usize| -> usize {
+        let (start, end, _) = edges[index];
+        let candidates = &outgoing[&end];
+        if candidates.len() == 1 {
+            return candidates[0];
+        }
+
+        let incoming = direction(start, end);
+        *candidates
+            .iter()
+            .find(|&&candidate| {
+                let (from, to, _) = edges[candidate];
+                let outgoing = direction(from, to);
+                incoming[0] * outgoing[1] - incoming[1] * outgoing[0] > 0
+            })
+            .unwrap_or(&candidates[0])
+    };
+
+    // Rings of each region, keeping only their corners.
+    let mut rings: BTreeMap<usize, Vec<Vec<[f64; 2]>>> = BTreeMap::new();
+    let mut visited = vec![false; edges.len()];
+    for first in 0..edges.len() {
+        if visited[first] {
+            continue;
+        }
+
+        let mut ring = Vec::new();
+        let mut current = first;
+        loop {
+            visited[current] = true;
+            let following = next(current);
+
+            let (start, end, _) = edges[current];
+            let (_, after, _) = edges[following];
+            if direction(start, end) != direction(end, after) {
+                ring.push([end[0] as f64, end[1] as f64]);
+            }
+
+            if following == first {
+                break;
+            }
+            current = following;
+        }
+
+        rings.entry(edges[first].2).or_default().push(ring);
+    }
+
+    let mut polygons = Vec::new();
+    for region in rings.into_values() {
+        let mut exterior = None;
+        let mut interiors = Vec::new();
+
+        // Exteriors wind clockwise, which is a positive area with y pointing down.
+        for ring in region {
+            let area = area(&ring);
+            if area > 0.0 {
+                exterior = Some(ring);
+            } else if -area >= MIN_AREA as f64 {
+                interiors.push(ring);
+            }
+        }
+
+        if let Some(exterior) = exterior {
+            polygons.push(Polygon {
+                exterior,
+                interiors,
+            });
+        }
+    }
+
+    polygons
+}
+
+/// Labels the 4-connected regions of the mask, returning the label of each pixel and
+/// the number of pixels of each region.
+fn label(mask: &[bool], columns: usize, rows: usize) -> (Vec<Option<usize>>, Vec<usize>) {
+    let mut labels = vec![None; mask.len()];
+    let mut sizes = Vec::new();
+    let mut queue = VecDeque::new();
+
+    for seed in 0..mask.len() {
+        if !mask[seed] || labels[seed].is_some() {
+            continue;
+        }
+
+        let label = sizes.len();
+        let mut size = 0;
+        labels[seed] = Some(label);
+        queue.push_back(seed);
+
+        while let Some(index) = queue.pop_front() {
+            size += 1;
+            let (x, y) = (index % columns, index / columns);
+
+            let neighbours = [
+                (x > 0).then(|| index - 1),
+                (x + 1 < columns).then(|| index + 1),
+                (y > 0).then(|| index - columns),
+                (y + 1 < rows).then(|| index + columns),
+            ];
+            for neighbour in neighbours.into_iter().flatten() {
+                if mask[neighbour] && labels[neighbour].is_none() {
+                    labels[neighbour] = Some(label);
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+
+        sizes.push(size);
+    }
+
+    (labels, sizes)
+}
+
+fn direction(from: [usize; 2], to: [usize; 2]) -> [isize; 2] {
+    [
+        to[0] as isize - from[0] as isize,
+        to[1] as isize - from[1] as isize,
+    ]
+}
+
+/// Signed area by the shoelace formula.
+fn area(ring: &[[f64; 2]]) -> f64 {
+    let doubled: f64 = ring
+        .iter()
+        .zip(ring.iter().cycle().skip(1))
+        .map(|([x0, y0], [x1, y1])| x0 * y1 - x1 * y0)
+        .sum();
+
+    doubled / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mask(columns: usize, rows: usize, filled: impl Fn(usize, usize) -> bool) -> Vec<bool> {
+        (0..rows)
+            .flat_map(|y| (0..columns).map(move |x| (x, y)))
+            .map(|(x, y)| filled(x, y))
+            .collect()
+    }
+
+    fn inside(x: usize, y: usize, from: usize, to: usize) -> bool {
+        (from..to).contains(&x) && (from..to).contains(&y)
+    }
+
+    #[test]
+    fn uniform_image_has_no_threshold() {
+        assert_eq!(otsu(&[128; 64]), None);
+        assert_eq!(otsu(&[]), None);
+    }
+
+    #[test]
+    fn threshold_separates_bimodal_values() {
+        let values: Vec<u8> = [10, 20, 30, 200, 210, 220].repeat(10);
+
+        let threshold = otsu(&values).unwrap();
+
+        assert!((30..200).contains(&threshold), "{threshold}");
+    }
+
+    #[test]
+    fn labels_four_connected_regions() {
+        // Two blocks touching only at a corner are separate regions.
+        let mask = mask(4, 4, |x, y| (x < 2 && y < 2) || (x >= 2 && y >= 2));
+
+        let (labels, sizes) = label(&mask, 4, 4);
+
+        assert_eq!(sizes, [4, 4]);
+        assert_eq!(labels[0], Some(0));
+        assert_eq!(labels[15], Some(1));
+        assert_eq!(labels[3], None);
+    }
+
+    #[test]
+    fn traces_region_with_hole() {
+        let mask = mask(20, 20, |x, y| inside(x, y, 1, 19) && !inside(x, y, 5, 15));
+
+        let polygons = polygons(&mask, 20, 20);
+
+        assert_eq!(polygons.len(), 1);
+        let polygon = &polygons[0];
+        assert_eq!(polygon.exterior.len(), 4);
+        assert_eq!(area(&polygon.exterior), 324.0);
+        assert_eq!(polygon.interiors.len(), 1);
+        assert_eq!(polygon.interiors[0].len(), 4);
+        assert_eq!(area(&polygon.interiors[0]), -100.0);
+    }
+
+    #[test]
+    fn keeps_regions_touching_at_corner_apart() {
+        let mask = mask(20, 20, |x, y| (x < 10 && y < 10) || (x >= 10 && y >= 10));
+
+        let polygons = polygons(&mask, 20, 20);
+
+        assert_eq!(polygons.len(), 2);
+        for polygon in &polygons {
+            assert_eq!(polygon.exterior.len(), 4);
+            assert_eq!(area(&polygon.exterior), 100.0);
+            assert!(polygon.interiors.is_empty());
+        }
+    }
+
+    #[test]
+    fn drops_noise_below_min_area() {
+        // A speck of 49 pixels, and a region with a hole of 9 pixels.
+        let mask = mask(20, 20, |x, y| {
+            inside(x, y, 1, 8) || (x >= 10 && !inside(x, y, 14, 17))
+        });
+
+        let polygons = polygons(&mask, 20, 20);
+
+        assert_eq!(polygons.len(), 1);
+        assert!(polygons[0].interiors.is_empty());
+    }
+}
@@ -1,5 +1,5 @@
 use crate::types::{AnnotationLayer, MetadataLayer, Region, Size};
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use image::{ImageBuffer, Rgb};
 use std::path::Path;
 
@@ -13,6 +13,10 @@ pub trait Decoder: Send + Sync {
     fn get_level_dimensions(&self, level: u32) -> Result<(u32, u32)>;
     fn read_region(&self, region: &Region) -> Result<Vec<u8>>;
     fn thumbnail(&self, size: &Size) -> Result<ImageBuffer<Rgb<u8>, Vec<u8>>>;
+    /// Physical size of a level 0 pixel, if the image records it.
+    fn get_microns_per_pixel(&self) -> Result<Option<f64>> {
+        Ok(None)
+    }
 }
 
 pub trait Encoder: Send + Sync {
@@ -27,9 +31,28 @@ pub trait Encoder: Send + Sync {
         x: u32,
         y: u32,
     ) -> Result<()>;
+    /// Reads an arbitrary rectangle of one level as interleaved RGB.
+    fn retrieve_region(&self, image_path: &Path, region: &Region) -> Result<Vec<u8>>;
 }
 
 pub trait Generator: Send + Sync {
     fn name(&self) -> &'static str;
-    fn translate(&self, annotations_path: &Path) -> Result<Vec<AnnotationLayer>>;
+    /// Reads annotations from a file produced by another tool.
+    fn translate(&self, _annotations_path: &Path) -> Result<Vec<AnnotationLayer>> {
+        Err(anyhow!(
+            "{} does not translate annotation files.",
+            self.name()
+        ))
+    }
+    /// Computes annotations from the image itself, reporting the fraction done as it goes.
+    /// Output of the tools it runs is passed to `log` a line at a time.
+    fn generate(
+        &self,
+        _image_path: &Path,
+        _decoder: &dyn Decoder,
+        _progress: &dyn Fn(f64),
+        _log: &dyn Fn(&str),
+    ) -> Result<Vec<AnnotationLayer>> {
+        Err(anyhow!("{} does not generate annotations.", self.name()))
+    }
 }
@@ -1,4 +1,5 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use std::collections::HashMap;
 
 pub struct Region {
@@ -17,50 +18,26 @@ pub struct Address {
     pub y: u32,
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Default, Serialize)]
 pub struct AnnotationLayers {
     count: usize,
     layers: HashMap<String, AnnotationLayer>,
-    colours: Vec<String>,
-}
-
-impl Default for AnnotationLayers {
-    fn default() -> Self {
-        Self {
-            count: 0,
-            layers: HashMap::new(),
-            colours: vec![
-                "#FF0000".into(), // Red
-                "#FF7F00".into(), // Orange
-                "#FFFF00".into(), // Yellow
-                "#0000FF".into(), // Blue
-                "#FF1493".into(), // DeepPink
-                "#4B0082".into(), // Indigo
-                "#8B00FF".into(), // Violet
-                "#00FF00".into(), // Green
-            ],
-        }
-    }
 }
 
 impl AnnotationLayers {
-    pub fn new(colours: Vec<String>) -> Self {
-        Self {
-            count: 0,
-            layers: HashMap::new(),
-            colours,
-        }
+    pub fn insert(&mut self, tag: String, annotation: Annotation) {
+        self.insert_with_fill(tag, None, annotation);
     }
 
-    pub fn insert(&mut self, tag: String, geometry: Vec<[f64; 2]>) {
+    /// Inserts into the layer for `tag`, creating it with the given fill if it is new.
+    pub fn insert_with_fill(&mut self, tag: String, fill: Option<String>, annotation: Annotation) {
         let layer = self.layers.entry(tag.clone()).or_insert_with(|| {
-            let fill = &self.colours[self.count % self.colours.len()];
-            let new_layer = AnnotationLayer::new(self.count, tag, fill.into());
+            let new_layer = AnnotationLayer::new(self.count, tag, fill);
             self.count += 1;
             new_layer
         });
 
-        layer.insert(geometry);
+        layer.insert(annotation);
     }
 
     pub fn to_vec(self) -> Vec<AnnotationLayer> {
@@ -74,13 +51,14 @@ pub struct AnnotationLayer {
     pub tag: String,
     pub visible: bool,
     pub opacity: f32,
-    pub fill: String,
+    // Layers without a fill take the next colour of their store's palette when saved.
+    pub fill: Option<String>,
     pub stroke: String,
-    pub annotations: Vec<Vec<[f64; 2]>>,
+    pub annotations: Vec<Annotation>,
 }
 
 impl AnnotationLayer {
-    pub fn new(id: usize, tag: String, fill: String) -> Self {
+    pub fn new(id: usize, tag: String, fill: Option<String>) -> Self {
         Self {
             id,
             tag,
@@ -92,8 +70,92 @@ impl AnnotationLayer {
         }
     }
 
-    pub fn insert(&mut self, geometry: Vec<[f64; 2]>) {
-        self.annotations.push(geometry);
+    pub fn insert(&mut self, annotation: Annotation) {
+        self.annotations.push(annotation);
+    }
+}
+
+/// Free-form properties of an annotation, e.g. class probabilities or measurements.
+pub type Properties = Map<String, Value>;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Annotation {
+    pub geometry: Geometry,
+    #[serde(default)]
+    pub properties: Properties,
+}
+
+impl From<Geometry> for Annotation {
+    fn from(geometry: Geometry) -> Self {
+        Self {
+            geometry,
+            properties: Properties::new(),
+        }
+    }
+}
+
+/// Geometry of an annotation, in level 0 pixel coordinates.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", content = "coordinates")]
+pub enum Geometry {
+    Point([f64; 2]),
+    LineString(Vec<[f64; 2]>),
+    Polygon(Polygon),
+    MultiPolygon(Vec<Polygon>),
+}
+
+impl Geometry {
+    /// Axis-aligned bounding box, or `None` if the geometry has no coordinates.
+    pub fn bounds(&self) -> Option<Bounds> {
+        let points: Box<dyn Iterator<Item = &[f64; 2]>> = match self {
+            Geometry::Point(point) => Box::new(std::iter::once(point)),
+            Geometry::LineString(points) => Box::new(points.iter()),
+            Geometry::Polygon(polygon) => Box::new(polygon.exterior.iter()),
+            Geometry::MultiPolygon(polygons) => {
+                Box::new(polygons.iter().flat_map(|polygon| polygon.exterior.iter()))
+            }
+        };
+
+        points.fold(None, |bounds: Option<Bounds>, &[x, y]| {
+            Some(match bounds {
+                Some(bounds) => Bounds {
+                    min_x: bounds.min_x.min(x),
+                    min_y: bounds.min_y.min(y),
+                    max_x: bounds.max_x.max(x),
+                    max_y: bounds.max_y.max(y),
+                },
+                None => Bounds {
+                    min_x: x,
+                    min_y: y,
+                    max_x: x,
+                    max_y: y,
+                },
+            })
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Bounds {
+    pub min_x: f64,
+    pub min_y: f64,
+    pub max_x: f64,
+    pub max_y: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Polygon {
+    pub exterior: Vec<[f64; 2]>,
+    pub interiors: Vec<Vec<[f64; 2]>>,
+}
+
+impl Polygon {
+    pub fn new(exterior: Vec<[f64; 2]>) -> Self {
+        Self {
+            exterior,
+            interiors: vec![],
+        }
     }
 }
 
@@ -176,7 +176,6 @@
 
         devRunScript = pkgs.writeShellScriptBin "dev" ''
           cd backend && cargo run & \
-          cd backend/geometry-computer && bun install & \
           cd frontend && bun install && bun run dev
         '';
       in
@@ -1,7 +1,7 @@
 import { ASSET_URL } from '$constants';
 import { request, defined } from '$helpers';
 import type { UploaderOptions } from '$types';
-import type { Geometry2DLayer } from '$view/Geometry2D/types';
+import type { Geometry2DLayer, Geometry2DRegion } from '$view/Geometry2D/types';
 import type { Image2DLayer } from '$view/Image2D/types';
 import { GLTFLoader, type GLTF } from 'three/addons/loaders/GLTFLoader.js';
 
@@ -25,8 +25,33 @@ export async function thumbnail(storeId: number, id: number): Promise<HTMLImageE
 	return image;
 }
 
-export async function geometry2d(storeId: number, id: number, layerId: number): Promise<GLTF> {
-	return await gltfLoader.loadAsync(`${ASSET_URL}/${storeId}/${id}/annotations/${layerId}`);
+// Annotations of a layer within a region, simplified for the level it is drawn at.
+export async function geometry2d(
+	storeId: number,
+	id: number,
+	layerId: number,
+	region: Geometry2DRegion
+): Promise<GLTF> {
+	const query = new URLSearchParams(
+		Object.entries(region).map(([key, value]) => [key, `${value}`])
+	);
+	return await gltfLoader.loadAsync(
+		`${ASSET_URL}/${storeId}/${id}/annotations/${layerId}/region?${query}`
+	);
+}
+
+// Layer styles are shared, so changes are saved for everyone viewing the image.
+export async function updateLayer(storeId: number, id: number, layer: Geometry2DLayer) {
+	await request.patch({
+		url: `${ASSET_URL}/${storeId}/${id}/layers/${layer.id}`,
+		body: {
+			visible: layer.visible,
+			opacity: layer.opacity,
+			fill: layer.fill,
+			stroke: layer.stroke
+		},
+		type: 'json'
+	});
 }
 
 export async function remove(storeId: number, id: number, mode: 'soft' | 'hard') {
@@ -1,78 +1,137 @@
 import {
 	WEBSOCKET_URL,
-	S_ERROR_TAG,
-	S_DIRECTORY_CREATE_TAG,
-	S_DIRECTORY_DELETE_TAG,
-	S_DIRECTORY_MOVE_TAG,
-	S_DIRECTORY_RENAME_TAG,
-	S_DIRECTORY_TAG,
-	S_TILE_TAG
+	PROTOCOL_VERSION,
+	TILE_FORMAT_MIME_TYPES,
+	encodeClientMsg,
+	decodeServerMsg,
+	type ClientMsg
 } from '$constants';
 import { views, registry } from '$states';
-import type { Image2DView } from '$view/Image2D/types';
 
 let socket: WebSocket;
+// Stores to receive directory updates for, renewed on every connection.
+const subscriptions = new Set<number>();
 
-export function send(data: Uint8Array): boolean {
-	if (socket.readyState !== WebSocket.OPEN) return false;
-	socket.send(data);
+export function send(msg: ClientMsg): boolean {
+	if (socket?.readyState !== WebSocket.OPEN) return false;
+	socket.send(encodeClientMsg(msg));
 	return true;
 }
 
+// Views showing the image a message relates to.
+function viewsOf(storeId: number, imageId: number) {
+	return views.filter((view) => view.state.storeId === storeId && view.state.id === imageId);
+}
+
 async function receive(event: MessageEvent) {
-	const data = new Uint8Array(await event.data.arrayBuffer());
-	const dataView = new DataView(data.buffer);
+	const msg = decodeServerMsg(await event.data.arrayBuffer());
 
-	switch (dataView.getUint8(0)) {
-		case S_ERROR_TAG:
-			console.log('Error');
+	switch (msg.type) {
+		case 'Error': {
+			const { requestId, error, id, message } = msg.payload;
+			console.error(`${error} (${id}) ${message}`, { requestId });
 			break;
-		case S_TILE_TAG:
-			const storeId = dataView.getUint32(1);
-			const id = dataView.getUint32(5);
-			const level = dataView.getUint32(9);
-			const x = dataView.getUint32(13);
-			const y = dataView.getUint32(17);
-			const tile = data.slice(29);
+		}
+		case 'Tile': {
+			const { storeId, imageId, level, x, y, format, buffer } = msg.payload;
+			const type = TILE_FORMAT_MIME_TYPES[format.type];
 
-			views[0].state.insertTile(level, x, y, tile);
+			// Route the tile to every view showing the image it belongs to.
+			for (const view of viewsOf(storeId, imageId)) {
+				view.state.insertTile(level, x, y, buffer, type);
+			}
 			break;
-		case S_DIRECTORY_TAG:
-			switch (dataView.getUint8(1)) {
-				case S_DIRECTORY_CREATE_TAG: {
-					const storeId = dataView.getUint32(6);
-					const parentId = dataView.getUint32(10);
-					const id = dataView.getUint32(14);
-					const name = new TextDecoder().decode(data.slice(26)); // Skip 8 bytes encoding length.
-
-					registry.add('Directory', storeId, parentId, id, name);
+		}
+		case 'Hello':
+		case 'Capabilities':
+			break;
+		case 'Resync':
+			// Directory updates were missed, so the store trees may be stale.
+			registry.load();
+			break;
+		case 'Directory': {
+			const directory = msg.payload;
+			switch (directory.type) {
+				case 'Create':
+					registry.add(
+						'Directory',
+						directory.storeId,
+						directory.parentId,
+						directory.id,
+						directory.name
+					);
 					break;
-				}
-				case S_DIRECTORY_DELETE_TAG: {
-					const storeId = dataView.getUint32(6);
-					const id = dataView.getUint32(10);
-
-					registry.delete(storeId, id);
+				case 'Delete':
+					registry.delete(directory.storeId, directory.id);
 					break;
-				}
-				case S_DIRECTORY_MOVE_TAG: {
-					const storeId = dataView.getUint32(6);
-					const id = dataView.getUint32(10);
-					const destinationId = dataView.getUint32(14);
-
-					registry.move(storeId, id, destinationId);
+				case 'Move':
+					registry.move(directory.storeId, directory.id, directory.destinationId);
 					break;
-				}
-				case S_DIRECTORY_RENAME_TAG: {
+				case 'Rename':
 					console.log('TODO: Implement rename.');
 					break;
+			}
+			break;
+		}
+		case 'Annotation': {
+			// Every annotation edit changes the geometry of its layer.
+			const { storeId, imageId, layerId } = msg.payload;
+
+			for (const view of viewsOf(storeId, imageId)) {
+				view.state.reloadGeometry(layerId);
+			}
+			break;
+		}
+		case 'AnnotationLayer': {
+			const layer = msg.payload;
+
+			for (const view of viewsOf(layer.storeId, layer.imageId)) {
+				switch (layer.type) {
+					case 'Create':
+					case 'Update': {
+						const { id, tag, fill, visible, opacity, stroke } = layer;
+						// Attached files can add annotations to a layer that already exists.
+						if (layer.type === 'Create') view.state.reloadGeometry(id);
+						view.state.upsertGeometry({ id, tag, fill, visible, opacity, stroke });
+						break;
+					}
+					case 'Delete':
+						view.state.removeGeometry(layer.id);
+						break;
 				}
 			}
 			break;
+		}
+		case 'Generation': {
+			const { storeId, imageId, progress, generator } = msg.payload;
+
+			for (const view of viewsOf(storeId, imageId)) {
+				// Generators report 1 once they have finished, successfully or not.
+				view.state.generation = progress < 1 ? { generator, progress } : undefined;
+			}
+			break;
+		}
 	}
 }
 
+function hello() {
+	send({ type: 'Hello', payload: { version: PROTOCOL_VERSION } });
+
+	subscriptions.forEach((storeId) => send({ type: 'Subscribe', payload: { storeId } }));
+}
+
+export function subscribe(storeId: number) {
+	subscriptions.add(storeId);
+	send({ type: 'Subscribe', payload: { storeId } });
+}
+
+export function unsubscribe(storeId: number) {
+	subscriptions.delete(storeId);
+	send({ type: 'Unsubscribe', payload: { storeId } });
+}
+
 export function connect() {
 	socket = new WebSocket(WEBSOCKET_URL);
+	socket.addEventListener('open', hello);
 	socket.addEventListener('message', receive);
 }
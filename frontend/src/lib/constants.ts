@@ -11,12 +11,12 @@ export const ASSET_URL = HTTP_BASE_URL + '/api/image';
 export const STORE_URL = HTTP_BASE_URL + '/api/store';
 export const WEBSOCKET_URL = WEBSOCKET_BASE_URL + '/api/websocket';
 
-export const C_TILE_TAG = 0;
+// Largest annotation region the server returns, in pixels of the level it is drawn at.
+export const MAX_REGION_SIZE = 8192;
+// Annotations are fetched for half a viewport around it, so panning does not reveal gaps.
+export const ANNOTATION_REGION_MARGIN = 0.5;
+// Milliseconds the view must settle for before annotations are fetched for it.
+export const ANNOTATION_REGION_DELAY = 200;
 
-export const S_ERROR_TAG = 0;
-export const S_TILE_TAG = 1;
-export const S_DIRECTORY_TAG = 2;
-export const S_DIRECTORY_CREATE_TAG = 0;
-export const S_DIRECTORY_DELETE_TAG = 1;
-export const S_DIRECTORY_MOVE_TAG = 2;
-export const S_DIRECTORY_RENAME_TAG = 3;
+// Message tags and tile formats are generated from backend/core/protocol.json.
+export * from './protocol.ts';
@@ -0,0 +1,717 @@
+// Auto-generated file from backend/core/protocol.json. Any changes will be overwritten.
+
+export const PROTOCOL_VERSION = 1;
+export const MIN_PROTOCOL_VERSION = 1;
+
+export const C_TILE_TAG = 0;
+export const C_VIEWPORT_TAG = 1;
+export const C_CANCEL_TAG = 2;
+export const C_FORMAT_TAG = 3;
+export const C_HELLO_TAG = 4;
+export const C_SUBSCRIBE_TAG = 5;
+export const C_UNSUBSCRIBE_TAG = 6;
+
+export const S_ERROR_TAG = 0;
+export const S_TILE_TAG = 1;
+export const S_DIRECTORY_TAG = 2;
+export const S_CAPABILITIES_TAG = 3;
+export const S_RESYNC_TAG = 4;
+export const S_HELLO_TAG = 5;
+export const S_ANNOTATION_TAG = 6;
+export const S_ANNOTATION_LAYER_TAG = 7;
+export const S_GENERATION_TAG = 8;
+export const S_DIRECTORY_CREATE_TAG = 0;
+export const S_DIRECTORY_DELETE_TAG = 1;
+export const S_DIRECTORY_MOVE_TAG = 2;
+export const S_DIRECTORY_RENAME_TAG = 3;
+export const S_ANNOTATION_CREATE_TAG = 0;
+export const S_ANNOTATION_UPDATE_TAG = 1;
+export const S_ANNOTATION_DELETE_TAG = 2;
+export const S_ANNOTATION_LAYER_CREATE_TAG = 0;
+export const S_ANNOTATION_LAYER_UPDATE_TAG = 1;
+export const S_ANNOTATION_LAYER_DELETE_TAG = 2;
+
+export interface TileClientMsg {
+	requestId: number;
+	storeId: number;
+	imageId: number;
+	level: number;
+	x: number;
+	y: number;
+	/** Overrides the connection's tile format for this request only. */
+	format?: TileFormat;
+}
+
+export interface ViewportClientMsg {
+	requestId: number;
+	storeId: number;
+	imageId: number;
+	level: number;
+	bounds: ViewportBounds;
+	/** Overrides the connection's tile format for this request only. */
+	format?: TileFormat;
+}
+
+export type ViewportBounds =
+	/** Inclusive range of tile indices at the requested level. */
+	| { type: 'Tiles'; xStart: number; yStart: number; xEnd: number; yEnd: number }
+	/** Bounding box in level 0 pixel coordinates. */
+	| { type: 'Region'; x: number; y: number; width: number; height: number };
+
+/** Drops queued tile requests for an image, or only one of its levels. */
+export interface CancelClientMsg {
+	storeId: number;
+	imageId: number;
+	level?: number;
+}
+
+/** Sets the tile format used for the rest of the connection. */
+export interface FormatClientMsg {
+	format: TileFormat;
+}
+
+/** Starts receiving directory updates for a store. */
+export interface SubscribeClientMsg {
+	storeId: number;
+}
+
+/** Stops receiving directory updates for a store. */
+export interface UnsubscribeClientMsg {
+	storeId: number;
+}
+
+/** Must be the first message on a connection. */
+export interface HelloClientMsg {
+	/** Newest protocol version the client speaks. */
+	version: number;
+}
+
+export type TileFormat =
+	| { type: 'Jpeg'; quality: number; subsampling: Subsampling }
+	/** Lossless. */
+	| { type: 'Png' }
+	/** Lossless. */
+	| { type: 'WebP' }
+	/** Uncompressed interleaved RGB bytes. */
+	| { type: 'Raw' }
+	/** Lossless. */
+	| { type: 'Tiff' };
+
+export type Subsampling =
+	/** 4:4:4, no chroma subsampling. */
+	| 'Sub1x1'
+	/** 4:2:2. */
+	| 'Sub2x1'
+	/** 4:2:0. */
+	| 'Sub2x2';
+
+/** Failure of a client message, identified by its `log::Error` category and id. */
+export interface ErrorServerMsg {
+	/** Request that failed, if the error relates to one. */
+	requestId?: number;
+	error: string;
+	id: string;
+	message: string;
+}
+
+export interface TileServerMsg {
+	requestId: number;
+	storeId: number;
+	imageId: number;
+	level: number;
+	x: number;
+	y: number;
+	format: TileFormat;
+	buffer: Uint8Array;
+}
+
+/** Protocol version both sides will use for the rest of the connection. */
+export interface HelloServerMsg {
+	version: number;
+}
+
+/** Sent once the handshake completes. */
+export interface CapabilitiesServerMsg {
+	formats: string[];
+	defaultFormat: TileFormat;
+}
+
+export type DirectoryServerMsg =
+	| { type: 'Create'; storeId: number; parentId: number; id: number; name: string }
+	| { type: 'Delete'; storeId: number; id: number }
+	| { type: 'Move'; storeId: number; id: number; destinationId: number }
+	| { type: 'Rename'; storeId: number; id: number; name: string };
+
+/**
+ * Edit to the annotations of an image.
+ * Geometry and properties are JSON, as returned by the annotations query.
+ */
+export type AnnotationServerMsg =
+	| {
+			type: 'Create';
+			storeId: number;
+			imageId: number;
+			layerId: number;
+			id: number;
+			geometry: string;
+			properties: string;
+	  }
+	| {
+			type: 'Update';
+			storeId: number;
+			imageId: number;
+			layerId: number;
+			id: number;
+			geometry: string;
+			properties: string;
+	  }
+	| { type: 'Delete'; storeId: number; imageId: number; layerId: number; id: number };
+
+export type AnnotationLayerServerMsg =
+	| {
+			type: 'Create';
+			storeId: number;
+			imageId: number;
+			id: number;
+			tag: string;
+			fill: string;
+			visible: boolean;
+			opacity: number;
+			stroke: string;
+	  }
+	| {
+			type: 'Update';
+			storeId: number;
+			imageId: number;
+			id: number;
+			tag: string;
+			fill: string;
+			visible: boolean;
+			opacity: number;
+			stroke: string;
+	  }
+	| { type: 'Delete'; storeId: number; imageId: number; id: number };
+
+export interface GenerationServerMsg {
+	storeId: number;
+	imageId: number;
+	progress: number;
+	generator: string;
+}
+
+export type ClientMsg =
+	| { type: 'Tile'; payload: TileClientMsg }
+	| { type: 'Viewport'; payload: ViewportClientMsg }
+	| { type: 'Cancel'; payload: CancelClientMsg }
+	| { type: 'Format'; payload: FormatClientMsg }
+	/** Opens the handshake. Its tag must never change between versions. */
+	| { type: 'Hello'; payload: HelloClientMsg }
+	| { type: 'Subscribe'; payload: SubscribeClientMsg }
+	| { type: 'Unsubscribe'; payload: UnsubscribeClientMsg };
+
+export type ServerMsg =
+	| { type: 'Error'; payload: ErrorServerMsg }
+	| { type: 'Tile'; payload: TileServerMsg }
+	| { type: 'Directory'; payload: DirectoryServerMsg }
+	| { type: 'Capabilities'; payload: CapabilitiesServerMsg }
+	/** Sent when broadcasts were dropped; the client should refetch its store trees. */
+	| { type: 'Resync' }
+	/** Completes the handshake. Its tag must never change between versions. */
+	| { type: 'Hello'; payload: HelloServerMsg }
+	| { type: 'Annotation'; payload: AnnotationServerMsg }
+	| { type: 'AnnotationLayer'; payload: AnnotationLayerServerMsg }
+	/** Progress of an annotation generator, with 1 once it has finished or failed. */
+	| { type: 'Generation'; payload: GenerationServerMsg };
+
+export const TILE_FORMAT_MIME_TYPES: Record<TileFormat['type'], string> = {
+	Jpeg: 'image/jpeg',
+	Png: 'image/png',
+	WebP: 'image/webp',
+	Raw: 'application/octet-stream',
+	Tiff: 'image/tiff'
+};
+
+const encoder = new TextEncoder();
+const decoder = new TextDecoder();
+
+// Messages are encoded by bincode with big-endian fixed-size integers. Enum variants are
+// sent as a u32 index, options with a u8 tag, and strings and lists with a u64 length.
+class Writer {
+	private data = new Uint8Array(64);
+	private view = new DataView(this.data.buffer);
+	private offset = 0;
+
+	private reserve(length: number): number {
+		const offset = this.offset;
+		if (offset + length > this.data.length) {
+			const data = new Uint8Array(Math.max(this.data.length * 2, offset + length));
+			data.set(this.data);
+			this.data = data;
+			this.view = new DataView(data.buffer);
+		}
+		this.offset += length;
+		return offset;
+	}
+
+	u8(value: number) {
+		this.view.setUint8(this.reserve(1), value);
+	}
+
+	u32(value: number) {
+		this.view.setUint32(this.reserve(4), value);
+	}
+
+	u64(value: bigint) {
+		this.view.setBigUint64(this.reserve(8), value);
+	}
+
+	f32(value: number) {
+		this.view.setFloat32(this.reserve(4), value);
+	}
+
+	f64(value: number) {
+		this.view.setFloat64(this.reserve(8), value);
+	}
+
+	bool(value: boolean) {
+		this.u8(value ? 1 : 0);
+	}
+
+	bytes(value: Uint8Array) {
+		this.u64(BigInt(value.length));
+		const offset = this.reserve(value.length);
+		this.data.set(value, offset);
+	}
+
+	string(value: string) {
+		this.bytes(encoder.encode(value));
+	}
+
+	option<T>(value: T | undefined, write: (value: T) => void) {
+		this.u8(value === undefined ? 0 : 1);
+		if (value !== undefined) write(value);
+	}
+
+	vec<T>(values: T[], write: (value: T) => void) {
+		this.u64(BigInt(values.length));
+		values.forEach((value) => write(value));
+	}
+
+	finish(): Uint8Array {
+		return this.data.slice(0, this.offset);
+	}
+}
+
+class Reader {
+	private offset = 0;
+
+	constructor(private view: DataView) {}
+
+	private advance(length: number): number {
+		const offset = this.offset;
+		if (offset + length > this.view.byteLength) throw new RangeError('Message is truncated.');
+		this.offset += length;
+		return offset;
+	}
+
+	// Every value takes at least a byte, so longer lengths can only come from a corrupt message.
+	private length(): number {
+		const length = this.u64();
+		if (length > BigInt(this.view.byteLength - this.offset)) {
+			throw new RangeError('Message is truncated.');
+		}
+		return Number(length);
+	}
+
+	u8(): number {
+		return this.view.getUint8(this.advance(1));
+	}
+
+	u32(): number {
+		return this.view.getUint32(this.advance(4));
+	}
+
+	u64(): bigint {
+		return this.view.getBigUint64(this.advance(8));
+	}
+
+	f32(): number {
+		return this.view.getFloat32(this.advance(4));
+	}
+
+	f64(): number {
+		return this.view.getFloat64(this.advance(8));
+	}
+
+	bool(): boolean {
+		return this.u8() !== 0;
+	}
+
+	bytes(): Uint8Array {
+		const length = this.length();
+		const offset = this.advance(length);
+		return new Uint8Array(this.view.buffer, this.view.byteOffset + offset, length);
+	}
+
+	string(): string {
+		return decoder.decode(this.bytes());
+	}
+
+	option<T>(read: () => T): T | undefined {
+		return this.u8() === 0 ? undefined : read();
+	}
+
+	vec<T>(read: () => T): T[] {
+		return Array.from({ length: this.length() }, () => read());
+	}
+}
+
+function writeTileClientMsg(writer: Writer, value: TileClientMsg) {
+	writer.u32(value.requestId);
+	writer.u32(value.storeId);
+	writer.u32(value.imageId);
+	writer.u32(value.level);
+	writer.u32(value.x);
+	writer.u32(value.y);
+	writer.option(value.format, (item) => writeTileFormat(writer, item));
+}
+
+function writeViewportClientMsg(writer: Writer, value: ViewportClientMsg) {
+	writer.u32(value.requestId);
+	writer.u32(value.storeId);
+	writer.u32(value.imageId);
+	writer.u32(value.level);
+	writeViewportBounds(writer, value.bounds);
+	writer.option(value.format, (item) => writeTileFormat(writer, item));
+}
+
+function writeViewportBounds(writer: Writer, value: ViewportBounds) {
+	switch (value.type) {
+		case 'Tiles':
+			writer.u32(0);
+			writer.u32(value.xStart);
+			writer.u32(value.yStart);
+			writer.u32(value.xEnd);
+			writer.u32(value.yEnd);
+			break;
+		case 'Region':
+			writer.u32(1);
+			writer.u32(value.x);
+			writer.u32(value.y);
+			writer.u32(value.width);
+			writer.u32(value.height);
+			break;
+	}
+}
+
+function writeCancelClientMsg(writer: Writer, value: CancelClientMsg) {
+	writer.u32(value.storeId);
+	writer.u32(value.imageId);
+	writer.option(value.level, (item) => writer.u32(item));
+}
+
+function writeFormatClientMsg(writer: Writer, value: FormatClientMsg) {
+	writeTileFormat(writer, value.format);
+}
+
+function writeSubscribeClientMsg(writer: Writer, value: SubscribeClientMsg) {
+	writer.u32(value.storeId);
+}
+
+function writeUnsubscribeClientMsg(writer: Writer, value: UnsubscribeClientMsg) {
+	writer.u32(value.storeId);
+}
+
+function writeHelloClientMsg(writer: Writer, value: HelloClientMsg) {
+	writer.u32(value.version);
+}
+
+function writeTileFormat(writer: Writer, value: TileFormat) {
+	switch (value.type) {
+		case 'Jpeg':
+			writer.u32(0);
+			writer.u8(value.quality);
+			writeSubsampling(writer, value.subsampling);
+			break;
+		case 'Png':
+			writer.u32(1);
+			break;
+		case 'WebP':
+			writer.u32(2);
+			break;
+		case 'Raw':
+			writer.u32(3);
+			break;
+		case 'Tiff':
+			writer.u32(4);
+			break;
+	}
+}
+
+function writeSubsampling(writer: Writer, value: Subsampling) {
+	switch (value) {
+		case 'Sub1x1':
+			writer.u32(0);
+			break;
+		case 'Sub2x1':
+			writer.u32(1);
+			break;
+		case 'Sub2x2':
+			writer.u32(2);
+			break;
+	}
+}
+
+function readTileFormat(reader: Reader): TileFormat {
+	const index = reader.u32();
+	switch (index) {
+		case 0:
+			return {
+				type: 'Jpeg',
+				quality: reader.u8(),
+				subsampling: readSubsampling(reader)
+			};
+		case 1:
+			return { type: 'Png' };
+		case 2:
+			return { type: 'WebP' };
+		case 3:
+			return { type: 'Raw' };
+		case 4:
+			return { type: 'Tiff' };
+		default:
+			throw new RangeError(`Invalid TileFormat variant ${index}.`);
+	}
+}
+
+function readSubsampling(reader: Reader): Subsampling {
+	const index = reader.u32();
+	switch (index) {
+		case 0:
+			return 'Sub1x1';
+		case 1:
+			return 'Sub2x1';
+		case 2:
+			return 'Sub2x2';
+		default:
+			throw new RangeError(`Invalid Subsampling variant ${index}.`);
+	}
+}
+
+function readErrorServerMsg(reader: Reader): ErrorServerMsg {
+	return {
+		requestId: reader.option(() => reader.u32()),
+		error: reader.string(),
+		id: reader.string(),
+		message: reader.string()
+	};
+}
+
+function readTileServerMsg(reader: Reader): TileServerMsg {
+	return {
+		requestId: reader.u32(),
+		storeId: reader.u32(),
+		imageId: reader.u32(),
+		level: reader.u32(),
+		x: reader.u32(),
+		y: reader.u32(),
+		format: readTileFormat(reader),
+		buffer: reader.bytes()
+	};
+}
+
+function readHelloServerMsg(reader: Reader): HelloServerMsg {
+	return { version: reader.u32() };
+}
+
+function readCapabilitiesServerMsg(reader: Reader): CapabilitiesServerMsg {
+	return { formats: reader.vec(() => reader.string()), defaultFormat: readTileFormat(reader) };
+}
+
+function readDirectoryServerMsg(reader: Reader): DirectoryServerMsg {
+	const index = reader.u32();
+	switch (index) {
+		case 0:
+			return {
+				type: 'Create',
+				storeId: reader.u32(),
+				parentId: reader.u32(),
+				id: reader.u32(),
+				name: reader.string()
+			};
+		case 1:
+			return {
+				type: 'Delete',
+				storeId: reader.u32(),
+				id: reader.u32()
+			};
+		case 2:
+			return {
+				type: 'Move',
+				storeId: reader.u32(),
+				id: reader.u32(),
+				destinationId: reader.u32()
+			};
+		case 3:
+			return {
+				type: 'Rename',
+				storeId: reader.u32(),
+				id: reader.u32(),
+				name: reader.string()
+			};
+		default:
+			throw new RangeError(`Invalid DirectoryServerMsg variant ${index}.`);
+	}
+}
+
+function readAnnotationServerMsg(reader: Reader): AnnotationServerMsg {
+	const index = reader.u32();
+	switch (index) {
+		case 0:
+			return {
+				type: 'Create',
+				storeId: reader.u32(),
+				imageId: reader.u32(),
+				layerId: reader.u32(),
+				id: reader.u32(),
+				geometry: reader.string(),
+				properties: reader.string()
+			};
+		case 1:
+			return {
+				type: 'Update',
+				storeId: reader.u32(),
+				imageId: reader.u32(),
+				layerId: reader.u32(),
+				id: reader.u32(),
+				geometry: reader.string(),
+				properties: reader.string()
+			};
+		case 2:
+			return {
+				type: 'Delete',
+				storeId: reader.u32(),
+				imageId: reader.u32(),
+				layerId: reader.u32(),
+				id: reader.u32()
+			};
+		default:
+			throw new RangeError(`Invalid AnnotationServerMsg variant ${index}.`);
+	}
+}
+
+function readAnnotationLayerServerMsg(reader: Reader): AnnotationLayerServerMsg {
+	const index = reader.u32();
+	switch (index) {
+		case 0:
+			return {
+				type: 'Create',
+				storeId: reader.u32(),
+				imageId: reader.u32(),
+				id: reader.u32(),
+				tag: reader.string(),
+				fill: reader.string(),
+				visible: reader.bool(),
+				opacity: reader.f32(),
+				stroke: reader.string()
+			};
+		case 1:
+			return {
+				type: 'Update',
+				storeId: reader.u32(),
+				imageId: reader.u32(),
+				id: reader.u32(),
+				tag: reader.string(),
+				fill: reader.string(),
+				visible: reader.bool(),
+				opacity: reader.f32(),
+				stroke: reader.string()
+			};
+		case 2:
+			return {
+				type: 'Delete',
+				storeId: reader.u32(),
+				imageId: reader.u32(),
+				id: reader.u32()
+			};
+		default:
+			throw new RangeError(`Invalid AnnotationLayerServerMsg variant ${index}.`);
+	}
+}
+
+function readGenerationServerMsg(reader: Reader): GenerationServerMsg {
+	return {
+		storeId: reader.u32(),
+		imageId: reader.u32(),
+		progress: reader.f64(),
+		generator: reader.string()
+	};
+}
+
+export function encodeClientMsg(msg: ClientMsg): Uint8Array {
+	const writer = new Writer();
+	switch (msg.type) {
+		case 'Tile':
+			writer.u8(C_TILE_TAG);
+			writeTileClientMsg(writer, msg.payload);
+			break;
+		case 'Viewport':
+			writer.u8(C_VIEWPORT_TAG);
+			writeViewportClientMsg(writer, msg.payload);
+			break;
+		case 'Cancel':
+			writer.u8(C_CANCEL_TAG);
+			writeCancelClientMsg(writer, msg.payload);
+			break;
+		case 'Format':
+			writer.u8(C_FORMAT_TAG);
+			writeFormatClientMsg(writer, msg.payload);
+			break;
+		case 'Hello':
+			writer.u8(C_HELLO_TAG);
+			writeHelloClientMsg(writer, msg.payload);
+			break;
+		case 'Subscribe':
+			writer.u8(C_SUBSCRIBE_TAG);
+			writeSubscribeClientMsg(writer, msg.payload);
+			break;
+		case 'Unsubscribe':
+			writer.u8(C_UNSUBSCRIBE_TAG);
+			writeUnsubscribeClientMsg(writer, msg.payload);
+			break;
+	}
+	return writer.finish();
+}
+
+export function decodeServerMsg(data: ArrayBuffer): ServerMsg {
+	const reader = new Reader(new DataView(data));
+	const tag = reader.u8();
+	switch (tag) {
+		case S_ERROR_TAG:
+			return { type: 'Error', payload: readErrorServerMsg(reader) };
+		case S_TILE_TAG:
+			return { type: 'Tile', payload: readTileServerMsg(reader) };
+		case S_DIRECTORY_TAG:
+			// The subtag repeats the variant index of the payload.
+			reader.u8();
+			return { type: 'Directory', payload: readDirectoryServerMsg(reader) };
+		case S_CAPABILITIES_TAG:
+			return { type: 'Capabilities', payload: readCapabilitiesServerMsg(reader) };
+		case S_RESYNC_TAG:
+			return { type: 'Resync' };
+		case S_HELLO_TAG:
+			return { type: 'Hello', payload: readHelloServerMsg(reader) };
+		case S_ANNOTATION_TAG:
+			// The subtag repeats the variant index of the payload.
+			reader.u8();
+			return { type: 'Annotation', payload: readAnnotationServerMsg(reader) };
+		case S_ANNOTATION_LAYER_TAG:
+			// The subtag repeats the variant index of the payload.
+			reader.u8();
+			return { type: 'AnnotationLayer', payload: readAnnotationLayerServerMsg(reader) };
+		case S_GENERATION_TAG:
+			return { type: 'Generation', payload: readGenerationServerMsg(reader) };
+		default:
+			throw new RangeError(`Invalid message tag ${tag}.`);
+	}
+}
@@ -1,4 +1,4 @@
-import { http } from '$api';
+import { http, websocket } from '$api';
 import type { Directory, Store, Asset } from '$types';
 import { defined } from '$helpers';
 import { SvelteMap } from 'svelte/reactivity';
@@ -98,24 +98,31 @@ export class Registry {
 		});
 	}
 
+	/** Fetches every store and its tree, replacing what is currently held. */
+	load() {
+		http.registry().then((registry) => {
+			if (!defined(registry)) return;
+			this.#registry = new SvelteMap<number, Store>();
+			this.#stores.clear();
+			registry.forEach((store) => {
+				websocket.subscribe(store.id);
+				http.store.get(store.id).then((root) => {
+					if (!defined(root)) return;
+					this.#registry!.set(store.id, store);
+					const rootMap = new SvelteMap<number, Directory | Asset>();
+					root.forEach((item) => {
+						rootMap.set(item.id, item);
+					});
+					this.#stores.set(store.id, rootMap);
+				});
+			});
+		});
+	}
+
 	constructor() {
 		$effect.root(() => {
 			$effect(() => {
-				http.registry().then((registry) => {
-					if (!defined(registry)) return;
-					this.#registry = new SvelteMap<number, Store>();
-					registry.forEach((store) => {
-						http.store.get(store.id).then((root) => {
-							if (!defined(root)) return;
-							this.#registry!.set(store.id, store);
-							const rootMap = new SvelteMap<number, Directory | Asset>();
-							root.forEach((item) => {
-								rootMap.set(item.id, item);
-							});
-							this.#stores.set(store.id, rootMap);
-						});
-					});
-				});
+				this.load();
 			});
 		});
 	}
@@ -5,4 +5,15 @@ export type Geometry2DLayer = {
 	opacity: number;
 	fill: string;
 	stroke: string;
+	// Bumped when the layer is edited elsewhere, so its geometry is fetched again.
+	revision?: number;
+};
+
+// Rectangle in level 0 pixels, and the level annotations within it are drawn at.
+export type Geometry2DRegion = {
+	x: number;
+	y: number;
+	width: number;
+	height: number;
+	level: number;
 };
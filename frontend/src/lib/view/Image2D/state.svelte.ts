@@ -1,10 +1,10 @@
-import { C_TILE_TAG } from '$constants';
 import { http, websocket } from '$api';
+import { ANNOTATION_REGION_DELAY, ANNOTATION_REGION_MARGIN, MAX_REGION_SIZE } from '$constants';
 import { defined } from '$helpers';
 import { views } from '$states';
 import { Transformer } from './transformer.svelte.ts';
 import type { Image2DLayer } from './types.ts';
-import type { Geometry2DLayer } from '$view/Geometry2D/types.ts';
+import type { Geometry2DLayer, Geometry2DRegion } from '$view/Geometry2D/types.ts';
 
 class Image2DState {
 	width: number;
@@ -12,7 +12,13 @@ class Image2DState {
 	levels: number;
 	layers: Image2DLayer[] = $state([]);
 	geometries: Geometry2DLayer[] = $state([]);
+	// Part of the image annotations are fetched for, following the viewport.
+	region: Geometry2DRegion | undefined = $state();
+	#regionTimeout: ReturnType<typeof setTimeout> | undefined;
+	// Annotation generator currently running on the image.
+	generation: { generator: string; progress: number } | undefined = $state();
 	transformer: Transformer;
+	requestId = 0;
 
 	constructor(
 		public storeId: number,
@@ -42,27 +48,75 @@ class Image2DState {
 		this.transformer = new Transformer(layers);
 	}
 
-	// TODO: Cleanup. Should not need to know how to format websocket msgs here.
 	async getTile(level: number, x: number, y: number): Promise<boolean> {
-		const buffer = new ArrayBuffer(1 + 5 * 4);
-		const view = new DataView(buffer);
-
-		view.setUint8(0, C_TILE_TAG);
-		view.setUint32(1, this.storeId);
-		view.setUint32(5, this.id);
-		view.setUint32(9, level);
-		view.setUint32(13, x);
-		view.setUint32(17, y);
-
-		return websocket.send(new Uint8Array(buffer));
+		// Tiles use the connection's format, so none is given.
+		return websocket.send({
+			type: 'Tile',
+			payload: { requestId: this.requestId++, storeId: this.storeId, imageId: this.id, level, x, y }
+		});
 	}
 
-	async insertTile(level: number, x: number, y: number, tile: Uint8Array) {
+	async insertTile(level: number, x: number, y: number, tile: Uint8Array, type: string) {
 		const newTile = new Image();
-		const blob = new Blob([tile], { type: 'image/jpeg' });
+		const blob = new Blob([tile], { type });
 		newTile.src = URL.createObjectURL(blob);
 		this.layers[level].tiles[y][x] = newTile;
 	}
+
+	// Fetches annotations for the viewport once panning and zooming have settled.
+	updateRegion() {
+		clearTimeout(this.#regionTimeout);
+		this.#regionTimeout = setTimeout(() => {
+			this.region = this.#viewport();
+		}, ANNOTATION_REGION_DELAY);
+	}
+
+	#viewport(): Geometry2DRegion {
+		const { offsetX, offsetY, scale } = this.transformer;
+		const level = this.transformer.currentLevel ?? 0;
+		const [image, layer] = [this.layers[0], this.layers[level]];
+
+		// The image spans the width of the screen at a scale of 1.
+		const pixels = (window.innerWidth * scale) / image.width;
+		const centreX = (window.innerWidth / 2 - offsetX) / pixels;
+		const centreY = (window.innerHeight / 2 - offsetY) / pixels;
+
+		// Regions are capped at the resolution of the level they are drawn at.
+		const limit = Math.floor((MAX_REGION_SIZE * image.width) / layer.width);
+		const size = 1 + 2 * ANNOTATION_REGION_MARGIN;
+		const width = Math.min(Math.ceil((window.innerWidth / pixels) * size), limit);
+		const height = Math.min(Math.ceil((window.innerHeight / pixels) * size), limit);
+
+		const x = Math.min(Math.max(Math.floor(centreX - width / 2), 0), image.width);
+		const y = Math.min(Math.max(Math.floor(centreY - height / 2), 0), image.height);
+
+		return {
+			x,
+			y,
+			width: Math.max(Math.min(width, image.width - x), 1),
+			height: Math.max(Math.min(height, image.height - y), 1),
+			level
+		};
+	}
+
+	// Annotation edits broadcast by the server.
+	reloadGeometry(id: number) {
+		const layer = this.geometries.find((layer) => layer.id === id);
+		if (defined(layer)) layer.revision = (layer.revision ?? 0) + 1;
+	}
+
+	upsertGeometry(style: Geometry2DLayer) {
+		const layer = this.geometries.find((layer) => layer.id === style.id);
+		if (defined(layer)) {
+			Object.assign(layer, style);
+		} else {
+			this.geometries.push(style);
+		}
+	}
+
+	removeGeometry(id: number) {
+		this.geometries = this.geometries.filter((layer) => layer.id !== id);
+	}
 }
 
 export async function load(storeId: number, parentId: number, id: number, name: string) {